@@ -0,0 +1,58 @@
+#[cfg(feature = "std")]
+use crate::error::Error;
+use crate::error::Result;
+
+/*
+Authenticated encryption with associated data.
+
+A failed decryption never exposes plaintext and leaves the buffer holding the ciphertext,
+implementations either check the tag before decrypting or restore the ciphertext on failure.
+*/
+pub trait Aead {
+    // The length of the tags this instance produces and accepts, in bytes.
+    fn tag_len(&self) -> usize;
+
+    // Encrypts buffer in place and writes the tag, which must be tag_len bytes long.
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()>;
+
+    // Checks the tag and decrypts buffer in place.
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()>;
+
+    // Returns the ciphertext followed by the tag.
+    #[cfg(feature = "std")]
+    fn encrypt(&self, nonce: &[u8], associated_data: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut out = plaintext.to_vec();
+        let mut tag = vec![0; self.tag_len()];
+        self.encrypt_in_place(nonce, associated_data, &mut out, &mut tag)?;
+        out.extend(tag);
+        Ok(out)
+    }
+
+    // Takes the ciphertext followed by the tag, and returns the plaintext if the tag is valid.
+    #[cfg(feature = "std")]
+    fn decrypt(&self, nonce: &[u8], associated_data: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < self.tag_len() {
+            return Err(Error::InvalidLength {
+                expected: self.tag_len(),
+                found: sealed.len(),
+            });
+        }
+
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - self.tag_len());
+        let mut out = ciphertext.to_vec();
+        self.decrypt_in_place(nonce, associated_data, &mut out, tag)?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,166 @@
+use crate::byte_order::ByteOrder;
+use crate::rc5::Rc5;
+use crate::rc5_fixed::Rc5Fixed;
+use crate::uint::UInt;
+
+// The largest block of any cipher in the crate, RC5 with 256-bit words.
+// Modes use it to size their stack buffers.
+pub const MAX_BLOCK_SIZE: usize = 64;
+
+/*
+Block cipher traits, so modes of operation, MACs and padding can be written once
+and used with any cipher in the crate.
+*/
+pub trait BlockCipher {
+    // The size of a block in bytes.
+    const BLOCK_SIZE: usize;
+}
+
+pub trait BlockEncrypt: BlockCipher {
+    // Encrypts a single block in place, block must be exactly BLOCK_SIZE bytes long.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    // Encrypts each block of data independently, data must be a whole number of blocks.
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        assert_eq!(data.len() % Self::BLOCK_SIZE, 0, "invalid block size");
+        for block in data.chunks_exact_mut(Self::BLOCK_SIZE) {
+            self.encrypt_block(block);
+        }
+    }
+}
+
+pub trait BlockDecrypt: BlockCipher {
+    // Decrypts a single block in place, block must be exactly BLOCK_SIZE bytes long.
+    fn decrypt_block(&self, block: &mut [u8]);
+
+    // Decrypts each block of data independently, data must be a whole number of blocks.
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        assert_eq!(data.len() % Self::BLOCK_SIZE, 0, "invalid block size");
+        for block in data.chunks_exact_mut(Self::BLOCK_SIZE) {
+            self.decrypt_block(block);
+        }
+    }
+}
+
+impl<T, const R: usize, O> BlockCipher for Rc5<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    const BLOCK_SIZE: usize = 2 * T::BYTES;
+}
+
+impl<T, const R: usize, O> BlockEncrypt for Rc5<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    fn encrypt_block(&self, block: &mut [u8]) {
+        Rc5::encrypt_block(self, block)
+    }
+}
+
+impl<T, const R: usize, O> BlockDecrypt for Rc5<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    fn decrypt_block(&self, block: &mut [u8]) {
+        Rc5::decrypt_block(self, block)
+    }
+}
+
+impl<T, const R: usize, O> BlockCipher for Rc5Fixed<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    const BLOCK_SIZE: usize = 2 * T::BYTES;
+}
+
+impl<T, const R: usize, O> BlockEncrypt for Rc5Fixed<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    fn encrypt_block(&self, block: &mut [u8]) {
+        Rc5Fixed::encrypt_block(self, block)
+    }
+}
+
+impl<T, const R: usize, O> BlockDecrypt for Rc5Fixed<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    fn decrypt_block(&self, block: &mut [u8]) {
+        Rc5Fixed::decrypt_block(self, block)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::custom_uint::{U256, U64};
+
+    // A cipher that leaves every block unchanged, to test code written against the traits.
+    struct Identity;
+
+    impl BlockCipher for Identity {
+        const BLOCK_SIZE: usize = 4;
+    }
+
+    impl BlockEncrypt for Identity {
+        fn encrypt_block(&self, block: &mut [u8]) {
+            assert_eq!(block.len(), Self::BLOCK_SIZE);
+        }
+    }
+
+    impl BlockDecrypt for Identity {
+        fn decrypt_block(&self, block: &mut [u8]) {
+            assert_eq!(block.len(), Self::BLOCK_SIZE);
+        }
+    }
+
+    fn round_trip<C: BlockEncrypt + BlockDecrypt>(cipher: &C, data: &[u8]) -> Vec<u8> {
+        let mut buf = data.to_vec();
+        cipher.encrypt_blocks(&mut buf);
+        let ciphertext = buf.clone();
+        cipher.decrypt_blocks(&mut buf);
+        assert_eq!(buf, data);
+        ciphertext
+    }
+
+    #[test]
+    fn block_sizes() {
+        assert_eq!(<Rc5<u8> as BlockCipher>::BLOCK_SIZE, 2);
+        assert_eq!(<Rc5<u32> as BlockCipher>::BLOCK_SIZE, 8);
+        assert_eq!(<Rc5<U64> as BlockCipher>::BLOCK_SIZE, 16);
+        assert_eq!(<Rc5Fixed<u128, 28> as BlockCipher>::BLOCK_SIZE, 32);
+        assert_eq!(<Rc5<U256> as BlockCipher>::BLOCK_SIZE, MAX_BLOCK_SIZE);
+    }
+
+    #[test]
+    fn identity_cipher() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(round_trip(&Identity, &data), data);
+    }
+
+    #[test]
+    fn rc5_through_traits() {
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D,
+            0x0E, 0x0F,
+        ];
+        let mut rc5: Rc5<u32> = Rc5::new(12, key.len()).unwrap();
+        rc5.setup(&key);
+
+        let data = [
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+            0x66, 0x77,
+        ];
+        let ct = round_trip(&rc5, &data);
+        assert_eq!(ct[..8], [0x2D, 0xDC, 0x14, 0x9B, 0xCF, 0x08, 0x8B, 0x9E]);
+        assert_eq!(ct[..8], ct[8..]);
+    }
+}
@@ -0,0 +1,126 @@
+/*
+The order in which the bytes of a word are laid out in memory.
+
+RC5 as specified loads key and block words little-endian, which is the default everywhere.
+BigEndian is for interoperating with implementations that pack words most significant byte first.
+*/
+pub trait ByteOrder {
+    // Whether the most significant byte of a word comes first.
+    const BIG_ENDIAN: bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LittleEndian;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BigEndian;
+
+impl ByteOrder for LittleEndian {
+    const BIG_ENDIAN: bool = false;
+}
+
+impl ByteOrder for BigEndian {
+    const BIG_ENDIAN: bool = true;
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::custom_uint::{U32, U80};
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::rc5_fixed::Rc5Fixed;
+    use crate::uint::UInt;
+
+    fn encrypt<T: UInt, O: ByteOrder>(rounds: u8, key: &str, pt: &str) -> Vec<u8> {
+        let key = decode_hex(key).unwrap();
+        let mut rc5: Rc5<T, 255, O> = Rc5::new(rounds, key.len()).unwrap();
+        rc5.setup(&key);
+
+        let mut block = decode_hex(pt).unwrap();
+        rc5.encrypt_block(&mut block);
+
+        let mut decrypted = block.clone();
+        rc5.decrypt_block(&mut decrypted);
+        assert_eq!(decrypted, decode_hex(pt).unwrap());
+
+        block
+    }
+
+    #[test]
+    fn words() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+        assert_eq!(u32::from_bytes::<LittleEndian>(&mut &bytes[..]), 0x04030201);
+        assert_eq!(u32::from_bytes::<BigEndian>(&mut &bytes[..]), 0x01020304);
+        assert_eq!(U32::from_bytes::<BigEndian>(&bytes).to_u128(), 0x01020304);
+
+        let mut out = [0; 10];
+        U80::from_u128(0x0102).write_bytes::<BigEndian>(&mut out);
+        assert_eq!(out, [0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x02]);
+        U80::from_u128(0x0102).write_bytes::<LittleEndian>(&mut out);
+        assert_eq!(out, [0x02, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn little_endian_is_the_default() {
+        let ct = encrypt::<u32, LittleEndian>(
+            12,
+            "000102030405060708090A0B0C0D0E0F",
+            "0011223344556677",
+        );
+        assert_eq!(ct, decode_hex("2DDC149BCF088B9E").unwrap());
+    }
+
+    // The RC5-32/12/16 vector above with every word of the key and block byte swapped.
+    #[test]
+    fn big_endian() {
+        let key = "03020100070605040B0A09080F0E0D0C";
+        let pt = "3322110077665544";
+        let ct = decode_hex("9B14DC2D9E8B08CF").unwrap();
+
+        assert_eq!(encrypt::<u32, BigEndian>(12, key, pt), ct);
+        assert_eq!(encrypt::<U32, BigEndian>(12, key, pt), ct);
+
+        let key = decode_hex(key).unwrap();
+        let mut fixed: Rc5Fixed<u32, 12, BigEndian> = Rc5Fixed::new(key.len()).unwrap();
+        fixed.setup(&key);
+        let mut block = decode_hex(pt).unwrap();
+        fixed.encrypt_block(&mut block);
+        assert_eq!(block, ct);
+    }
+
+    #[test]
+    fn big_endian_rc5_64() {
+        let le = encrypt::<u64, LittleEndian>(
+            24,
+            "000102030405060708090A0B0C0D0E0F1011121314151617",
+            "000102030405060708090A0B0C0D0E0F",
+        );
+        assert_eq!(le, decode_hex("A46772820EDBCE0235ABEA32AE7178DA").unwrap());
+
+        let be = encrypt::<u64, BigEndian>(
+            24,
+            "07060504030201000F0E0D0C0B0A09081716151413121110",
+            "07060504030201000F0E0D0C0B0A0908",
+        );
+        assert_eq!(be, decode_hex("02CEDB0E827267A4DA7871AE32EAAB35").unwrap());
+    }
+
+    // A key that doesn't fill its last word is padded after the key bytes in both orders.
+    #[test]
+    fn partial_key_word() {
+        let le = encrypt::<u32, LittleEndian>(12, "0001020304", "0011223344556677");
+        let be = encrypt::<u32, BigEndian>(12, "0302010004", "3322110077665544");
+        assert_ne!(swap_words(&le), be);
+
+        let padded = encrypt::<u32, BigEndian>(12, "0302010004000000", "3322110077665544");
+        assert_eq!(be, padded);
+    }
+
+    fn swap_words(bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .chunks(4)
+            .flat_map(|w| w.iter().rev().copied())
+            .collect()
+    }
+}
@@ -0,0 +1,265 @@
+use crate::block_cipher::{BlockDecrypt, BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::error::{Error, Result};
+use crate::mac::xor;
+
+/*
+CBC mode with PKCS#7 padding, as used by RC5-CBC-Pad (RFC 2040) and most file formats.
+
+Padding always adds 1 to BLOCK_SIZE bytes, each holding the number of bytes added.
+CBC on its own doesn't authenticate anything: a wrong key or a corrupted ciphertext
+usually shows up as InvalidPadding, but can also decrypt to garbage with valid padding.
+*/
+pub struct Cbc<'a, C> {
+    cipher: &'a C,
+}
+
+impl<'a, C> Cbc<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C) -> Cbc<'a, C> {
+        Self { cipher }
+    }
+
+    // The ciphertext length for a message of msg_len bytes.
+    pub fn padded_len(msg_len: usize) -> usize {
+        (msg_len / C::BLOCK_SIZE + 1) * C::BLOCK_SIZE
+    }
+
+    /*
+    Encrypts the first msg_len bytes of buffer, padding them in place, and returns the
+    ciphertext length. The buffer must have room for padded_len(msg_len) bytes.
+    */
+    pub fn encrypt_padded_in_place(
+        &self,
+        iv: &[u8],
+        buffer: &mut [u8],
+        msg_len: usize,
+    ) -> Result<usize> {
+        let n = C::BLOCK_SIZE;
+        check_iv(iv, n)?;
+        let padded_len = Self::padded_len(msg_len);
+        if buffer.len() < padded_len {
+            return Err(Error::BufferTooSmall { needed: padded_len });
+        }
+
+        let pad = (padded_len - msg_len) as u8;
+        for byte in &mut buffer[msg_len..padded_len] {
+            *byte = pad;
+        }
+
+        let mut previous = [0; MAX_BLOCK_SIZE];
+        previous[..n].copy_from_slice(iv);
+        for block in buffer[..padded_len].chunks_exact_mut(n) {
+            xor(block, &previous[..n]);
+            self.cipher.encrypt_block(block);
+            previous[..n].copy_from_slice(block);
+        }
+        Ok(padded_len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encrypt_padded(&self, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = plaintext.to_vec();
+        buffer.resize(Self::padded_len(plaintext.len()), 0);
+        self.encrypt_padded_in_place(iv, &mut buffer, plaintext.len())?;
+        Ok(buffer)
+    }
+}
+
+impl<'a, C> Cbc<'a, C>
+where
+    C: BlockDecrypt,
+{
+    // Decrypts buffer in place and returns the length of the plaintext left at its start.
+    pub fn decrypt_padded_in_place(&self, iv: &[u8], buffer: &mut [u8]) -> Result<usize> {
+        let n = C::BLOCK_SIZE;
+        check_iv(iv, n)?;
+        if buffer.is_empty() || !buffer.len().is_multiple_of(n) {
+            return Err(Error::InvalidLength {
+                expected: (buffer.len() / n + 1) * n,
+                found: buffer.len(),
+            });
+        }
+
+        let mut previous = [0; MAX_BLOCK_SIZE];
+        previous[..n].copy_from_slice(iv);
+        for block in buffer.chunks_exact_mut(n) {
+            let mut saved = [0; MAX_BLOCK_SIZE];
+            saved[..n].copy_from_slice(block);
+            self.cipher.decrypt_block(block);
+            xor(block, &previous[..n]);
+            previous = saved;
+        }
+
+        let pad = unpad(&buffer[buffer.len() - n..])?;
+        Ok(buffer.len() - pad)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decrypt_padded(&self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = ciphertext.to_vec();
+        let len = self.decrypt_padded_in_place(iv, &mut buffer)?;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+}
+
+fn check_iv(iv: &[u8], n: usize) -> Result<()> {
+    if iv.len() != n {
+        return Err(Error::InvalidLength {
+            expected: n,
+            found: iv.len(),
+        });
+    }
+    Ok(())
+}
+
+// The padding length of the last block, checked without branching on its bytes.
+fn unpad(last: &[u8]) -> Result<usize> {
+    let n = last.len();
+    let pad = last[n - 1] as usize;
+
+    let mut bad = (pad == 0) as u8 | (pad > n) as u8;
+    for (i, &byte) in last.iter().enumerate() {
+        // Only the last pad bytes have to match.
+        let in_pad = (i >= n - pad.min(n)) as u8;
+        bad |= in_pad & (byte != pad as u8) as u8;
+    }
+
+    if bad != 0 {
+        return Err(Error::InvalidPadding);
+    }
+    Ok(pad)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::testing::{bytes, rc5, Aes};
+
+    // SP 800-38A F.2.1, the padding adds a fifth block.
+    #[test]
+    fn aes_sp_800_38a() {
+        let aes = Aes::new(&decode_hex("2B7E151628AED2A6ABF7158809CF4F3C").unwrap());
+        let cbc = Cbc::new(&aes);
+        let iv = bytes(0, 16);
+        let plaintext = decode_hex(
+            "6BC1BEE22E409F96E93D7E117393172AAE2D8A571E03AC9C9EB76FAC45AF8E51\
+             30C81C46A35CE411E5FBC1191A0A52EFF69F2445DF4F9B17AD2B417BE66C3710",
+        )
+        .unwrap();
+        let ciphertext = cbc.encrypt_padded(&iv, &plaintext).unwrap();
+        assert_eq!(
+            ciphertext[..64],
+            decode_hex(
+                "7649ABAC8119B246CEE98E9B12E9197D5086CB9B507219EE95DB113A917678B2\
+                 73BED6B8E3C1743B7116E69E222295163FF1CAA1681FAC09120ECA307586E1A7"
+            )
+            .unwrap()[..]
+        );
+        assert_eq!(ciphertext.len(), 80);
+        assert_eq!(cbc.decrypt_padded(&iv, &ciphertext).unwrap(), plaintext);
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py cbc`.
+    #[test]
+    fn known_answers() {
+        let rc5_32 = rc5::<u32>(12, 16);
+        let cbc = Cbc::new(&rc5_32);
+        let iv = bytes(100, 8);
+        for (len, expected) in [
+            (0, "870AD98E9A7D9368"),
+            (5, "2A7545CCA2F98358"),
+            (16, "93097EFD3049DA48B2609C06D0C74BDC64D5F5FCD57E1C35"),
+        ]
+        .iter()
+        {
+            let ciphertext = cbc.encrypt_padded(&iv, &bytes(0, *len)).unwrap();
+            assert_eq!(ciphertext, decode_hex(expected).unwrap());
+            assert_eq!(
+                cbc.decrypt_padded(&iv, &ciphertext).unwrap(),
+                bytes(0, *len)
+            );
+        }
+
+        let rc5_64 = rc5::<u64>(24, 16);
+        let cbc = Cbc::new(&rc5_64);
+        let iv = bytes(100, 16);
+        let ciphertext = cbc.encrypt_padded(&iv, &bytes(0, 37)).unwrap();
+        assert_eq!(
+            ciphertext,
+            decode_hex(
+                "58FA31E234BDBFEB64D85BADE16C55A7D8A6ABA293F2ED01\
+                 C671985F9D85D46E67E1C12D1E469579C51A27F01054422E"
+            )
+            .unwrap()
+        );
+        assert_eq!(cbc.decrypt_padded(&iv, &ciphertext).unwrap(), bytes(0, 37));
+    }
+
+    #[test]
+    fn round_trip_lengths() {
+        let cipher = rc5::<u16>(16, 8);
+        let cbc = Cbc::new(&cipher);
+        let iv = bytes(7, 4);
+        for len in 0..20 {
+            let plaintext = bytes(0, len);
+            let ciphertext = cbc.encrypt_padded(&iv, &plaintext).unwrap();
+            assert_eq!(ciphertext.len(), Cbc::<Rc5<u16>>::padded_len(len));
+            assert!(ciphertext.len() > len);
+            assert_eq!(cbc.decrypt_padded(&iv, &ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn invalid_padding() {
+        let cipher = rc5::<u32>(12, 16);
+        let cbc = Cbc::new(&cipher);
+        let iv = bytes(0, 8);
+
+        // Valid blocks with bad padding, encrypted without padding of their own.
+        for last in [
+            [1, 2, 3, 4, 5, 6, 7, 0],
+            [1, 2, 3, 4, 5, 6, 7, 9],
+            [1, 2, 3, 4, 5, 3, 2, 3],
+        ]
+        .iter()
+        {
+            let mut ciphertext = last.to_vec();
+            xor(&mut ciphertext, &iv);
+            cipher.encrypt_block(&mut ciphertext);
+            assert!(matches!(
+                cbc.decrypt_padded(&iv, &ciphertext),
+                Err(Error::InvalidPadding)
+            ));
+        }
+
+        assert!(matches!(
+            cbc.decrypt_padded(&iv, &[0; 12]),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 12
+            })
+        ));
+        assert!(matches!(
+            cbc.decrypt_padded(&iv, &[]),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            cbc.encrypt_padded(&iv[..4], b"abc"),
+            Err(Error::InvalidLength {
+                expected: 8,
+                found: 4
+            })
+        ));
+
+        let mut buffer = [0; 8];
+        assert!(matches!(
+            cbc.encrypt_padded_in_place(&iv, &mut buffer, 8),
+            Err(Error::BufferTooSmall { needed: 16 })
+        ));
+    }
+}
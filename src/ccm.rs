@@ -0,0 +1,452 @@
+use crate::aead::Aead;
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::constant_time::ct_eq;
+use crate::ctr::apply_keystream;
+use crate::error::{Error, Result};
+use crate::mac::xor;
+
+/*
+Counter with CBC-MAC, formatted as in RFC 3610 with the block size taken from the cipher.
+
+The length field takes length_size (L) bytes of the counter block, which leaves
+BLOCK_SIZE - 1 - L bytes for the nonce and limits messages to 2^(8L) - 1 bytes.
+With 128-bit blocks such as Rc5<u64> this is exactly RFC 3610, with 64-bit blocks
+such as Rc5<u32> and L = 2 the nonce is 5 bytes.
+
+Tags are tag_len (M) bytes, an even number from 4 up to the block size, and at most 16.
+*/
+pub struct Ccm<'a, C> {
+    cipher: &'a C,
+    tag_len: usize,
+    length_size: usize,
+}
+
+impl<'a, C> Ccm<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C, tag_len: usize, length_size: usize) -> Result<Ccm<'a, C>> {
+        let n = C::BLOCK_SIZE;
+        // M is stored as (M - 2) / 2 in three bits of the flags byte.
+        if !(4..=16).contains(&tag_len) || tag_len > n || !tag_len.is_multiple_of(2) {
+            return Err(Error::InvalidTagLength(tag_len));
+        }
+        // L is stored as L - 1 in three bits, and the nonce needs at least one byte.
+        if !(2..=8).contains(&length_size) || length_size + 2 > n {
+            return Err(Error::InvalidLengthFieldSize(length_size));
+        }
+
+        Ok(Self {
+            cipher,
+            tag_len,
+            length_size,
+        })
+    }
+
+    // The nonce length in bytes, BLOCK_SIZE - 1 - L.
+    pub fn nonce_len(&self) -> usize {
+        C::BLOCK_SIZE - 1 - self.length_size
+    }
+
+    // A_0 = flags || nonce || 0, the counter block that encrypts the tag.
+    fn initial_counter(&self, nonce: &[u8]) -> Result<[u8; MAX_BLOCK_SIZE]> {
+        if nonce.len() != self.nonce_len() {
+            return Err(Error::InvalidNonceLength(nonce.len()));
+        }
+
+        let mut counter = [0; MAX_BLOCK_SIZE];
+        counter[0] = (self.length_size - 1) as u8;
+        counter[1..1 + nonce.len()].copy_from_slice(nonce);
+        Ok(counter)
+    }
+
+    // Checks the nonce and that the message length fits in L bytes, and returns A_0.
+    fn start(&self, nonce: &[u8], message_len: usize) -> Result<[u8; MAX_BLOCK_SIZE]> {
+        let l = self.length_size;
+        if l < 8 && message_len as u64 >> (8 * l) != 0 {
+            return Err(Error::MessageTooLong);
+        }
+        self.initial_counter(nonce)
+    }
+
+    // The CBC-MAC over B_0, the encoded associated data and the plaintext.
+    fn mac(&self, nonce: &[u8], associated_data: &[u8], plaintext: &[u8]) -> [u8; MAX_BLOCK_SIZE] {
+        let n = C::BLOCK_SIZE;
+        let l = self.length_size;
+
+        // B_0 = flags || nonce || l(m)
+        let mut b0 = [0; MAX_BLOCK_SIZE];
+        b0[0] = (((self.tag_len - 2) / 2) << 3 | (l - 1)) as u8;
+        if !associated_data.is_empty() {
+            b0[0] |= 0x40;
+        }
+        b0[1..1 + nonce.len()].copy_from_slice(nonce);
+        b0[n - l..n].copy_from_slice(&(plaintext.len() as u64).to_be_bytes()[8 - l..]);
+
+        let mut chain = CbcChain::new(self.cipher);
+        chain.update(&b0[..n]);
+
+        if !associated_data.is_empty() {
+            let len = associated_data.len() as u64;
+            let mut encoded = [0; 10];
+            let encoded_len = if len < 0xFF00 {
+                encoded[..2].copy_from_slice(&(len as u16).to_be_bytes());
+                2
+            } else if len <= u32::MAX as u64 {
+                encoded[..2].copy_from_slice(&[0xFF, 0xFE]);
+                encoded[2..6].copy_from_slice(&(len as u32).to_be_bytes());
+                6
+            } else {
+                encoded[..2].copy_from_slice(&[0xFF, 0xFF]);
+                encoded[2..].copy_from_slice(&len.to_be_bytes());
+                10
+            };
+            chain.update(&encoded[..encoded_len]);
+            chain.update(associated_data);
+            chain.pad();
+        }
+
+        chain.update(plaintext);
+        chain.pad();
+        chain.state
+    }
+
+    // Encrypts the CBC-MAC with S_0 and writes the first tag_len bytes.
+    fn encrypt_tag(&self, counter: &[u8; MAX_BLOCK_SIZE], mac: &mut [u8; MAX_BLOCK_SIZE]) {
+        let n = C::BLOCK_SIZE;
+        let mut s0 = *counter;
+        self.cipher.encrypt_block(&mut s0[..n]);
+        xor(&mut mac[..n], &s0[..n]);
+    }
+
+    // CTR over the data, starting with A_1.
+    fn apply_keystream(&self, counter: &[u8; MAX_BLOCK_SIZE], data: &mut [u8]) {
+        let n = C::BLOCK_SIZE;
+        let mut counter = *counter;
+        counter[n - 1] = 1;
+        apply_keystream(self.cipher, &mut counter[..n], self.length_size, data);
+    }
+}
+
+impl<'a, C> Aead for Ccm<'a, C>
+where
+    C: BlockEncrypt,
+{
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let counter = self.start(nonce, buffer.len())?;
+        let mut mac = self.mac(nonce, associated_data, buffer);
+        self.apply_keystream(&counter, buffer);
+
+        self.encrypt_tag(&counter, &mut mac);
+        tag.copy_from_slice(&mac[..self.tag_len]);
+        Ok(())
+    }
+
+    /*
+    The MAC is over the plaintext, so CCM has to decrypt before it can check the tag.
+    On failure the keystream is applied again, restoring the ciphertext.
+    */
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let counter = self.start(nonce, buffer.len())?;
+        self.apply_keystream(&counter, buffer);
+
+        let mut mac = self.mac(nonce, associated_data, buffer);
+        self.encrypt_tag(&counter, &mut mac);
+        if !ct_eq(&mac[..self.tag_len], tag) {
+            self.apply_keystream(&counter, buffer);
+            return Err(Error::AuthenticationFailed);
+        }
+        Ok(())
+    }
+}
+
+// Raw CBC-MAC chaining, where pad() zero pads the data so far to a whole number of blocks.
+struct CbcChain<'a, C> {
+    cipher: &'a C,
+    state: [u8; MAX_BLOCK_SIZE],
+    buffer_len: usize,
+}
+
+impl<'a, C> CbcChain<'a, C>
+where
+    C: BlockEncrypt,
+{
+    fn new(cipher: &'a C) -> Self {
+        Self {
+            cipher,
+            state: [0; MAX_BLOCK_SIZE],
+            buffer_len: 0,
+        }
+    }
+
+    // Data is xored straight into the state, a block is encrypted once it is complete.
+    fn update(&mut self, mut data: &[u8]) {
+        let n = C::BLOCK_SIZE;
+        while !data.is_empty() {
+            let take = (n - self.buffer_len).min(data.len());
+            xor(
+                &mut self.state[self.buffer_len..self.buffer_len + take],
+                &data[..take],
+            );
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len == n {
+                self.cipher.encrypt_block(&mut self.state[..n]);
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    fn pad(&mut self) {
+        if self.buffer_len > 0 {
+            self.cipher.encrypt_block(&mut self.state[..C::BLOCK_SIZE]);
+            self.buffer_len = 0;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::{bytes, rc5, Aes};
+
+    // (M, L, associated data length, message length, ciphertext, tag),
+    // generated by `python3 tests/vectors/rc5ref.py ccm`.
+    type Vector = (usize, usize, usize, usize, &'static str, &'static str);
+
+    fn check<C: BlockEncrypt>(cipher: &C, vectors: &[Vector]) {
+        for &(tag_len, length_size, ad_len, len, ct, tag) in vectors {
+            let ccm = Ccm::new(cipher, tag_len, length_size).unwrap();
+            let nonce = bytes(100, ccm.nonce_len());
+            let ad = bytes(200, ad_len);
+            let pt = bytes(0, len);
+
+            let sealed = ccm.encrypt(&nonce, &ad, &pt).unwrap();
+            let mut expected = decode_hex(ct).unwrap();
+            expected.extend(decode_hex(tag).unwrap());
+            assert_eq!(sealed, expected);
+
+            assert_eq!(ccm.decrypt(&nonce, &ad, &sealed).unwrap(), pt);
+        }
+    }
+
+    // Packet vectors 1 and 2 of RFC 3610.
+    #[test]
+    fn aes_rfc_3610() {
+        let aes = Aes::new(&bytes(0xC0, 16));
+        let ccm = Ccm::new(&aes, 8, 2).unwrap();
+        for (nonce, len, sealed) in [
+            (
+                "00000003020100A0A1A2A3A4A5",
+                23,
+                "588C979A61C663D2F066D0C2C0F989806D5F6B61DAC38417E8D12CFDF926E0",
+            ),
+            (
+                "00000004030201A0A1A2A3A4A5",
+                24,
+                "72C91A36E135F8CF291CA894085C87E3CC15C439C9E43A3BA091D56E10400916",
+            ),
+        ] {
+            let nonce = decode_hex(nonce).unwrap();
+            let sealed = decode_hex(sealed).unwrap();
+            assert_eq!(
+                ccm.encrypt(&nonce, &bytes(0, 8), &bytes(8, len)).unwrap(),
+                sealed
+            );
+            assert_eq!(
+                ccm.decrypt(&nonce, &bytes(0, 8), &sealed).unwrap(),
+                bytes(8, len)
+            );
+        }
+    }
+
+    #[test]
+    fn rc5_32() {
+        check(
+            &rc5::<u32>(12, 16),
+            &[
+                (4, 2, 0, 0, "", "DD020366"),
+                (
+                    8,
+                    2,
+                    3,
+                    21,
+                    "7A40BE17489C04481F12BD32BEB40CED63EF89AE85",
+                    "038B57ACCB74972F",
+                ),
+                (
+                    6,
+                    3,
+                    8,
+                    16,
+                    "3B3B1FF1E1348961798A85097BD5EAB9",
+                    "4535FB923C85",
+                ),
+                (8, 4, 0, 9, "592CBBC7181C225018", "8AD6D5258E4A79A9"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        check(
+            &rc5::<u64>(24, 24),
+            &[
+                (16, 2, 0, 0, "", "F4096C938FD3FDDD0D7181AEDA89D6F1"),
+                (
+                    16,
+                    3,
+                    20,
+                    40,
+                    "8CCF90CC3A383DD9A48B955BFF7D6F1A05418952AB8E7F9309DC56D5CA118F623717F4FB532E994E",
+                    "7C4DFCFA3A846FFB49A5AE5B1C8FE6C1",
+                ),
+                (8, 8, 7, 17, "E91EF274B017700C6FDF94080AA45F5D6A", "CBAA9B233450D13B"),
+                (
+                    12,
+                    2,
+                    16,
+                    16,
+                    "48241542E7DBACF526CA798697084BB7",
+                    "320F17B11AC92D52179EA0F9",
+                ),
+            ],
+        );
+    }
+
+    // Associated data of 0xFF00 bytes or more switches to the six byte length encoding,
+    // the vector is from the reference script too.
+    #[test]
+    fn long_associated_data() {
+        let cipher = rc5::<u32>(12, 16);
+        let ccm = Ccm::new(&cipher, 8, 2).unwrap();
+        let sealed = ccm.encrypt(&bytes(100, 5), &[0; 70000], b"x").unwrap();
+        assert_eq!(sealed, decode_hex("024C31C1D232E66B84").unwrap());
+    }
+
+    #[test]
+    fn message_length_limit() {
+        let cipher = rc5::<u16>(16, 8);
+        let ccm = Ccm::new(&cipher, 4, 2).unwrap();
+        assert_eq!(ccm.nonce_len(), 1);
+
+        let mut buffer = vec![0; 0x10000];
+        let mut tag = [0; 4];
+        assert!(matches!(
+            ccm.encrypt_in_place(&[0], b"", &mut buffer, &mut tag),
+            Err(Error::MessageTooLong)
+        ));
+        assert!(ccm
+            .encrypt_in_place(&[0], b"", &mut buffer[1..], &mut tag)
+            .is_ok());
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let cipher = rc5::<u32>(12, 16);
+        let ccm = Ccm::new(&cipher, 8, 3).unwrap();
+        let nonce = bytes(100, 4);
+        let sealed = ccm.encrypt(&nonce, b"header", b"attack at dawn").unwrap();
+
+        for i in 0..sealed.len() {
+            let mut forged = sealed.clone();
+            forged[i] ^= 0x01;
+            assert!(matches!(
+                ccm.decrypt(&nonce, b"header", &forged),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+        assert!(matches!(
+            ccm.decrypt(&nonce, b"", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            ccm.decrypt(&bytes(101, 4), b"header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn no_plaintext_on_failure() {
+        let cipher = rc5::<u64>(24, 24);
+        let ccm = Ccm::new(&cipher, 16, 2).unwrap();
+        let nonce = bytes(100, 13);
+
+        let mut buffer = b"secret message".to_vec();
+        let mut tag = [0; 16];
+        ccm.encrypt_in_place(&nonce, b"", &mut buffer, &mut tag)
+            .unwrap();
+        let ciphertext = buffer.clone();
+
+        tag[15] ^= 1;
+        assert!(matches!(
+            ccm.decrypt_in_place(&nonce, b"", &mut buffer, &tag),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert_eq!(buffer, ciphertext);
+    }
+
+    #[test]
+    fn parameters() {
+        let rc5_32 = rc5::<u32>(12, 16);
+        for tag_len in [0, 2, 5, 10] {
+            assert!(matches!(
+                Ccm::new(&rc5_32, tag_len, 2),
+                Err(Error::InvalidTagLength(len)) if len == tag_len
+            ));
+        }
+        for length_size in [0, 1, 7, 9] {
+            assert!(matches!(
+                Ccm::new(&rc5_32, 8, length_size),
+                Err(Error::InvalidLengthFieldSize(size)) if size == length_size
+            ));
+        }
+
+        let ccm = Ccm::new(&rc5_32, 8, 6).unwrap();
+        assert_eq!(ccm.nonce_len(), 1);
+        assert!(matches!(
+            ccm.encrypt(&[0; 2], b"", b""),
+            Err(Error::InvalidNonceLength(2))
+        ));
+
+        // The flags byte has no room for tags longer than 16 bytes, whatever the block size.
+        let rc5_128 = rc5::<u128>(12, 16);
+        assert!(matches!(
+            Ccm::new(&rc5_128, 18, 2),
+            Err(Error::InvalidTagLength(18))
+        ));
+        assert!(Ccm::new(&rc5_128, 16, 8).is_ok());
+
+        let rc5_8 = rc5::<u8>(12, 4);
+        assert!(matches!(
+            Ccm::new(&rc5_8, 4, 2),
+            Err(Error::InvalidTagLength(4))
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+// CRC-32 (IEEE 802.3), as used by zip and png.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// CRC-24 from OpenPGP (RFC 4880 section 6.1), as used by ASCII armor.
+#[cfg(feature = "std")]
+pub fn crc24(data: &[u8]) -> u32 {
+    let mut crc = 0xB7_04CE_u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= 0x186_4CFB;
+            }
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn crc24_check_value() {
+        assert_eq!(crc24(b"123456789"), 0x21_CF02);
+        assert_eq!(crc24(b""), 0xB7_04CE);
+    }
+}
@@ -0,0 +1,49 @@
+use core::hint::black_box;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+// Compares two byte strings in time that depends only on their lengths.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut diff = 0;
+    for (x, y) in a.iter().zip(b) {
+        diff |= x ^ y;
+    }
+    black_box(diff) == 0
+}
+
+/*
+Zeroes key material that is no longer needed. Volatile writes keep the compiler from removing
+them as dead stores, and the fence keeps later code from being moved before them.
+*/
+pub fn wipe(data: &mut [u8]) {
+    for byte in data.iter_mut() {
+        // SAFETY: byte is a valid, aligned and exclusive reference to a u8.
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"tag", b"tag"));
+        assert!(!ct_eq(b"tag", b"tah"));
+        assert!(!ct_eq(b"tag", b"ta"));
+    }
+
+    #[test]
+    fn wipe_zeroes() {
+        let mut key = [0xA5; 24];
+        wipe(&mut key[4..]);
+        assert_eq!(key[..4], [0xA5; 4]);
+        assert_eq!(key[4..], [0; 20]);
+    }
+}
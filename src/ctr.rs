@@ -0,0 +1,54 @@
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::mac::xor;
+
+/*
+Counter mode keystream, shared by the modes built on it.
+
+counter is the first counter block, after each block its last counter_bytes bytes are
+incremented as a big-endian integer, wrapping around without touching the rest of the block.
+On return counter holds the next unused counter block.
+*/
+pub(crate) fn apply_keystream<C>(
+    cipher: &C,
+    counter: &mut [u8],
+    counter_bytes: usize,
+    data: &mut [u8],
+) where
+    C: BlockEncrypt,
+{
+    let n = C::BLOCK_SIZE;
+    let mut keystream = [0; MAX_BLOCK_SIZE];
+
+    for chunk in data.chunks_mut(n) {
+        keystream[..n].copy_from_slice(counter);
+        cipher.encrypt_block(&mut keystream[..n]);
+        xor(chunk, &keystream[..n]);
+        increment(&mut counter[n - counter_bytes..]);
+    }
+}
+
+// Adds one to a big-endian integer, modulo 2^(8 * counter.len()).
+pub(crate) fn increment(counter: &mut [u8]) {
+    for byte in counter.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increment_wraps() {
+        let mut counter = [0x00, 0xFF, 0xFF];
+        increment(&mut counter);
+        assert_eq!(counter, [0x01, 0x00, 0x00]);
+
+        let mut counter = [0xFF, 0xFF];
+        increment(&mut counter);
+        assert_eq!(counter, [0x00, 0x00]);
+    }
+}
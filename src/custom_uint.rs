@@ -1,8 +1,12 @@
-use std::{
-    fmt::{Binary, Display, Formatter, LowerHex},
-    num::ParseIntError,
-};
+use core::fmt::{Binary, Display, Formatter, LowerHex, Write};
 
+#[cfg(feature = "std")]
+use crate::byte_order::BigEndian;
+use crate::byte_order::ByteOrder;
+
+#[cfg(feature = "std")]
+use crate::error::Result;
+#[cfg(feature = "std")]
 use crate::hex::{decode_hex, encode_hex};
 
 // Type aliases for common unsigned integer types
@@ -31,124 +35,91 @@ impl<const N: usize> CustomUInt<N> {
     pub const MIN: Self = Self { bits: [false; N] };
     pub const MAX: Self = Self { bits: [true; N] };
 
-    fn bits_to_u128(bits: Vec<bool>) -> u128 {
+    // bits are ordered from the most significant to the least significant
+    fn bits_to_u128(bits: &[bool]) -> u128 {
         let mut sum = 0;
-        for i in 0..bits.len() {
-            let bit = bits[bits.len() - i - 1];
-
-            if bit {
+        for (i, bit) in bits.iter().rev().enumerate() {
+            if *bit {
                 sum += 2_u128.pow(i as u32);
             }
         }
         sum
     }
 
-    pub fn to_u128(&self) -> u128 {
-        Self::bits_to_u128(self.bits.to_vec())
+    pub fn to_u128(self) -> u128 {
+        Self::bits_to_u128(&self.bits)
     }
 
-    pub fn to_u32(&self) -> u32 {
-        let mut bits = [false; 32];
-
-        for i in 0..32 {
-            if N >= 32 {
-                let bit_index = N - 32 + i;
-                bits[i] = self.bits[bit_index];
-            } else {
-                let bit_index = 32 - i;
-                if bit_index <= N {
-                    bits[i] = self.bits[N - bit_index];
-                }
-            }
-        }
-        Self::bits_to_u128(bits.to_vec()) as u32
+    pub fn to_u32(self) -> u32 {
+        let start = N.saturating_sub(32);
+        Self::bits_to_u128(&self.bits[start..]) as u32
     }
 
-    fn to_bit_str(&self) -> String {
-        let mut s = String::new();
-        for i in 0..N {
-            let bit = self.bits[N - i - 1];
-            s = format!("{}{}", s, if bit { "1" } else { "0" })
-        }
-        s
-    }
-
-    pub fn from_bytes(bytes: &[u8], hex: bool) -> Self {
-        let mut bit_string = String::new();
-        for byte in bytes {
-            let s = format!("{:b}", byte);
-            let diff = 8 - s.len();
-            let leading_zeroes: String = (0..diff).map(|_| '0').collect();
-            let bits = format!("{}{}", leading_zeroes, s);
-            if hex {
-                bit_string = format!("{}{}", bit_string, bits);
-            } else {
-                bit_string = format!("{}{}", bits, bit_string);
+    // The byte holding bits 8*k..8*k+8 of the value, counted from the least significant end.
+    fn byte(&self, k: usize) -> u8 {
+        let mut byte = 0;
+        for i in 0..8 {
+            let bit_index = 8 * k + i;
+            if bit_index < N && self.bits[N - 1 - bit_index] {
+                byte |= 1 << i;
             }
         }
+        byte
+    }
 
-        let diff = N - bit_string.len();
-        let leading_zeroes: String = (0..diff).map(|_| '0').collect();
-        bit_string = format!("{}{}", leading_zeroes, bit_string);
-
-        let bit_chars: Vec<char> = bit_string.chars().collect();
+    // Builds the integer from a byte string laid out in the byte order O.
+    pub fn from_bytes<O: ByteOrder>(bytes: &[u8]) -> Self {
         let mut bits = [false; N];
 
-        for i in 0..N {
-            bits[i] = bit_chars.get(i) == Some(&'1');
+        for (k, byte) in bytes.iter().enumerate() {
+            let significance = if O::BIG_ENDIAN {
+                bytes.len() - 1 - k
+            } else {
+                k
+            };
+            for i in 0..8 {
+                let bit_index = 8 * significance + i;
+                if bit_index < N {
+                    bits[N - 1 - bit_index] = byte & (1 << i) != 0;
+                }
+            }
         }
 
         Self { bits }
     }
 
-    pub fn to_bytes(&self, hex: bool) -> Vec<u8> {
-        let bit_str = self.to_bit_str();
-        let mut bytes: Vec<u8> = vec![];
-
-        for i in (0..N).step_by(8) {
-            let s = bit_str[i..i + 8].to_string();
-
-            // reverse string to assign bits correctly
-            let mut bit_chars: Vec<char> = s.chars().collect();
-            bit_chars.reverse();
-
-            let mut byte = [false; 8];
-            //convert to booleans
-            for i in 0..8 {
-                byte[i] = bit_chars.get(i) == Some(&'1');
-            }
-
-            //convert boolean to u8 using 2^i
-            let mut sum: u8 = 0;
-            for i in 0..8 {
-                let bit = byte[8 - i - 1];
-                if bit {
-                    sum += 2_u8.pow(i as u32);
-                }
-            }
-            bytes.push(sum);
+    // Writes the integer into out in the byte order O, out must hold exactly N / 8 bytes.
+    pub fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        let len = out.len();
+        for (k, byte) in out.iter_mut().enumerate() {
+            let significance = if O::BIG_ENDIAN { len - 1 - k } else { k };
+            *byte = self.byte(significance);
         }
+    }
 
-        if hex {
-            bytes.reverse();
-        }
+    #[cfg(feature = "std")]
+    pub fn to_bytes<O: ByteOrder>(self) -> Vec<u8> {
+        let mut bytes = vec![0; N / 8];
+        self.write_bytes::<O>(&mut bytes);
         bytes
     }
 
     // From Hex String
-    pub fn from_hex_str(s: &str) -> Result<Self, ParseIntError> {
+    #[cfg(feature = "std")]
+    pub fn from_hex_str(s: &str) -> Result<Self> {
         let s = decode_hex(s)?;
-        Ok(Self::from_bytes(&mut s.as_slice(), true))
+        Ok(Self::from_bytes::<BigEndian>(&s))
     }
 
-    pub fn to_hex_str(&self) -> String {
-        encode_hex(&self.to_bytes(true))
+    #[cfg(feature = "std")]
+    pub fn to_hex_str(self) -> String {
+        encode_hex(&self.to_bytes::<BigEndian>())
     }
 
     pub fn from_u128(u: u128) -> Self {
         let mut bits = [false; N];
-        for i in 0..N {
-            bits[i] = (u & (1_u128.rotate_left(i as u32))) != 0;
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = (u & (1_u128.rotate_left(i as u32))) != 0;
         }
         bits.reverse();
         Self { bits }
@@ -156,8 +127,8 @@ impl<const N: usize> CustomUInt<N> {
 
     pub fn rotate_left(self, rhs: u32) -> Self {
         let mut bits = [false; N];
-        for i in 0..N {
-            bits[i] = self.bits[(i + rhs as usize) % N];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = self.bits[(i + rhs as usize) % N];
         }
         Self { bits }
     }
@@ -166,15 +137,12 @@ impl<const N: usize> CustomUInt<N> {
     pub fn rotate_right(self, rhs: u32) -> Self {
         let mut bits = [false; N];
 
-        for i in 0..N {
+        for (i, bit) in bits.iter_mut().enumerate() {
             let bit_index = ((i + N).wrapping_sub(rhs as usize)) % N;
-            bits[i] = self.bits[bit_index];
+            *bit = self.bits[bit_index];
         }
 
-        let s = Self { bits };
-        // #[cfg(test)]
-        // println!("{} >> {} = {}", self, rhs, s.to_u128());
-        s
+        Self { bits }
     }
 
     pub fn wrapping_add(self, rhs: Self) -> Self {
@@ -187,54 +155,54 @@ impl<const N: usize> CustomUInt<N> {
 }
 
 impl<const N: usize> Binary for CustomUInt<N> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_bit_str())
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for bit in self.bits.iter().rev() {
+            f.write_char(if *bit { '1' } else { '0' })?;
+        }
+        Ok(())
     }
 }
 
 impl<const N: usize> Display for CustomUInt<N> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.to_u128())
     }
 }
 
 impl<const N: usize> LowerHex for CustomUInt<N> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_hex_str())
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for k in (0..N / 8).rev() {
+            write!(f, "{:02x}", self.byte(k))?;
+        }
+        Ok(())
     }
 }
 
-impl<const N: usize> std::ops::BitXor for CustomUInt<N> {
+impl<const N: usize> core::ops::BitXor for CustomUInt<N> {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
         let mut bits = [false; N];
-        for i in 0..N {
-            bits[i] = self.bits[i] ^ rhs.bits[i];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = self.bits[i] ^ rhs.bits[i];
         }
-
-        let s = Self { bits };
-
-        // #[cfg(test)]
-        // println!("{} ^ {} = {}", self, rhs, s);
-
-        s
+        Self { bits }
     }
 }
 
-impl<const N: usize> std::ops::BitOr for CustomUInt<N> {
+impl<const N: usize> core::ops::BitOr for CustomUInt<N> {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
         let mut bits = [false; N];
-        for i in 0..N {
-            bits[i] = self.bits[i] || rhs.bits[i];
+        for (i, bit) in bits.iter_mut().enumerate() {
+            *bit = self.bits[i] || rhs.bits[i];
         }
         Self { bits }
     }
 }
 
-impl<const N: usize> std::ops::Add for CustomUInt<N> {
+impl<const N: usize> core::ops::Add for CustomUInt<N> {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
@@ -275,7 +243,7 @@ impl<const N: usize> std::ops::Add for CustomUInt<N> {
     }
 }
 
-impl<const N: usize> std::ops::Sub for CustomUInt<N> {
+impl<const N: usize> core::ops::Sub for CustomUInt<N> {
     type Output = Self;
     fn sub(self, mut rhs: Self) -> Self::Output {
         for i in (0..N).rev() {
@@ -286,21 +254,23 @@ impl<const N: usize> std::ops::Sub for CustomUInt<N> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use crate::byte_order::LittleEndian;
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn to_u32() {
         let v8 = 244_u8;
-        let r8 = U8::from_bytes(&v8.to_le_bytes(), false);
+        let r8 = U8::from_bytes::<LittleEndian>(&v8.to_le_bytes());
         let v16 = 520_u16;
-        let r16 = U16::from_bytes(&v16.to_le_bytes(), false);
+        let r16 = U16::from_bytes::<LittleEndian>(&v16.to_le_bytes());
         let v32 = 902166484_u32;
-        let r32 = U32::from_bytes(&v32.to_le_bytes(), false);
+        let r32 = U32::from_bytes::<LittleEndian>(&v32.to_le_bytes());
 
         let v64: u64 = 902166487400020018;
-        let r64 = U80::from_bytes(&v64.to_le_bytes(), false);
+        let r64 = U80::from_bytes::<LittleEndian>(&v64.to_le_bytes());
 
         assert_eq!(r8.to_u32(), v8 as u32);
         assert_eq!(r16.to_u32(), v16 as u32);
@@ -342,12 +312,12 @@ mod tests {
 
         let c = 1111_u32.to_le_bytes();
 
-        let u = U80::from_bytes(&mut a.as_slice(), false);
+        let u = U80::from_bytes::<LittleEndian>(&a);
         assert_eq!(u.to_u128(), 11);
-        let u = U80::from_bytes(&mut b.as_slice(), false);
+        let u = U80::from_bytes::<LittleEndian>(&b);
         assert_eq!(u.to_u128(), 1);
         println!("\nc\n");
-        let u = U80::from_bytes(&mut c.as_slice(), false);
+        let u = U80::from_bytes::<LittleEndian>(&c);
         assert_eq!(u.to_u128(), 1111);
     }
 
@@ -355,7 +325,7 @@ mod tests {
     fn to_bytes() {
         let a: Vec<u8> = vec![250, 209, 184, 0, 0, 0, 0, 0, 0, 0];
 
-        let u = U80::from_u128(12112378).to_bytes(false);
+        let u = U80::from_u128(12112378).to_bytes::<LittleEndian>();
         assert_eq!(u, a);
     }
 
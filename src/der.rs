@@ -0,0 +1,394 @@
+use crate::error::{Error, Result};
+use crate::pbes2::{Rc5CbcParameters, RC5_CBC_VERSION};
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+
+// 1.2.840.113549.3.8 and 1.2.840.113549.3.9, without the tag and length.
+const RC5_CBC_OID: [u8; 8] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x03, 0x08];
+const RC5_CBC_PAD_OID: [u8; 8] = [0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x03, 0x09];
+
+// The longest encoding, with a 128-bit block size and a 16 byte IV.
+pub const MAX_ALGORITHM_IDENTIFIER_LEN: usize = 42;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rc5Algorithm {
+    // rc5CBC from RFC 2040, the message is a whole number of blocks.
+    Cbc,
+    // rc5-CBC-PAD from RFC 2040 and RFC 8018, with PKCS#7 padding.
+    CbcPad,
+}
+
+impl Rc5Algorithm {
+    // The OID in dotted form.
+    pub fn oid(&self) -> &'static str {
+        match self {
+            Rc5Algorithm::Cbc => "1.2.840.113549.3.8",
+            Rc5Algorithm::CbcPad => "1.2.840.113549.3.9",
+        }
+    }
+
+    fn oid_bytes(&self) -> &'static [u8] {
+        match self {
+            Rc5Algorithm::Cbc => &RC5_CBC_OID,
+            Rc5Algorithm::CbcPad => &RC5_CBC_PAD_OID,
+        }
+    }
+}
+
+/*
+An AlgorithmIdentifier for RC5 in CBC mode, the OID with RC5-CBC-Parameters:
+
+    RC5-CBC-Parameters ::= SEQUENCE {
+        version          INTEGER {v1-0(16)},
+        rounds           INTEGER (8..127),
+        blockSizeInBits  INTEGER (64 | 128),
+        iv               OCTET STRING OPTIONAL }
+
+The decoder only accepts DER, and takes a missing IV to be all zero bytes. The encoder
+always writes the IV, so an identifier without one doesn't encode back to the same bytes.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rc5AlgorithmIdentifier {
+    pub algorithm: Rc5Algorithm,
+    pub parameters: Rc5CbcParameters,
+}
+
+impl Rc5AlgorithmIdentifier {
+    pub fn new(algorithm: Rc5Algorithm, parameters: Rc5CbcParameters) -> Rc5AlgorithmIdentifier {
+        Self {
+            algorithm,
+            parameters,
+        }
+    }
+
+    fn parameters_len(&self) -> usize {
+        let block_size = integer_len(self.parameters.block_size_bits() as u64);
+        3 + 3 + 2 + block_size + 2 + self.parameters.iv().len()
+    }
+
+    // The length of the DER encoding in bytes.
+    pub fn encoded_len(&self) -> usize {
+        2 + 2 + self.algorithm.oid_bytes().len() + 2 + self.parameters_len()
+    }
+
+    // Writes the DER encoding to the start of out and returns its length.
+    pub fn encode_into(&self, out: &mut [u8]) -> Result<usize> {
+        let len = self.encoded_len();
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let mut writer = Writer { out, position: 0 };
+        writer.header(SEQUENCE, len - 2);
+        writer.header(OBJECT_IDENTIFIER, self.algorithm.oid_bytes().len());
+        writer.bytes(self.algorithm.oid_bytes());
+        writer.header(SEQUENCE, self.parameters_len());
+        writer.integer(RC5_CBC_VERSION as u64);
+        writer.integer(self.parameters.rounds() as u64);
+        writer.integer(self.parameters.block_size_bits() as u64);
+        writer.header(OCTET_STRING, self.parameters.iv().len());
+        writer.bytes(self.parameters.iv());
+
+        Ok(writer.position)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![0; self.encoded_len()];
+        self.encode_into(&mut out)
+            .expect("the buffer is exactly the encoded length");
+        out
+    }
+
+    // Decodes an identifier that makes up the whole of input.
+    pub fn decode(input: &[u8]) -> Result<Rc5AlgorithmIdentifier> {
+        let mut outer = Reader::new(input);
+        let mut identifier = outer.nested(SEQUENCE)?;
+        outer.finish()?;
+
+        let (oid, oid_position) = identifier.read(OBJECT_IDENTIFIER)?;
+        let algorithm = if oid == RC5_CBC_OID {
+            Rc5Algorithm::Cbc
+        } else if oid == RC5_CBC_PAD_OID {
+            Rc5Algorithm::CbcPad
+        } else {
+            return Err(Error::UnknownAlgorithm {
+                position: oid_position,
+            });
+        };
+
+        let mut params = identifier.nested(SEQUENCE)?;
+        identifier.finish()?;
+
+        let (version, _) = params.integer()?;
+        if version != RC5_CBC_VERSION as u64 {
+            return Err(Error::UnsupportedVersion(version.min(0xFF) as u8));
+        }
+        let (rounds, _) = params.integer()?;
+        let (block_size_bits, _) = params.integer()?;
+        if block_size_bits != 64 && block_size_bits != 128 {
+            return Err(Error::UnsupportedBlockSize(
+                block_size_bits.min(0xFFFF) as usize / 8,
+            ));
+        }
+
+        let zero_iv = [0; 16];
+        let iv = if params.is_empty() {
+            &zero_iv[..block_size_bits as usize / 8]
+        } else {
+            params.read(OCTET_STRING)?.0
+        };
+        params.finish()?;
+
+        let rounds = rounds.min(0xFF) as u8;
+        let parameters = Rc5CbcParameters::new(rounds, block_size_bits as u16, iv)?;
+        Ok(Self::new(algorithm, parameters))
+    }
+}
+
+fn integer_len(value: u64) -> usize {
+    // A leading zero byte keeps values with the top bit set positive.
+    let bits = 64 - value.leading_zeros() as usize;
+    bits / 8 + 1
+}
+
+struct Writer<'a> {
+    out: &'a mut [u8],
+    position: usize,
+}
+
+impl<'a> Writer<'a> {
+    // Everything in these structures is under 128 bytes, so lengths take the short form.
+    fn header(&mut self, tag: u8, len: usize) {
+        debug_assert!(len < 0x80);
+        self.bytes(&[tag, len as u8]);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.out[self.position..self.position + bytes.len()].copy_from_slice(bytes);
+        self.position += bytes.len();
+    }
+
+    fn integer(&mut self, value: u64) {
+        let len = integer_len(value);
+        self.header(INTEGER, len);
+        let mut bytes = [0; 9];
+        bytes[1..].copy_from_slice(&value.to_be_bytes());
+        self.bytes(&bytes[9 - len..]);
+    }
+}
+
+// Reads DER elements from input[position..end], reporting errors at absolute byte positions.
+struct Reader<'a> {
+    input: &'a [u8],
+    position: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a [u8]) -> Reader<'a> {
+        Self {
+            input,
+            position: 0,
+            end: input.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position == self.end
+    }
+
+    fn malformed(&self) -> Error {
+        Error::MalformedDer {
+            position: self.position,
+        }
+    }
+
+    // Reads an element with the given tag, returning its contents and where they start.
+    fn read(&mut self, tag: u8) -> Result<(&'a [u8], usize)> {
+        if self.end - self.position < 2 || self.input[self.position] != tag {
+            return Err(self.malformed());
+        }
+        // Long form lengths are never the shortest encoding for elements this small.
+        let len = self.input[self.position + 1] as usize;
+        if len >= 0x80 || len > self.end - self.position - 2 {
+            return Err(Error::MalformedDer {
+                position: self.position + 1,
+            });
+        }
+
+        let start = self.position + 2;
+        self.position = start + len;
+        Ok((&self.input[start..start + len], start))
+    }
+
+    fn nested(&mut self, tag: u8) -> Result<Reader<'a>> {
+        let (contents, start) = self.read(tag)?;
+        Ok(Reader {
+            input: self.input,
+            position: start,
+            end: start + contents.len(),
+        })
+    }
+
+    // A non-negative integer of at most 64 bits in its shortest encoding.
+    fn integer(&mut self) -> Result<(u64, usize)> {
+        let (contents, start) = self.read(INTEGER)?;
+        let malformed = Err(Error::MalformedDer { position: start });
+
+        match contents {
+            [] => return malformed,
+            // Negative
+            [first, ..] if first & 0x80 != 0 => return malformed,
+            // A leading zero that isn't needed for the sign.
+            [0, second, ..] if second & 0x80 == 0 => return malformed,
+            _ => {}
+        }
+        let contents = if contents[0] == 0 {
+            &contents[1..]
+        } else {
+            contents
+        };
+        if contents.len() > 8 {
+            return malformed;
+        }
+
+        let value = contents.iter().fold(0, |value, &b| (value << 8) | b as u64);
+        Ok((value, start))
+    }
+
+    // Fails if anything is left after the elements read so far.
+    fn finish(&self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.malformed())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    fn identifier(
+        algorithm: Rc5Algorithm,
+        rounds: u8,
+        bits: u16,
+        iv: &[u8],
+    ) -> Rc5AlgorithmIdentifier {
+        Rc5AlgorithmIdentifier::new(algorithm, Rc5CbcParameters::new(rounds, bits, iv).unwrap())
+    }
+
+    // Encodings checked with openssl asn1parse.
+    #[test]
+    fn encode_and_decode() {
+        let iv = decode_hex("0001020304050607").unwrap();
+        let cbc_pad = identifier(Rc5Algorithm::CbcPad, 12, 64, &iv);
+        let der = decode_hex("301F06082A864886F70D0309301302011002010C02014004080001020304050607")
+            .unwrap();
+        assert_eq!(cbc_pad.encode(), der);
+        assert_eq!(cbc_pad.encoded_len(), der.len());
+        assert_eq!(Rc5AlgorithmIdentifier::decode(&der).unwrap(), cbc_pad);
+
+        // 128 needs a leading zero byte to stay positive.
+        let iv = decode_hex("000102030405060708090A0B0C0D0E0F").unwrap();
+        let cbc = identifier(Rc5Algorithm::Cbc, 16, 128, &iv);
+        let der = decode_hex(
+            "302806082A864886F70D0308301C020110020110020200800410000102030405060708090A0B0C0D0E0F",
+        )
+        .unwrap();
+        assert_eq!(cbc.encode(), der);
+        assert_eq!(der.len(), MAX_ALGORITHM_IDENTIFIER_LEN);
+        assert_eq!(Rc5AlgorithmIdentifier::decode(&der).unwrap(), cbc);
+
+        assert_eq!(cbc.parameters.word_size_bits(), 64);
+        assert_eq!(cbc.algorithm.oid(), "1.2.840.113549.3.8");
+    }
+
+    #[test]
+    fn missing_iv_is_zero() {
+        let der = decode_hex("301506082A864886F70D0309300902011002010C020140").unwrap();
+        let decoded = Rc5AlgorithmIdentifier::decode(&der).unwrap();
+        assert_eq!(decoded, identifier(Rc5Algorithm::CbcPad, 12, 64, &[0; 8]));
+    }
+
+    #[test]
+    fn rejects_invalid_encodings() {
+        let valid = identifier(Rc5Algorithm::CbcPad, 12, 64, &[7; 8]).encode();
+        let decode = |der: &[u8]| Rc5AlgorithmIdentifier::decode(der);
+
+        // Every truncation fails.
+        for len in 0..valid.len() {
+            assert!(decode(&valid[..len]).is_err());
+        }
+
+        let mut trailing = valid.clone();
+        trailing.push(0);
+        assert!(matches!(
+            decode(&trailing),
+            Err(Error::MalformedDer { position: 33 })
+        ));
+
+        // An unknown OID, 1.2.840.113549.3.7 is DES-EDE3-CBC.
+        let mut other = valid.clone();
+        other[11] = 0x07;
+        assert!(matches!(
+            decode(&other),
+            Err(Error::UnknownAlgorithm { position: 4 })
+        ));
+
+        let mut version = valid.clone();
+        version[16] = 0x11;
+        assert!(matches!(
+            decode(&version),
+            Err(Error::UnsupportedVersion(0x11))
+        ));
+
+        let mut rounds = valid.clone();
+        rounds[19] = 0x05;
+        assert!(matches!(decode(&rounds), Err(Error::InvalidRounds(5))));
+
+        let mut block_size = valid.clone();
+        block_size[22] = 0x20;
+        assert!(matches!(
+            decode(&block_size),
+            Err(Error::UnsupportedBlockSize(4))
+        ));
+
+        // A negative number of rounds.
+        let mut negative = valid.clone();
+        negative[19] = 0x8C;
+        assert!(matches!(
+            decode(&negative),
+            Err(Error::MalformedDer { position: 19 })
+        ));
+
+        // The IV must be one block.
+        let mut short_iv = valid;
+        short_iv[1] -= 1;
+        short_iv[13] -= 1;
+        short_iv[24] -= 1;
+        short_iv.pop();
+        assert!(matches!(
+            decode(&short_iv),
+            Err(Error::InvalidLength {
+                expected: 8,
+                found: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn buffer_too_small() {
+        let id = identifier(Rc5Algorithm::CbcPad, 12, 64, &[0; 8]);
+        assert!(matches!(
+            id.encode_into(&mut [0; 20]),
+            Err(Error::BufferTooSmall { needed: 33 })
+        ));
+    }
+}
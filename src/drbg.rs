@@ -0,0 +1,294 @@
+use crate::block_cipher::MAX_BLOCK_SIZE;
+use crate::ctr::increment;
+use crate::error::{Error, Result};
+use crate::mac::xor;
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+// The cipher is rekeyed with 256-bit keys.
+pub const DRBG_KEY_LEN: usize = 32;
+
+const MAX_SEED_LEN: usize = DRBG_KEY_LEN + MAX_BLOCK_SIZE;
+
+/*
+A deterministic random bit generator in the style of CTR_DRBG from NIST SP 800-90A,
+without a derivation function, over RC5 with any word size.
+
+The state is a 256-bit key and a counter block V. Entropy and personalization strings are
+seed_len() bytes, key plus block, and shorter personalization or additional input is zero padded.
+The same seed always gives the same stream, seeding from the OS gives an unpredictable one.
+
+The limits scale with the block size b in bits, as SP 800-90A does for 64- and 128-bit blocks:
+at most 2^(b/2) requests between reseeds, capped at 2^48, and requests of at most
+2^(b/4 - 3) bits, capped at 2^19 bits and never less than one block.
+*/
+pub struct CtrDrbg<T> {
+    cipher: Rc5<T>,
+    v: [u8; MAX_BLOCK_SIZE],
+    reseed_counter: u64,
+}
+
+impl<T> CtrDrbg<T>
+where
+    T: UInt,
+{
+    // Instantiates the DRBG from seed_len() bytes of entropy.
+    pub fn new(rounds: u8, entropy: &[u8], personalization: &[u8]) -> Result<CtrDrbg<T>> {
+        let mut drbg = Self {
+            cipher: Rc5::new(rounds, DRBG_KEY_LEN)?,
+            v: [0; MAX_BLOCK_SIZE],
+            reseed_counter: 1,
+        };
+        drbg.cipher.setup(&[0; DRBG_KEY_LEN]);
+
+        let seed = drbg.seed_material(entropy, personalization)?;
+        drbg.update(&seed[..Self::seed_len()]);
+        Ok(drbg)
+    }
+
+    // Instantiates the DRBG with entropy read from /dev/urandom.
+    #[cfg(feature = "std")]
+    pub fn from_os_entropy(rounds: u8, personalization: &[u8]) -> Result<CtrDrbg<T>> {
+        let entropy = os_entropy(Self::seed_len())?;
+        Self::new(rounds, &entropy, personalization)
+    }
+
+    fn block_len() -> usize {
+        2 * T::BYTES
+    }
+
+    // The length of the entropy input, the key length plus the block length.
+    pub fn seed_len() -> usize {
+        DRBG_KEY_LEN + Self::block_len()
+    }
+
+    // The number of generate calls allowed between reseeds.
+    pub fn reseed_interval() -> u64 {
+        let half = (8 * Self::block_len() / 2).min(48);
+        1 << half
+    }
+
+    // The largest request generate accepts, in bytes.
+    pub fn max_request_len() -> usize {
+        let bits = 8 * Self::block_len();
+        let log2 = (bits / 4).saturating_sub(3).min(19);
+        ((1usize << log2) / 8).max(Self::block_len())
+    }
+
+    pub fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) -> Result<()> {
+        let seed = self.seed_material(entropy, additional_input)?;
+        self.update(&seed[..Self::seed_len()]);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn reseed_from_os(&mut self, additional_input: &[u8]) -> Result<()> {
+        let entropy = os_entropy(Self::seed_len())?;
+        self.reseed(&entropy, additional_input)
+    }
+
+    // Fills out with random bytes, mixing in additional_input if it isn't empty.
+    pub fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<()> {
+        if self.reseed_counter > Self::reseed_interval() {
+            return Err(Error::ReseedRequired);
+        }
+        if out.len() > Self::max_request_len() {
+            return Err(Error::RequestTooLarge {
+                max: Self::max_request_len(),
+            });
+        }
+        let additional = padded(additional_input, Self::seed_len())?;
+        if !additional_input.is_empty() {
+            self.update(&additional[..Self::seed_len()]);
+        }
+
+        let n = Self::block_len();
+        for chunk in out.chunks_mut(n) {
+            increment(&mut self.v[..n]);
+            let mut block = self.v;
+            self.cipher.encrypt_block(&mut block[..n]);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        self.update(&additional[..Self::seed_len()]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    // entropy xor the zero padded string, entropy has to be exactly seed_len() bytes.
+    fn seed_material(&self, entropy: &[u8], string: &[u8]) -> Result<[u8; MAX_SEED_LEN]> {
+        if entropy.len() != Self::seed_len() {
+            return Err(Error::InvalidLength {
+                expected: Self::seed_len(),
+                found: entropy.len(),
+            });
+        }
+        let mut seed = padded(string, Self::seed_len())?;
+        xor(&mut seed, entropy);
+        Ok(seed)
+    }
+
+    // CTR_DRBG_Update, derives a new key and V from the current state and provided_data.
+    fn update(&mut self, provided_data: &[u8]) {
+        let n = Self::block_len();
+        let mut temp = [0; MAX_SEED_LEN];
+        for chunk in temp[..Self::seed_len()].chunks_mut(n) {
+            increment(&mut self.v[..n]);
+            chunk.copy_from_slice(&self.v[..n]);
+            self.cipher.encrypt_block(chunk);
+        }
+        xor(&mut temp, provided_data);
+
+        self.cipher.setup(&temp[..DRBG_KEY_LEN]);
+        self.v[..n].copy_from_slice(&temp[DRBG_KEY_LEN..DRBG_KEY_LEN + n]);
+    }
+}
+
+fn padded(data: &[u8], len: usize) -> Result<[u8; MAX_SEED_LEN]> {
+    if data.len() > len {
+        return Err(Error::InvalidLength {
+            expected: len,
+            found: data.len(),
+        });
+    }
+    let mut out = [0; MAX_SEED_LEN];
+    out[..data.len()].copy_from_slice(data);
+    Ok(out)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn os_entropy(len: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut entropy = vec![0; len];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut entropy)?;
+    Ok(entropy)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::bytes;
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py drbg`.
+    fn check<T: UInt>(rounds: u8, outputs: [&str; 3]) {
+        let seed_len = CtrDrbg::<T>::seed_len();
+        let mut drbg: CtrDrbg<T> = CtrDrbg::new(rounds, &bytes(0, seed_len), b"personal").unwrap();
+
+        let mut out = vec![0; 40];
+        drbg.generate(&mut out, b"").unwrap();
+        assert_eq!(out, decode_hex(outputs[0]).unwrap());
+
+        let mut out = vec![0; 16];
+        drbg.generate(&mut out, b"extra").unwrap();
+        assert_eq!(out, decode_hex(outputs[1]).unwrap());
+
+        drbg.reseed(&bytes(100, seed_len), b"more").unwrap();
+        let mut out = vec![0; 24];
+        drbg.generate(&mut out, b"").unwrap();
+        assert_eq!(out, decode_hex(outputs[2]).unwrap());
+    }
+
+    #[test]
+    fn rc5_32() {
+        check::<u32>(
+            12,
+            [
+                "742C78A44CA4A463CCD01D134827469E5A2EF67B34E506E911369B6ACBEA036A1E3BC616A77CF804",
+                "54C4E9B670D3D55A7D732CBE5DD6C257",
+                "2250579CB4664BF9122E186C03579121DDB8AC7BD6575973",
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        check::<u64>(
+            24,
+            [
+                "FF0C32CA5044BC628FC618F78BC57A887432BF32DA2DDDB71993B037E17BBB41945DC8274D19F803",
+                "173D66DE72EBBFF01C0694D7012EFBB1",
+                "E3868BB14CB1FC992E47ABD7119D4D6CEEDFD1AFFC2F1F53",
+            ],
+        );
+    }
+
+    #[test]
+    fn limits_follow_block_size() {
+        assert_eq!(CtrDrbg::<u8>::reseed_interval(), 1 << 8);
+        assert_eq!(CtrDrbg::<u32>::reseed_interval(), 1 << 32);
+        assert_eq!(CtrDrbg::<u64>::reseed_interval(), 1 << 48);
+        assert_eq!(CtrDrbg::<u8>::max_request_len(), 2);
+        assert_eq!(CtrDrbg::<u32>::max_request_len(), 1 << 10);
+        assert_eq!(CtrDrbg::<u64>::max_request_len(), 1 << 16);
+    }
+
+    #[test]
+    fn reseed_required() {
+        let seed_len = CtrDrbg::<u8>::seed_len();
+        let mut drbg: CtrDrbg<u8> = CtrDrbg::new(12, &bytes(0, seed_len), b"").unwrap();
+
+        let mut out = [0; 2];
+        for _ in 0..CtrDrbg::<u8>::reseed_interval() {
+            drbg.generate(&mut out, b"").unwrap();
+        }
+        assert!(matches!(
+            drbg.generate(&mut out, b""),
+            Err(Error::ReseedRequired)
+        ));
+
+        drbg.reseed(&bytes(1, seed_len), b"").unwrap();
+        assert!(drbg.generate(&mut out, b"").is_ok());
+        assert!(matches!(
+            drbg.generate(&mut [0; 3], b""),
+            Err(Error::RequestTooLarge { max: 2 })
+        ));
+    }
+
+    #[test]
+    fn inputs_change_the_stream() {
+        let seed_len = CtrDrbg::<u32>::seed_len();
+        let generate = |entropy: &[u8], personalization: &[u8], additional: &[u8]| {
+            let mut drbg: CtrDrbg<u32> = CtrDrbg::new(12, entropy, personalization).unwrap();
+            let mut out = [0; 32];
+            drbg.generate(&mut out, additional).unwrap();
+            out
+        };
+
+        let base = generate(&bytes(0, seed_len), b"", b"");
+        assert_eq!(base, generate(&bytes(0, seed_len), b"", b""));
+        assert_ne!(base, generate(&bytes(1, seed_len), b"", b""));
+        assert_ne!(base, generate(&bytes(0, seed_len), b"app", b""));
+        assert_ne!(base, generate(&bytes(0, seed_len), b"", b"request"));
+    }
+
+    #[test]
+    fn invalid_input() {
+        let seed_len = CtrDrbg::<u32>::seed_len();
+        assert!(matches!(
+            CtrDrbg::<u32>::new(12, &bytes(0, seed_len - 1), b""),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            CtrDrbg::<u32>::new(12, &bytes(0, seed_len), &bytes(0, seed_len + 1)),
+            Err(Error::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn os_entropy() {
+        let mut first: CtrDrbg<u64> = CtrDrbg::from_os_entropy(24, b"").unwrap();
+        let mut second: CtrDrbg<u64> = CtrDrbg::from_os_entropy(24, b"").unwrap();
+
+        let mut a = [0; 32];
+        let mut b = [0; 32];
+        first.generate(&mut a, b"").unwrap();
+        second.generate(&mut b, b"").unwrap();
+        assert_ne!(a, b);
+
+        first.reseed_from_os(b"").unwrap();
+        first.generate(&mut a, b"").unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use crate::aead::Aead;
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::constant_time::ct_eq;
+use crate::ctr::apply_keystream;
+use crate::error::{Error, Result};
+use crate::mac::{xor, Cmac};
+
+/*
+EAX authenticated encryption (Bellare, Rogaway and Wagner), built from CTR and OMAC.
+
+It works for every block size CMAC supports, with nonces and associated data of any length.
+Tags are tag_len bytes, at most one block.
+*/
+pub struct Eax<'a, C> {
+    cipher: &'a C,
+    mac: Cmac<'a, C>,
+    tag_len: usize,
+}
+
+impl<'a, C> Eax<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C, tag_len: usize) -> Result<Eax<'a, C>> {
+        if tag_len == 0 || tag_len > C::BLOCK_SIZE {
+            return Err(Error::InvalidTagLength(tag_len));
+        }
+
+        Ok(Self {
+            cipher,
+            mac: Cmac::new(cipher)?,
+            tag_len,
+        })
+    }
+
+    // OMAC^t(data), the CMAC of the block [t] followed by data.
+    fn omac(&self, t: u8, data: &[u8], out: &mut [u8; MAX_BLOCK_SIZE]) {
+        let n = C::BLOCK_SIZE;
+        let mut prefix = [0; MAX_BLOCK_SIZE];
+        prefix[n - 1] = t;
+
+        let mut mac = self.mac.clone();
+        mac.update(&prefix[..n]);
+        mac.update(data);
+        mac.finalize_into(&mut out[..n])
+            .expect("a full block is a valid tag length");
+    }
+
+    // The tag over the nonce, associated data and ciphertext, N' is left in nonce_mac.
+    fn tag(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        ciphertext: &[u8],
+        nonce_mac: &mut [u8; MAX_BLOCK_SIZE],
+    ) -> [u8; MAX_BLOCK_SIZE] {
+        let n = C::BLOCK_SIZE;
+        let mut header_mac = [0; MAX_BLOCK_SIZE];
+        let mut ciphertext_mac = [0; MAX_BLOCK_SIZE];
+
+        self.omac(0, nonce, nonce_mac);
+        self.omac(1, associated_data, &mut header_mac);
+        self.omac(2, ciphertext, &mut ciphertext_mac);
+
+        let mut tag = *nonce_mac;
+        xor(&mut tag[..n], &header_mac[..n]);
+        xor(&mut tag[..n], &ciphertext_mac[..n]);
+        tag
+    }
+}
+
+impl<'a, C> Aead for Eax<'a, C>
+where
+    C: BlockEncrypt,
+{
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let n = C::BLOCK_SIZE;
+        let mut counter = [0; MAX_BLOCK_SIZE];
+        self.omac(0, nonce, &mut counter);
+        apply_keystream(self.cipher, &mut counter[..n], n, buffer);
+
+        let mut nonce_mac = [0; MAX_BLOCK_SIZE];
+        let full_tag = self.tag(nonce, associated_data, buffer, &mut nonce_mac);
+        tag.copy_from_slice(&full_tag[..self.tag_len]);
+        Ok(())
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let n = C::BLOCK_SIZE;
+        let mut counter = [0; MAX_BLOCK_SIZE];
+        let expected = self.tag(nonce, associated_data, buffer, &mut counter);
+        if !ct_eq(&expected[..self.tag_len], tag) {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        apply_keystream(self.cipher, &mut counter[..n], n, buffer);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::{bytes, rc5, Aes};
+
+    // (nonce length, associated data length, message length, ciphertext, tag),
+    // generated by `python3 tests/vectors/rc5ref.py eax`.
+    type Vector = (usize, usize, usize, &'static str, &'static str);
+
+    fn check<C: BlockEncrypt>(cipher: &C, vectors: &[Vector]) {
+        let eax = Eax::new(cipher, C::BLOCK_SIZE).unwrap();
+
+        for &(nonce_len, ad_len, len, ct, tag) in vectors {
+            let nonce = bytes(100, nonce_len);
+            let ad = bytes(200, ad_len);
+            let pt = bytes(0, len);
+
+            let sealed = eax.encrypt(&nonce, &ad, &pt).unwrap();
+            let mut expected = decode_hex(ct).unwrap();
+            expected.extend(decode_hex(tag).unwrap());
+            assert_eq!(sealed, expected);
+
+            assert_eq!(eax.decrypt(&nonce, &ad, &sealed).unwrap(), pt);
+        }
+    }
+
+    // The first two vectors from the appendix of the EAX paper by Bellare, Rogaway and Wagner.
+    #[test]
+    fn aes_eax_paper() {
+        for (key, nonce, header, pt, sealed) in [
+            (
+                "233952DEE4D5ED5F9B9C6D6FF80FF478",
+                "62EC67F9C3A4A407FCB2A8C49031A8B3",
+                "6BFB914FD07EAE6B",
+                "",
+                "E037830E8389F27B025A2D6527E79D01",
+            ),
+            (
+                "91945D3F4DCBEE0BF45EF52255F095A4",
+                "BECAF043B0A23D843194BA972C66DEBD",
+                "FA3BFD4806EB53FA",
+                "F7FB",
+                "19DD5C4C9331049D0BDAB0277408F67967E5",
+            ),
+        ] {
+            let aes = Aes::new(&decode_hex(key).unwrap());
+            let eax = Eax::new(&aes, 16).unwrap();
+            let nonce = decode_hex(nonce).unwrap();
+            let header = decode_hex(header).unwrap();
+            let sealed = decode_hex(sealed).unwrap();
+            assert_eq!(
+                eax.encrypt(&nonce, &header, &decode_hex(pt).unwrap())
+                    .unwrap(),
+                sealed
+            );
+            assert_eq!(
+                eax.decrypt(&nonce, &header, &sealed).unwrap(),
+                decode_hex(pt).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn rc5_32() {
+        check(
+            &rc5::<u32>(12, 16),
+            &[
+                (0, 0, 0, "", "D10E105BD5828FEA"),
+                (
+                    12,
+                    5,
+                    21,
+                    "F4672EFA6C8ACDA17BE934A86A078B0080D2D14A48",
+                    "FF3804450E730E76",
+                ),
+                (
+                    3,
+                    0,
+                    40,
+                    "A8EFB55615B039CEEC9FD461430A7CE090CCAABE2960AF9EE374CE0CA8CAA2CA303B96671C927181",
+                    "8E71A3166971DC9E",
+                ),
+                (16, 16, 16, "C1723531630ADF29F63C6867A13826EF", "AD56D666D8302398"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        check(
+            &rc5::<u64>(24, 24),
+            &[
+                (0, 0, 0, "", "CA9107DA0E94472A14D8D53F069E9171"),
+                (
+                    12,
+                    5,
+                    21,
+                    "0252153C111A508152C08B8D79DB7969CBF6343E8F",
+                    "91D627E3BCCC9BBFB2F2B8A7830750D6",
+                ),
+                (
+                    3,
+                    0,
+                    40,
+                    "AF835890E1D4FCD5CF376006B4C812075CAF5B92FB8F5D7A811F34F8E7C0F2A524222FFD66FD72D3",
+                    "C671A40873F01DEDAF0C96D7FA129F50",
+                ),
+                (
+                    16,
+                    16,
+                    16,
+                    "03D9E6554338C83E76C2E0DFB080976F",
+                    "913BF10ECED4FEC9C4FAB9D06BA5DF48",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn truncated_tag() {
+        let cipher = rc5::<u64>(24, 24);
+        let full = Eax::new(&cipher, 16).unwrap();
+        let short = Eax::new(&cipher, 6).unwrap();
+
+        let sealed = full.encrypt(b"nonce", b"header", b"message").unwrap();
+        let truncated = short.encrypt(b"nonce", b"header", b"message").unwrap();
+        assert_eq!(truncated[..7], sealed[..7]);
+        assert_eq!(truncated[7..], sealed[7..13]);
+        assert_eq!(
+            short.decrypt(b"nonce", b"header", &truncated).unwrap(),
+            b"message"
+        );
+
+        assert!(matches!(
+            Eax::new(&cipher, 0),
+            Err(Error::InvalidTagLength(0))
+        ));
+        assert!(matches!(
+            Eax::new(&cipher, 17),
+            Err(Error::InvalidTagLength(17))
+        ));
+    }
+
+    fn tamper<C: BlockEncrypt>(cipher: &C) {
+        let eax = Eax::new(cipher, C::BLOCK_SIZE).unwrap();
+        let sealed = eax.encrypt(b"nonce", b"header", b"attack at dawn").unwrap();
+
+        for i in 0..sealed.len() {
+            let mut forged = sealed.clone();
+            forged[i] ^= 0x01;
+            assert!(matches!(
+                eax.decrypt(b"nonce", b"header", &forged),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+        assert!(matches!(
+            eax.decrypt(b"nonce", b"Header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            eax.decrypt(b"Nonce", b"header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            eax.decrypt(b"nonce", b"header", &sealed[1..]),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            eax.decrypt(b"nonce", b"header", &sealed[..3]),
+            Err(Error::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn tampered_rc5_32() {
+        tamper(&rc5::<u32>(12, 16));
+    }
+
+    #[test]
+    fn tampered_rc5_64() {
+        tamper(&rc5::<u64>(24, 24));
+    }
+
+    #[test]
+    fn no_plaintext_on_failure() {
+        let cipher = rc5::<u32>(12, 16);
+        let eax = Eax::new(&cipher, 8).unwrap();
+
+        let mut buffer = b"secret message".to_vec();
+        let mut tag = [0; 8];
+        eax.encrypt_in_place(b"nonce", b"", &mut buffer, &mut tag)
+            .unwrap();
+        let ciphertext = buffer.clone();
+
+        tag[0] ^= 1;
+        assert!(matches!(
+            eax.decrypt_in_place(b"nonce", b"", &mut buffer, &tag),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert_eq!(buffer, ciphertext);
+    }
+}
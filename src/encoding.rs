@@ -0,0 +1,550 @@
+use crate::error::{Error, Result};
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/*
+Text encodings of binary data from RFC 4648.
+
+Each character carries 4, 5 or 6 bits, most significant first, and the bits left over
+after the last byte must be zero, so every byte string has exactly one encoding. With
+padded set, encoding fills the last group of characters out with '=', and decoding
+accepts input with or without it, but padding has to be complete when present. Without
+padded set, '=' is an invalid character.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    // Lowercase hex, decoding accepts either case.
+    Hex,
+    // The uppercase base32 alphabet, decoding accepts either case.
+    Base32 { padded: bool },
+    Base64 { padded: bool },
+    // base64 with '-' and '_' in place of '+' and '/', safe in URLs and file names.
+    Base64Url { padded: bool },
+}
+
+impl Encoding {
+    // Bits per character.
+    fn bits(&self) -> u32 {
+        match self {
+            Encoding::Hex => 4,
+            Encoding::Base32 { .. } => 5,
+            Encoding::Base64 { .. } | Encoding::Base64Url { .. } => 6,
+        }
+    }
+
+    // Characters in the smallest group that holds a whole number of bytes.
+    fn group_len(&self) -> usize {
+        match self {
+            Encoding::Hex => 2,
+            Encoding::Base32 { .. } => 8,
+            Encoding::Base64 { .. } | Encoding::Base64Url { .. } => 4,
+        }
+    }
+
+    fn padded(&self) -> bool {
+        match self {
+            Encoding::Hex => false,
+            Encoding::Base32 { padded }
+            | Encoding::Base64 { padded }
+            | Encoding::Base64Url { padded } => *padded,
+        }
+    }
+
+    fn symbol(&self, value: u32) -> u8 {
+        match self {
+            Encoding::Hex => HEX[value as usize],
+            Encoding::Base32 { .. } => BASE32[value as usize],
+            Encoding::Base64 { .. } => BASE64[value as usize],
+            Encoding::Base64Url { .. } => BASE64_URL[value as usize],
+        }
+    }
+
+    fn value(&self, c: u8) -> Option<u32> {
+        let alphabet: &[u8] = match self {
+            Encoding::Hex => HEX,
+            Encoding::Base32 { .. } => BASE32,
+            Encoding::Base64 { .. } => BASE64,
+            Encoding::Base64Url { .. } => BASE64_URL,
+        };
+        let c = match self {
+            Encoding::Hex => c.to_ascii_lowercase(),
+            Encoding::Base32 { .. } => c.to_ascii_uppercase(),
+            _ => c,
+        };
+        alphabet.iter().position(|&b| b == c).map(|v| v as u32)
+    }
+
+    // Whether text can end after chars characters of a group, i.e. the last one adds to a byte.
+    fn can_end(&self, chars: usize) -> bool {
+        chars as u32 * self.bits() % 8 < self.bits()
+    }
+
+    // The length of the encoding of len bytes.
+    pub fn encoded_len(&self, len: usize) -> usize {
+        let chars = (8 * len).div_ceil(self.bits() as usize);
+        if self.padded() {
+            chars.div_ceil(self.group_len()) * self.group_len()
+        } else {
+            chars
+        }
+    }
+
+    // The most bytes text of len characters can decode to.
+    pub fn decoded_len(&self, len: usize) -> usize {
+        len * self.bits() as usize / 8
+    }
+
+    // Writes the encoding of data to the start of out and returns its length.
+    pub fn encode_into(&self, data: &[u8], out: &mut [u8]) -> Result<usize> {
+        let len = self.encoded_len(data.len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let mut encoder = Encoder::new(*self);
+        let written = encoder.update(data, out)?;
+        Ok(written + encoder.finalize(&mut out[written..])?)
+    }
+
+    // Writes the bytes text decodes to at the start of out and returns how many there are.
+    pub fn decode_into(&self, text: &[u8], out: &mut [u8]) -> Result<usize> {
+        let mut decoder = Decoder::new(*self);
+        let len = decoder.update(text, out)?;
+        decoder.finalize()?;
+        Ok(len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encode(&self, data: &[u8]) -> String {
+        let mut out = vec![0; self.encoded_len(data.len())];
+        self.encode_into(data, &mut out)
+            .expect("the buffer is encoded_len bytes");
+        String::from_utf8(out).expect("encodings are ASCII")
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>> {
+        let mut out = vec![0; self.decoded_len(text.len())];
+        let len = self.decode_into(text.as_bytes(), &mut out)?;
+        out.truncate(len);
+        Ok(out)
+    }
+}
+
+// Encodes data given in pieces, as if it was all passed to encode_into at once.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    encoding: Encoding,
+    buffer: u32,
+    bits: u32,
+    chars: usize,
+}
+
+impl Encoder {
+    pub fn new(encoding: Encoding) -> Encoder {
+        Self {
+            encoding,
+            buffer: 0,
+            bits: 0,
+            chars: 0,
+        }
+    }
+
+    // How many characters update writes for len more bytes.
+    pub fn update_len(&self, len: usize) -> usize {
+        (self.bits as usize + 8 * len) / self.encoding.bits() as usize
+    }
+
+    // How many characters finalize writes.
+    pub fn finalize_len(&self) -> usize {
+        let chars = self.chars + (self.bits > 0) as usize;
+        if self.encoding.padded() {
+            chars.div_ceil(self.encoding.group_len()) * self.encoding.group_len() - self.chars
+        } else {
+            chars - self.chars
+        }
+    }
+
+    // Encodes data to the start of out, holding back bits that don't fill a character yet.
+    pub fn update(&mut self, data: &[u8], out: &mut [u8]) -> Result<usize> {
+        let len = self.update_len(data.len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let k = self.encoding.bits();
+        let mut written = 0;
+        for &byte in data {
+            self.buffer = (self.buffer << 8) | byte as u32;
+            self.bits += 8;
+            while self.bits >= k {
+                self.bits -= k;
+                out[written] = self
+                    .encoding
+                    .symbol((self.buffer >> self.bits) & ((1 << k) - 1));
+                written += 1;
+            }
+            self.buffer &= (1 << self.bits) - 1;
+        }
+        self.chars = (self.chars + written) % self.encoding.group_len();
+        Ok(written)
+    }
+
+    // Writes the held back bits, zero padded, and any '=' padding.
+    pub fn finalize(self, out: &mut [u8]) -> Result<usize> {
+        let len = self.finalize_len();
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let mut chars = out[..len].iter_mut();
+        if self.bits > 0 {
+            let k = self.encoding.bits();
+            *chars.next().unwrap() = self.encoding.symbol(self.buffer << (k - self.bits));
+        }
+        for c in chars {
+            *c = b'=';
+        }
+        Ok(len)
+    }
+}
+
+/*
+Decodes text given in pieces, as if it was all passed to decode_into at once. Error
+positions count from the start of the first piece.
+
+A lenient decoder skips ASCII whitespace, for text that has been wrapped or indented.
+*/
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    encoding: Encoding,
+    skip_whitespace: bool,
+    buffer: u32,
+    bits: u32,
+    chars: usize,
+    padding: usize,
+    position: usize,
+    last: usize,
+}
+
+impl Decoder {
+    pub fn new(encoding: Encoding) -> Decoder {
+        Self {
+            encoding,
+            skip_whitespace: false,
+            buffer: 0,
+            bits: 0,
+            chars: 0,
+            padding: 0,
+            position: 0,
+            last: 0,
+        }
+    }
+
+    pub fn lenient(encoding: Encoding) -> Decoder {
+        Self {
+            skip_whitespace: true,
+            ..Self::new(encoding)
+        }
+    }
+
+    // The most bytes update writes for len more characters.
+    pub fn update_len(&self, len: usize) -> usize {
+        (self.bits as usize + len * self.encoding.bits() as usize) / 8
+    }
+
+    // Decodes text to the start of out and returns the number of bytes written.
+    pub fn update(&mut self, text: &[u8], out: &mut [u8]) -> Result<usize> {
+        let len = self.update_len(text.len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let k = self.encoding.bits();
+        let mut written = 0;
+        for &c in text {
+            let position = self.position;
+            self.position += 1;
+            if self.skip_whitespace && c.is_ascii_whitespace() {
+                continue;
+            }
+
+            // Padding can only finish a group that stops partway through a byte, encodings
+            // without padding leave '=' to be rejected as a character outside the alphabet.
+            if c == b'=' && self.encoding.padded() {
+                if self.chars == 0
+                    || !self.encoding.can_end(self.chars)
+                    || self.chars + self.padding == self.encoding.group_len()
+                {
+                    return Err(Error::InvalidCharacter { position });
+                }
+                self.padding += 1;
+                continue;
+            }
+            if self.padding > 0 {
+                return Err(Error::InvalidCharacter { position });
+            }
+
+            let value = self
+                .encoding
+                .value(c)
+                .ok_or(Error::InvalidCharacter { position })?;
+            self.buffer = (self.buffer << k) | value;
+            self.bits += k;
+            self.chars = (self.chars + 1) % self.encoding.group_len();
+            self.last = position;
+            if self.bits >= 8 {
+                self.bits -= 8;
+                out[written] = (self.buffer >> self.bits) as u8;
+                written += 1;
+                self.buffer &= (1 << self.bits) - 1;
+            }
+        }
+        Ok(written)
+    }
+
+    // Checks that the text didn't stop partway through a byte or its padding.
+    pub fn finalize(self) -> Result<()> {
+        let incomplete_padding =
+            self.padding > 0 && self.chars + self.padding != self.encoding.group_len();
+        if incomplete_padding || !self.encoding.can_end(self.chars) {
+            return Err(Error::UnexpectedEnd {
+                position: self.position,
+            });
+        }
+        if self.buffer != 0 {
+            return Err(Error::InvalidCharacter {
+                position: self.last,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    const BASE64_PADDED: Encoding = Encoding::Base64 { padded: true };
+    const BASE32_PADDED: Encoding = Encoding::Base32 { padded: true };
+
+    // Test vectors from RFC 4648 section 10.
+    #[test]
+    fn rfc_4648() {
+        let vectors = [
+            ("", "", "", ""),
+            ("f", "66", "MY======", "Zg=="),
+            ("fo", "666f", "MZXQ====", "Zm8="),
+            ("foo", "666f6f", "MZXW6===", "Zm9v"),
+            ("foob", "666f6f62", "MZXW6YQ=", "Zm9vYg=="),
+            ("fooba", "666f6f6261", "MZXW6YTB", "Zm9vYmE="),
+            ("foobar", "666f6f626172", "MZXW6YTBOI======", "Zm9vYmFy"),
+        ];
+        for (data, hex, base32, base64) in vectors.iter() {
+            let data = data.as_bytes();
+            for (encoding, text) in [
+                (Encoding::Hex, *hex),
+                (BASE32_PADDED, *base32),
+                (BASE64_PADDED, *base64),
+            ]
+            .iter()
+            {
+                assert_eq!(encoding.encode(data), *text);
+                assert_eq!(encoding.decode(text).unwrap(), data);
+                assert_eq!(encoding.encoded_len(data.len()), text.len());
+
+                // Padding is optional when decoding.
+                let unpadded = text.trim_end_matches('=');
+                assert_eq!(encoding.decode(unpadded).unwrap(), data);
+            }
+            assert_eq!(
+                Encoding::Base32 { padded: false }.encode(data),
+                base32.trim_end_matches('=')
+            );
+        }
+    }
+
+    #[test]
+    fn case_and_alphabet() {
+        assert_eq!(Encoding::Hex.decode("C0fFeE").unwrap(), [0xC0, 0xFF, 0xEE]);
+        assert_eq!(
+            Encoding::Base32 { padded: false }
+                .decode("mzxw6ytb")
+                .unwrap(),
+            b"fooba"
+        );
+
+        let data = [0xFB, 0xFF, 0xBF];
+        assert_eq!(BASE64_PADDED.encode(&data), "+/+/");
+        let url = Encoding::Base64Url { padded: false };
+        assert_eq!(url.encode(&data), "-_-_");
+        assert_eq!(url.encode(&data[..1]), "-w");
+        assert_eq!(url.decode("-w").unwrap(), [0xFB]);
+        assert!(matches!(
+            url.decode("+/+/"),
+            Err(Error::InvalidCharacter { position: 0 })
+        ));
+        assert!(matches!(
+            BASE64_PADDED.decode("-_-_"),
+            Err(Error::InvalidCharacter { position: 0 })
+        ));
+    }
+
+    #[test]
+    fn errors_have_positions() {
+        // Odd lengths and multibyte characters used to panic in decode_hex.
+        assert!(matches!(
+            Encoding::Hex.decode("abc"),
+            Err(Error::UnexpectedEnd { position: 3 })
+        ));
+        assert!(matches!(
+            Encoding::Hex.decode("aé"),
+            Err(Error::InvalidCharacter { position: 1 })
+        ));
+        assert!(matches!(
+            Encoding::Hex.decode("00 11"),
+            Err(Error::InvalidCharacter { position: 2 })
+        ));
+        assert!(matches!(
+            Encoding::Hex.decode("0g"),
+            Err(Error::InvalidCharacter { position: 1 })
+        ));
+
+        for (text, error) in [
+            // Nonzero bits after the last byte.
+            ("Zh==", Error::InvalidCharacter { position: 1 }),
+            ("Zm9=", Error::InvalidCharacter { position: 2 }),
+            // A single character can't end a group.
+            ("Zm9vY", Error::UnexpectedEnd { position: 5 }),
+            ("Zm9vY===", Error::InvalidCharacter { position: 5 }),
+            ("Zg=", Error::UnexpectedEnd { position: 3 }),
+            ("Zg===", Error::InvalidCharacter { position: 4 }),
+            ("Zg==Zg==", Error::InvalidCharacter { position: 4 }),
+            ("=Zg=", Error::InvalidCharacter { position: 0 }),
+            ("Zm9v=", Error::InvalidCharacter { position: 4 }),
+        ]
+        .iter()
+        {
+            let res = BASE64_PADDED.decode(text);
+            assert_eq!(format!("{:?}", res), format!("{:?}", Err::<(), _>(error)));
+        }
+
+        assert!(matches!(
+            BASE32_PADDED.decode("MZXW6YR"),
+            Err(Error::InvalidCharacter { position: 6 })
+        ));
+        for len in [1, 3, 6].iter() {
+            assert!(matches!(
+                BASE32_PADDED.decode(&"MZXW6YTB"[..*len]),
+                Err(Error::UnexpectedEnd { position: p }) if p == *len
+            ));
+        }
+    }
+
+    // Without padding, '=' is rejected even where it would complete a group.
+    #[test]
+    fn unpadded_rejects_padding() {
+        for (encoding, text, position, data) in [
+            (
+                Encoding::Base32 { padded: false },
+                "MZXW6YQ=",
+                7,
+                &b"foob"[..],
+            ),
+            (Encoding::Base32 { padded: false }, "MY======", 2, b"f"),
+            (Encoding::Base64 { padded: false }, "Zm9vYg==", 6, b"foob"),
+            (Encoding::Base64Url { padded: false }, "-w==", 2, &[0xFB]),
+            (Encoding::Base64Url { padded: false }, "=", 0, b""),
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                encoding.decode(text),
+                Err(Error::InvalidCharacter { position: p }) if p == *position
+            ));
+            assert_eq!(encoding.decode(text.trim_end_matches('=')).unwrap(), *data);
+        }
+
+        // The position counts from the start of the stream.
+        let mut decoder = Decoder::new(Encoding::Base32 { padded: false });
+        let mut out = [0; 8];
+        assert_eq!(decoder.update(b"MZXW6YQ", &mut out).unwrap(), 4);
+        assert!(matches!(
+            decoder.update(b"=", &mut out),
+            Err(Error::InvalidCharacter { position: 7 })
+        ));
+    }
+
+    #[test]
+    fn buffer_sizes() {
+        let mut out = [0; 7];
+        assert!(matches!(
+            BASE64_PADDED.encode_into(b"fooba", &mut out),
+            Err(Error::BufferTooSmall { needed: 8 })
+        ));
+        let mut out = [0; 2];
+        assert!(matches!(
+            Encoding::Hex.decode_into(b"666f6f", &mut out),
+            Err(Error::BufferTooSmall { needed: 3 })
+        ));
+    }
+
+    // Splitting the input anywhere gives the same result as encoding or decoding at once.
+    #[test]
+    fn streaming() {
+        let data: Vec<u8> = (0..100u8).map(|i| i.wrapping_mul(37)).collect();
+        for encoding in [
+            Encoding::Hex,
+            BASE32_PADDED,
+            Encoding::Base32 { padded: false },
+            BASE64_PADDED,
+            Encoding::Base64Url { padded: false },
+        ]
+        .iter()
+        {
+            let text = encoding.encode(&data);
+            for step in 1..12 {
+                let mut encoder = Encoder::new(*encoding);
+                let mut streamed = Vec::new();
+                for chunk in data.chunks(step) {
+                    let mut out = vec![0; encoder.update_len(chunk.len())];
+                    assert_eq!(encoder.update(chunk, &mut out).unwrap(), out.len());
+                    streamed.extend_from_slice(&out);
+                }
+                let mut out = vec![0; encoder.finalize_len()];
+                encoder.finalize(&mut out).unwrap();
+                streamed.extend_from_slice(&out);
+                assert_eq!(streamed, text.as_bytes());
+
+                let mut decoder = Decoder::new(*encoding);
+                let mut decoded = Vec::new();
+                for chunk in text.as_bytes().chunks(step) {
+                    let mut out = vec![0; decoder.update_len(chunk.len())];
+                    let len = decoder.update(chunk, &mut out).unwrap();
+                    decoded.extend_from_slice(&out[..len]);
+                }
+                decoder.finalize().unwrap();
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn lenient_decoder() {
+        let text = b"Zm9v\r\n  YmFy\n\tZg= =\n";
+        let mut decoder = Decoder::lenient(BASE64_PADDED);
+        let mut out = [0; 16];
+        let len = decoder.update(text, &mut out).unwrap();
+        decoder.finalize().unwrap();
+        assert_eq!(&out[..len], b"foobarf");
+
+        let mut decoder = Decoder::lenient(BASE64_PADDED);
+        assert!(matches!(
+            decoder.update(b"Zm9v\n  Ym*y", &mut out),
+            Err(Error::InvalidCharacter { position: 9 })
+        ));
+    }
+}
@@ -0,0 +1,826 @@
+use crate::aead::Aead;
+use crate::block_cipher::{BlockCipher, MAX_BLOCK_SIZE};
+use crate::cbc::Cbc;
+use crate::checksum::crc32;
+use crate::ctr::apply_keystream;
+use crate::eax::Eax;
+use crate::error::{Error, Result};
+use crate::mac::Cmac;
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+/*
+A self-describing container for a message encrypted with RC5, so it can be decrypted
+without remembering the configuration out of band.
+
+    magic       4 bytes   "RC5E"
+    version     1 byte    ENVELOPE_VERSION
+    w           2 bytes   word size in bits, little-endian
+    r           1 byte    number of rounds
+    mode        1 byte    1 CBC, 2 CTR, 3 EAX
+    tag_len     1 byte    length of the MAC at the end, 0 for CBC or CTR without one
+    nonce_len   1 byte
+    key_id_len  1 byte
+    nonce       nonce_len bytes, the IV for CBC and the first counter block for CTR
+    key_id      key_id_len bytes, names the key for whoever opens the envelope
+    checksum    4 bytes   CRC-32 of the header before it, little-endian
+    ciphertext            padded for CBC
+    tag         tag_len bytes
+
+The whole header is the associated data of EAX, so with EAX any change to the envelope
+is caught. CBC and CTR with a tag are encrypt-then-MAC, the tag is CMAC over the header and
+ciphertext under a second key. Without a tag the checksum only catches accidental damage to
+the header, and nothing catches changes to the ciphertext.
+
+A tag is at least MIN_TAG_LEN bytes, or a whole block for ciphers with shorter blocks, so
+tag_len can't be lowered to make forging it easy.
+*/
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"RC5E";
+pub const ENVELOPE_VERSION: u8 = 1;
+
+pub const MIN_TAG_LEN: usize = 8;
+
+const FIXED_LEN: usize = 12;
+const CHECKSUM_LEN: usize = 4;
+
+// Tags are at least MIN_TAG_LEN bytes and at most one block, CBC and CTR leave them out
+// when tag_len is 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeMode {
+    // CBC with PKCS#7 padding, the nonce is the IV and must be one block.
+    Cbc { tag_len: u8 },
+    // CTR over the whole block, the nonce is the first counter block.
+    Ctr { tag_len: u8 },
+    // EAX, the nonce can be any length up to 255 bytes.
+    Eax { tag_len: u8 },
+}
+
+impl EnvelopeMode {
+    fn id(&self) -> u8 {
+        match self {
+            EnvelopeMode::Cbc { .. } => 1,
+            EnvelopeMode::Ctr { .. } => 2,
+            EnvelopeMode::Eax { .. } => 3,
+        }
+    }
+
+    fn tag_len(&self) -> usize {
+        match self {
+            EnvelopeMode::Cbc { tag_len }
+            | EnvelopeMode::Ctr { tag_len }
+            | EnvelopeMode::Eax { tag_len } => *tag_len as usize,
+        }
+    }
+
+    // Whether the tag is a CMAC under the second key, rather than none or EAX's own.
+    fn uses_cmac(&self) -> bool {
+        !matches!(self, EnvelopeMode::Eax { .. }) && self.tag_len() > 0
+    }
+
+    // Checks the tag and nonce lengths for a cipher with blocks of n bytes.
+    fn check(&self, n: usize, nonce_len: usize) -> Result<()> {
+        match self {
+            EnvelopeMode::Cbc { .. } | EnvelopeMode::Ctr { .. } if nonce_len != n => {
+                Err(Error::InvalidNonceLength(nonce_len))
+            }
+            EnvelopeMode::Eax { tag_len: 0 } => Err(Error::InvalidTagLength(0)),
+            _ if self.tag_len() > n
+                || (self.tag_len() > 0 && self.tag_len() < MIN_TAG_LEN.min(n)) =>
+            {
+                Err(Error::InvalidTagLength(self.tag_len()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// A parsed and checked envelope, nothing in it has been decrypted or authenticated yet.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvelopeHeader<'a> {
+    word_size: u16,
+    rounds: u8,
+    mode: EnvelopeMode,
+    nonce: &'a [u8],
+    key_id: &'a [u8],
+    header: &'a [u8],
+    ciphertext: &'a [u8],
+    tag: &'a [u8],
+}
+
+impl<'a> EnvelopeHeader<'a> {
+    /*
+    Checks the header and the lengths of what follows it, so the key can be looked up
+    from key_id() and the cipher set up before anything is decrypted.
+    */
+    pub fn parse(sealed: &'a [u8]) -> Result<EnvelopeHeader<'a>> {
+        if sealed.len() < FIXED_LEN {
+            return Err(Error::InvalidLength {
+                expected: FIXED_LEN,
+                found: sealed.len(),
+            });
+        }
+        if sealed[0..4] != ENVELOPE_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        if sealed[4] != ENVELOPE_VERSION {
+            return Err(Error::UnsupportedVersion(sealed[4]));
+        }
+
+        let word_size = u16::from_le_bytes([sealed[5], sealed[6]]);
+        if word_size == 0 || !word_size.is_multiple_of(8) || word_size as usize > 4 * MAX_BLOCK_SIZE
+        {
+            return Err(Error::UnsupportedWordSize(word_size));
+        }
+        let n = word_size as usize / 4;
+        let rounds = sealed[7];
+
+        let tag_len = sealed[9];
+        let mode = match sealed[8] {
+            1 => EnvelopeMode::Cbc { tag_len },
+            2 => EnvelopeMode::Ctr { tag_len },
+            3 => EnvelopeMode::Eax { tag_len },
+            other => return Err(Error::UnsupportedMode(other)),
+        };
+        let nonce_len = sealed[10] as usize;
+        mode.check(n, nonce_len)?;
+
+        let key_id_len = sealed[11] as usize;
+        let header_len = FIXED_LEN + nonce_len + key_id_len + CHECKSUM_LEN;
+        if sealed.len() < header_len + mode.tag_len() {
+            return Err(Error::InvalidLength {
+                expected: header_len + mode.tag_len(),
+                found: sealed.len(),
+            });
+        }
+
+        let checksum_at = header_len - CHECKSUM_LEN;
+        let mut checksum = [0; CHECKSUM_LEN];
+        checksum.copy_from_slice(&sealed[checksum_at..header_len]);
+        if u32::from_le_bytes(checksum) != crc32(&sealed[..checksum_at]) {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        let (header, body) = sealed.split_at(header_len);
+        let (ciphertext, tag) = body.split_at(body.len() - mode.tag_len());
+        if matches!(mode, EnvelopeMode::Cbc { .. })
+            && (ciphertext.is_empty() || !ciphertext.len().is_multiple_of(n))
+        {
+            return Err(Error::InvalidLength {
+                expected: (ciphertext.len() / n + 1) * n,
+                found: ciphertext.len(),
+            });
+        }
+
+        Ok(Self {
+            word_size,
+            rounds,
+            mode,
+            nonce: &header[FIXED_LEN..FIXED_LEN + nonce_len],
+            key_id: &header[FIXED_LEN + nonce_len..checksum_at],
+            header,
+            ciphertext,
+            tag,
+        })
+    }
+
+    // The RC5 word size w in bits.
+    pub fn word_size(&self) -> u16 {
+        self.word_size
+    }
+
+    pub fn rounds(&self) -> u8 {
+        self.rounds
+    }
+
+    pub fn mode(&self) -> EnvelopeMode {
+        self.mode
+    }
+
+    pub fn nonce(&self) -> &'a [u8] {
+        self.nonce
+    }
+
+    pub fn key_id(&self) -> &'a [u8] {
+        self.key_id
+    }
+
+    // The length of the ciphertext, which bounds the length of the plaintext.
+    pub fn ciphertext_len(&self) -> usize {
+        self.ciphertext.len()
+    }
+}
+
+/*
+Seals and opens envelopes with one RC5 key, named by key_id, and optionally a second key for
+the CMAC tags of CBC and CTR envelopes.
+
+An Envelope with a MAC key refuses CBC and CTR envelopes without a tag, so stripping the tag
+and fixing up the header doesn't get an envelope past it.
+*/
+pub struct Envelope<'a, T> {
+    cipher: &'a Rc5<T>,
+    mac_cipher: Option<&'a Rc5<T>>,
+    key_id: &'a [u8],
+}
+
+impl<'a, T> Envelope<'a, T>
+where
+    T: UInt,
+{
+    // The key id can be up to 255 bytes, and isn't secret.
+    pub fn new(cipher: &'a Rc5<T>, key_id: &'a [u8]) -> Result<Envelope<'a, T>> {
+        if key_id.len() > u8::MAX as usize {
+            return Err(Error::InvalidLength {
+                expected: u8::MAX as usize,
+                found: key_id.len(),
+            });
+        }
+        Ok(Self {
+            cipher,
+            mac_cipher: None,
+            key_id,
+        })
+    }
+
+    // The MAC cipher must be keyed independently of cipher.
+    pub fn with_mac(
+        cipher: &'a Rc5<T>,
+        mac_cipher: &'a Rc5<T>,
+        key_id: &'a [u8],
+    ) -> Result<Envelope<'a, T>> {
+        let mut envelope = Self::new(cipher, key_id)?;
+        envelope.mac_cipher = Some(mac_cipher);
+        Ok(envelope)
+    }
+
+    // CMAC over the header and ciphertext, for CBC and CTR with a tag.
+    fn cmac(&self, header: &[u8], ciphertext: &[u8]) -> Result<Cmac<'a, Rc5<T>>> {
+        let mut mac = Cmac::new(self.mac_cipher.ok_or(Error::MacKeyRequired)?)?;
+        mac.update(header);
+        mac.update(ciphertext);
+        Ok(mac)
+    }
+
+    fn header_len(&self, nonce_len: usize) -> usize {
+        FIXED_LEN + nonce_len + self.key_id.len() + CHECKSUM_LEN
+    }
+
+    // The envelope length for a message of msg_len bytes.
+    pub fn sealed_len(&self, mode: EnvelopeMode, nonce_len: usize, msg_len: usize) -> usize {
+        let n = Rc5::<T>::BLOCK_SIZE;
+        let body_len = match mode {
+            EnvelopeMode::Cbc { .. } => (msg_len / n + 1) * n,
+            _ => msg_len,
+        };
+        self.header_len(nonce_len) + body_len + mode.tag_len()
+    }
+
+    // Writes the envelope for plaintext to the start of out and returns its length.
+    pub fn seal_into(
+        &self,
+        mode: EnvelopeMode,
+        nonce: &[u8],
+        plaintext: &[u8],
+        out: &mut [u8],
+    ) -> Result<usize> {
+        let n = Rc5::<T>::BLOCK_SIZE;
+        if nonce.len() > u8::MAX as usize {
+            return Err(Error::InvalidNonceLength(nonce.len()));
+        }
+        mode.check(n, nonce.len())?;
+        if mode.uses_cmac() && self.mac_cipher.is_none() {
+            return Err(Error::MacKeyRequired);
+        }
+        let len = self.sealed_len(mode, nonce.len(), plaintext.len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let header_len = self.header_len(nonce.len());
+        let checksum_at = header_len - CHECKSUM_LEN;
+        out[0..4].copy_from_slice(&ENVELOPE_MAGIC);
+        out[4] = ENVELOPE_VERSION;
+        out[5..7].copy_from_slice(&(T::w() as u16).to_le_bytes());
+        out[7] = self.cipher.rounds();
+        out[8] = mode.id();
+        out[9] = mode.tag_len() as u8;
+        out[10] = nonce.len() as u8;
+        out[11] = self.key_id.len() as u8;
+        out[FIXED_LEN..FIXED_LEN + nonce.len()].copy_from_slice(nonce);
+        out[FIXED_LEN + nonce.len()..checksum_at].copy_from_slice(self.key_id);
+        let checksum = crc32(&out[..checksum_at]);
+        out[checksum_at..header_len].copy_from_slice(&checksum.to_le_bytes());
+
+        let (header, body) = out[..len].split_at_mut(header_len);
+        let (ciphertext, tag) = body.split_at_mut(body.len() - mode.tag_len());
+        ciphertext[..plaintext.len()].copy_from_slice(plaintext);
+        match mode {
+            EnvelopeMode::Cbc { .. } => {
+                Cbc::new(self.cipher).encrypt_padded_in_place(
+                    nonce,
+                    ciphertext,
+                    plaintext.len(),
+                )?;
+            }
+            EnvelopeMode::Ctr { .. } => {
+                let mut counter = [0; MAX_BLOCK_SIZE];
+                counter[..n].copy_from_slice(nonce);
+                apply_keystream(self.cipher, &mut counter[..n], n, ciphertext);
+            }
+            EnvelopeMode::Eax { tag_len } => {
+                Eax::new(self.cipher, tag_len as usize)?
+                    .encrypt_in_place(nonce, header, ciphertext, tag)?;
+            }
+        }
+        if mode.uses_cmac() {
+            self.cmac(header, ciphertext)?.finalize_into(tag)?;
+        }
+        Ok(len)
+    }
+
+    /*
+    Opens an envelope sealed with this key, writing the plaintext to the start of out and
+    returning its length. out needs room for the whole ciphertext. The header is checked
+    first, and must name this key id, word size and number of rounds. A CMAC tag is checked
+    before anything is decrypted.
+    */
+    pub fn open_into(&self, sealed: &[u8], out: &mut [u8]) -> Result<usize> {
+        let envelope = EnvelopeHeader::parse(sealed)?;
+        if envelope.word_size as usize != T::w() {
+            return Err(Error::WordSizeMismatch {
+                expected: T::w(),
+                found: envelope.word_size as usize,
+            });
+        }
+        if envelope.rounds != self.cipher.rounds() {
+            return Err(Error::RoundsMismatch {
+                expected: self.cipher.rounds(),
+                found: envelope.rounds,
+            });
+        }
+        if envelope.key_id != self.key_id {
+            return Err(Error::KeyIdMismatch);
+        }
+        if envelope.mode.uses_cmac() {
+            self.cmac(envelope.header, envelope.ciphertext)?
+                .verify(envelope.tag)?;
+        } else if self.mac_cipher.is_some() && envelope.tag.is_empty() {
+            return Err(Error::AuthenticationFailed);
+        }
+        let len = envelope.ciphertext.len();
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let n = Rc5::<T>::BLOCK_SIZE;
+        let buffer = &mut out[..len];
+        buffer.copy_from_slice(envelope.ciphertext);
+        match envelope.mode {
+            EnvelopeMode::Cbc { .. } => {
+                Cbc::new(self.cipher).decrypt_padded_in_place(envelope.nonce, buffer)
+            }
+            EnvelopeMode::Ctr { .. } => {
+                let mut counter = [0; MAX_BLOCK_SIZE];
+                counter[..n].copy_from_slice(envelope.nonce);
+                apply_keystream(self.cipher, &mut counter[..n], n, buffer);
+                Ok(len)
+            }
+            EnvelopeMode::Eax { tag_len } => {
+                Eax::new(self.cipher, tag_len as usize)?.decrypt_in_place(
+                    envelope.nonce,
+                    envelope.header,
+                    buffer,
+                    envelope.tag,
+                )?;
+                Ok(len)
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn seal(&self, mode: EnvelopeMode, nonce: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; self.sealed_len(mode, nonce.len(), plaintext.len())];
+        self.seal_into(mode, nonce, plaintext, &mut out)?;
+        Ok(out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; sealed.len()];
+        let len = self.open_into(sealed, &mut out)?;
+        out.truncate(len);
+        Ok(out)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rng::CounterRng;
+    use crate::testing::{bytes, rc5, rc5_with_key};
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py envelope`.
+    #[test]
+    fn known_answers() {
+        let cipher = rc5::<u32>(12, 16);
+        let mac_cipher = rc5_with_key::<u32>(12, &bytes(100, 16));
+        let envelope = Envelope::with_mac(&cipher, &mac_cipher, b"k1").unwrap();
+        for (mode, nonce, sealed) in [
+            (
+                EnvelopeMode::Cbc { tag_len: 0 },
+                bytes(0xA0, 8),
+                "524335450120000C01000802A0A1A2A3A4A5A6A76B31E9DE\
+                 6012EC4A1AA268DC68AC3ACC50CB597C519C",
+            ),
+            (
+                EnvelopeMode::Ctr { tag_len: 0 },
+                bytes(0xA0, 8),
+                "524335450120000C02000802A0A1A2A3A4A5A6A76B3132FB\
+                 016EE7507599ED3D9B92097885490DBC",
+            ),
+            (
+                EnvelopeMode::Eax { tag_len: 8 },
+                bytes(0xA0, 12),
+                "524335450120000C03080C02A0A1A2A3A4A5A6A7A8A9AAAB6B319C42\
+                 B3FA0D6619FFCA21B3CAEA49887593664F8A3F8FE32CFE7B",
+            ),
+            (
+                EnvelopeMode::Cbc { tag_len: 8 },
+                bytes(0xA0, 8),
+                "524335450120000C01080802A0A1A2A3A4A5A6A76B31840D\
+                 6348EC4A1AA268DC68AC3ACC50CB597C519CE2E8D1F0A7D4DBC7",
+            ),
+            (
+                EnvelopeMode::Ctr { tag_len: 8 },
+                bytes(0xA0, 8),
+                "524335450120000C02080802A0A1A2A3A4A5A6A76B315F28\
+                 0234E7507599ED3D9B92097885490DBC0B61137ED2BEA522",
+            ),
+        ]
+        .iter()
+        {
+            let sealed = decode_hex(sealed).unwrap();
+            assert_eq!(
+                envelope.seal(*mode, nonce, b"attack at dawn").unwrap(),
+                sealed
+            );
+            // Envelopes without a tag only open without a MAC key.
+            let opener = if mode.tag_len() == 0 {
+                Envelope::new(&cipher, b"k1").unwrap()
+            } else {
+                Envelope::with_mac(&cipher, &mac_cipher, b"k1").unwrap()
+            };
+            assert_eq!(opener.open(&sealed).unwrap(), b"attack at dawn");
+        }
+
+        let cipher = rc5::<u64>(16, 24);
+        let envelope = Envelope::new(&cipher, b"").unwrap();
+        let sealed = decode_hex(
+            "524335450140001003100800B0B1B2B3B4B5B6B7D302293CEA58BBF1708BE021C6024A\
+             ACF2EB3D7201A4C1E9FE4D47D6F10DEB4C3F68",
+        )
+        .unwrap();
+        let mode = EnvelopeMode::Eax { tag_len: 16 };
+        assert_eq!(
+            envelope
+                .seal(mode, &bytes(0xB0, 8), b"attack at dawn")
+                .unwrap(),
+            sealed
+        );
+
+        let header = EnvelopeHeader::parse(&sealed).unwrap();
+        assert_eq!(header.word_size(), 64);
+        assert_eq!(header.rounds(), 16);
+        assert_eq!(header.mode(), mode);
+        assert_eq!(header.nonce(), &bytes(0xB0, 8)[..]);
+        assert_eq!(header.key_id(), b"");
+        assert_eq!(header.ciphertext_len(), 14);
+    }
+
+    #[test]
+    fn round_trip_lengths() {
+        let cipher = rc5::<u16>(12, 8);
+        let mac_cipher = rc5_with_key::<u16>(12, &bytes(8, 8));
+        let envelope = Envelope::new(&cipher, b"backup key").unwrap();
+        let authenticated = Envelope::with_mac(&cipher, &mac_cipher, b"backup key").unwrap();
+        for (envelope, mode) in [
+            (&envelope, EnvelopeMode::Cbc { tag_len: 0 }),
+            (&envelope, EnvelopeMode::Ctr { tag_len: 0 }),
+            (&envelope, EnvelopeMode::Eax { tag_len: 4 }),
+            (&authenticated, EnvelopeMode::Cbc { tag_len: 4 }),
+            (&authenticated, EnvelopeMode::Ctr { tag_len: 4 }),
+            (&authenticated, EnvelopeMode::Eax { tag_len: 4 }),
+        ]
+        .iter()
+        {
+            for len in 0..20 {
+                let plaintext = bytes(0, len);
+                let sealed = envelope.seal(*mode, &[7; 4], &plaintext).unwrap();
+                assert_eq!(sealed.len(), envelope.sealed_len(*mode, 4, len));
+                assert_eq!(envelope.open(&sealed).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let cipher = rc5::<u32>(12, 16);
+        let envelope = Envelope::new(&cipher, b"").unwrap();
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Cbc { tag_len: 0 }, &[0; 16], b""),
+            Err(Error::InvalidNonceLength(16))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Ctr { tag_len: 0 }, &[0; 4], b""),
+            Err(Error::InvalidNonceLength(4))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Cbc { tag_len: 8 }, &[0; 8], b""),
+            Err(Error::MacKeyRequired)
+        ));
+        let mac_cipher = rc5_with_key::<u32>(12, &bytes(100, 16));
+        assert!(matches!(
+            Envelope::with_mac(&cipher, &mac_cipher, b"").unwrap().seal(
+                EnvelopeMode::Ctr { tag_len: 9 },
+                &[0; 8],
+                b""
+            ),
+            Err(Error::InvalidTagLength(9))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Eax { tag_len: 0 }, &[], b""),
+            Err(Error::InvalidTagLength(0))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Eax { tag_len: 4 }, &[], b""),
+            Err(Error::InvalidTagLength(4))
+        ));
+        assert!(matches!(
+            Envelope::new(&rc5::<u16>(12, 8), b"").unwrap().seal(
+                EnvelopeMode::Eax { tag_len: 3 },
+                &[],
+                b""
+            ),
+            Err(Error::InvalidTagLength(3))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Eax { tag_len: 9 }, &[], b""),
+            Err(Error::InvalidTagLength(9))
+        ));
+        assert!(matches!(
+            envelope.seal(EnvelopeMode::Eax { tag_len: 8 }, &[0; 256], b""),
+            Err(Error::InvalidNonceLength(256))
+        ));
+        assert!(matches!(
+            Envelope::new(&cipher, &[0; 256]),
+            Err(Error::InvalidLength {
+                expected: 255,
+                found: 256
+            })
+        ));
+        let mut out = [0; 32];
+        assert!(matches!(
+            envelope.seal_into(
+                EnvelopeMode::Cbc { tag_len: 0 },
+                &[0; 8],
+                &[0; 16],
+                &mut out
+            ),
+            Err(Error::BufferTooSmall { needed: 48 })
+        ));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let cipher = rc5::<u32>(12, 16);
+        let sealed = Envelope::new(&cipher, b"k1")
+            .unwrap()
+            .seal(EnvelopeMode::Eax { tag_len: 8 }, &[1; 12], b"secret")
+            .unwrap();
+
+        assert!(matches!(
+            Envelope::new(&cipher, b"k2").unwrap().open(&sealed),
+            Err(Error::KeyIdMismatch)
+        ));
+        assert!(matches!(
+            Envelope::new(&rc5::<u32>(16, 16), b"k1")
+                .unwrap()
+                .open(&sealed),
+            Err(Error::RoundsMismatch {
+                expected: 16,
+                found: 12
+            })
+        ));
+        assert!(matches!(
+            Envelope::new(&rc5::<u64>(12, 16), b"k1")
+                .unwrap()
+                .open(&sealed),
+            Err(Error::WordSizeMismatch {
+                expected: 64,
+                found: 32
+            })
+        ));
+        assert!(matches!(
+            Envelope::new(&rc5::<u32>(12, 15), b"k1")
+                .unwrap()
+                .open(&sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn cmac_tags() {
+        let cipher = rc5::<u32>(12, 16);
+        let mac_cipher = rc5_with_key::<u32>(12, &bytes(100, 16));
+        let envelope = Envelope::with_mac(&cipher, &mac_cipher, b"k1").unwrap();
+        let sealed = envelope
+            .seal(EnvelopeMode::Cbc { tag_len: 8 }, &[0; 8], b"message")
+            .unwrap();
+
+        // A tagged envelope needs the MAC key, and the right one.
+        assert!(matches!(
+            Envelope::new(&cipher, b"k1").unwrap().open(&sealed),
+            Err(Error::MacKeyRequired)
+        ));
+        assert!(matches!(
+            Envelope::with_mac(&cipher, &cipher, b"k1")
+                .unwrap()
+                .open(&sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+
+        // Every byte after the checksum is covered by the tag.
+        for at in 26..sealed.len() {
+            let mut damaged = sealed.clone();
+            damaged[at] ^= 1;
+            assert!(matches!(
+                envelope.open(&damaged),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+
+        // Stripping the tag and fixing up the header doesn't get past a MAC key.
+        let mut stripped = sealed[..sealed.len() - 8].to_vec();
+        stripped[9] = 0;
+        let checksum = crc32(&stripped[..22]);
+        stripped[22..26].copy_from_slice(&checksum.to_le_bytes());
+        assert!(Envelope::new(&cipher, b"k1")
+            .unwrap()
+            .open(&stripped)
+            .is_ok());
+        assert!(matches!(
+            envelope.open(&stripped),
+            Err(Error::AuthenticationFailed)
+        ));
+
+        // Nor does truncating the tag, with CMAC or with EAX.
+        let eax = envelope
+            .seal(EnvelopeMode::Eax { tag_len: 8 }, &[0; 12], b"message")
+            .unwrap();
+        for (sealed, checksum_at) in [(&sealed, 22), (&eax, 26)].iter() {
+            let mut truncated = sealed[..sealed.len() - 7].to_vec();
+            truncated[9] = 1;
+            let checksum = crc32(&truncated[..*checksum_at]);
+            truncated[*checksum_at..checksum_at + 4].copy_from_slice(&checksum.to_le_bytes());
+            assert!(matches!(
+                envelope.open(&truncated),
+                Err(Error::InvalidTagLength(1))
+            ));
+        }
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let cipher = rc5::<u32>(12, 16);
+        let sealed = Envelope::new(&cipher, b"k1")
+            .unwrap()
+            .seal(EnvelopeMode::Ctr { tag_len: 0 }, &[0; 8], b"message")
+            .unwrap();
+
+        // Changes a header byte and fixes up the checksum, so the field check is reached.
+        let modified = |at: usize, value: u8| {
+            let mut sealed = sealed.clone();
+            sealed[at] = value;
+            let checksum = crc32(&sealed[..22]);
+            sealed[22..26].copy_from_slice(&checksum.to_le_bytes());
+            EnvelopeHeader::parse(&sealed).map(|_| ())
+        };
+        assert!(matches!(modified(0, b'X'), Err(Error::InvalidMagic)));
+        assert!(matches!(modified(4, 2), Err(Error::UnsupportedVersion(2))));
+        assert!(matches!(
+            modified(5, 33),
+            Err(Error::UnsupportedWordSize(33))
+        ));
+        assert!(matches!(
+            modified(6, 1),
+            Err(Error::UnsupportedWordSize(288))
+        ));
+        assert!(matches!(modified(8, 0), Err(Error::UnsupportedMode(0))));
+        assert!(matches!(modified(8, 4), Err(Error::UnsupportedMode(4))));
+        assert!(matches!(modified(9, 9), Err(Error::InvalidTagLength(9))));
+        assert!(matches!(modified(8, 3), Err(Error::InvalidTagLength(0))));
+        assert!(matches!(modified(5, 16), Err(Error::InvalidNonceLength(8))));
+        assert!(matches!(modified(11, 3), Err(Error::ChecksumMismatch)));
+        assert!(matches!(
+            modified(11, 200),
+            Err(Error::InvalidLength { .. })
+        ));
+
+        let mut damaged = sealed.clone();
+        damaged[12] ^= 1;
+        assert!(matches!(
+            EnvelopeHeader::parse(&damaged),
+            Err(Error::ChecksumMismatch)
+        ));
+
+        // A CBC body must be a nonzero number of blocks.
+        let cbc = Envelope::new(&cipher, b"k1")
+            .unwrap()
+            .seal(EnvelopeMode::Cbc { tag_len: 0 }, &[0; 8], b"message")
+            .unwrap();
+        for len in [26, 30, 33].iter() {
+            assert!(matches!(
+                EnvelopeHeader::parse(&cbc[..*len]),
+                Err(Error::InvalidLength { .. })
+            ));
+        }
+    }
+
+    // Random damage must give an error or, for the modes without a tag, some plaintext,
+    // but never a panic. Nothing damaged may open when there is a tag.
+    #[test]
+    fn fuzz_malformed() {
+        let cipher = rc5::<u32>(12, 16);
+        let mac_cipher = rc5_with_key::<u32>(12, &bytes(100, 16));
+        let plain = Envelope::new(&cipher, b"key id").unwrap();
+        let authenticated = Envelope::with_mac(&cipher, &mac_cipher, b"key id").unwrap();
+        let mut rng = CounterRng::<u64>::new(8, 48, 0).unwrap();
+
+        for (envelope, mode) in [
+            (&plain, EnvelopeMode::Cbc { tag_len: 0 }),
+            (&plain, EnvelopeMode::Ctr { tag_len: 0 }),
+            (&plain, EnvelopeMode::Eax { tag_len: 8 }),
+            (&authenticated, EnvelopeMode::Cbc { tag_len: 8 }),
+            (&authenticated, EnvelopeMode::Ctr { tag_len: 8 }),
+        ]
+        .iter()
+        {
+            let tagged = mode.tag_len() > 0;
+            let nonce = if let EnvelopeMode::Eax { .. } = mode {
+                bytes(0, 12)
+            } else {
+                bytes(0, 8)
+            };
+            let sealed = envelope.seal(*mode, &nonce, b"fuzzing the parser").unwrap();
+
+            for len in 0..sealed.len() {
+                let res = envelope.open(&sealed[..len]);
+                let ctr = matches!(mode, EnvelopeMode::Ctr { .. });
+                assert!(res.is_err() || (!tagged && ctr && len >= 30));
+            }
+
+            for _ in 0..2000 {
+                let mut damaged = sealed.clone();
+                match rng.below(4) {
+                    0 => {
+                        let at = rng.below(damaged.len() as u64) as usize;
+                        damaged[at] ^= 1 << rng.below(8);
+                    }
+                    1 => {
+                        let at = rng.below(damaged.len() as u64) as usize;
+                        damaged[at] = rng.next_u32() as u8;
+                    }
+                    2 => {
+                        let at = rng.below(damaged.len() as u64 + 1) as usize;
+                        damaged.insert(at, rng.next_u32() as u8);
+                    }
+                    _ => {
+                        let at = rng.below(damaged.len() as u64) as usize;
+                        damaged.remove(at);
+                    }
+                }
+                if damaged == sealed {
+                    continue;
+                }
+
+                let _ = EnvelopeHeader::parse(&damaged);
+                let res = envelope.open(&damaged);
+                if tagged {
+                    assert!(res.is_err());
+                }
+            }
+
+            // Random bytes behind a valid magic and version.
+            for _ in 0..2000 {
+                let mut random = vec![0; rng.below(64) as usize];
+                rng.fill_bytes(&mut random);
+                if random.len() >= 5 {
+                    random[..4].copy_from_slice(&ENVELOPE_MAGIC);
+                    random[4] = ENVELOPE_VERSION;
+                }
+                assert!(envelope.open(&random).is_err());
+            }
+        }
+    }
+}
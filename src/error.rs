@@ -1,11 +1,75 @@
-use std::num::ParseIntError;
+use core::num::ParseIntError;
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
+// Skipped so the cfg on Io doesn't make rustfmt spread every struct variant over several lines.
+#[rustfmt::skip]
 #[derive(Debug)]
 pub enum Error {
-    KeySizeError(String),
+    KeySizeError(&'static str),
     ParseIntError(ParseIntError),
+    // The requested number of rounds doesn't fit in the cipher's key table.
+    InvalidRounds(u8),
+    // The output buffer can't hold the result.
+    BufferTooSmall { needed: usize },
+    // The input doesn't have the length its format requires.
+    InvalidLength { expected: usize, found: usize },
+    // The input doesn't start with the expected magic bytes.
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    // A stored key schedule was made for a different word size or number of rounds.
+    WordSizeMismatch { expected: usize, found: usize },
+    RoundsMismatch { expected: u8, found: u8 },
+    // Byte order flags of a key schedule and a cipher, 0 little-endian and 1 big-endian.
+    ByteOrderMismatch { expected: u8, found: u8 },
+    ChecksumMismatch,
+    // There's no reduction polynomial for blocks of this many bytes.
+    UnsupportedBlockSize(usize),
+    InvalidTagLength(usize),
+    // A tag didn't match the message, nothing from the message should be trusted.
+    AuthenticationFailed,
+    MessageTooLong,
+    InvalidNonceLength(usize),
+    // The CCM length field size L is outside 2 to 8 bytes or leaves no room for a nonce.
+    InvalidLengthFieldSize(usize),
+    // SIV can only bind a limited number of associated data components.
+    TooManyHeaders { max: usize, found: usize },
+    // XTS needs at least one full block of data and a block aligned offset into the sector.
+    SectorTooShort(usize),
+    UnalignedOffset(usize),
+    // A symbol that isn't part of the alphabet, at the given byte position of the input.
+    InvalidCharacter { position: usize },
+    // Encoded text that stops partway through a byte or its padding, at the given byte position.
+    UnexpectedEnd { position: usize },
+    // An alphabet needs at least two symbols and no repeats.
+    InvalidAlphabet,
+    // The format-preserving encryption domain is below the minimum size, or too large for the cipher.
+    DomainTooSmall,
+    DomainTooLarge,
+    // A wrapped key failed its integrity check, it was corrupted or wrapped under another key.
+    UnwrapFailed,
+    // The DRBG has produced as many outputs as its block size allows for one seed.
+    ReseedRequired,
+    RequestTooLarge { max: usize },
+    // The padding of a decrypted message is malformed, usually from a wrong key or password.
+    InvalidPadding,
+    // Password-based key derivation needs at least one iteration.
+    InvalidIterationCount(u32),
+    // The input isn't valid DER for the expected structure, at the given byte position.
+    MalformedDer { position: usize },
+    // An algorithm identifier with an OID the crate doesn't implement.
+    UnknownAlgorithm { position: usize },
+    // An envelope header names a word size or mode the crate doesn't implement.
+    UnsupportedWordSize(u16),
+    UnsupportedMode(u8),
+    // An envelope was sealed under a different key id than the one opening it.
+    KeyIdMismatch,
+    // An envelope mode with a CMAC tag, used without a MAC key.
+    MacKeyRequired,
+    // ASCII armor with missing or malformed markers, headers or checksum line, counting from 1.
+    InvalidArmor { line: usize },
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
 }
 
 impl From<ParseIntError> for Error {
@@ -13,3 +77,10 @@ impl From<ParseIntError> for Error {
         Self::ParseIntError(err)
     }
 }
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -1,9 +1,10 @@
 pub trait FromBytes {
-    fn from_le_bytes(a: &mut &[u8]) -> Self;
+    // Splits the next value off the front of a, without interpreting its bytes.
+    fn take_bytes(a: &mut &[u8]) -> Self;
 }
 
 impl<const N: usize> FromBytes for [u8; N] {
-    fn from_le_bytes(a: &mut &[u8]) -> [u8; N] {
+    fn take_bytes(a: &mut &[u8]) -> [u8; N] {
         let (int_bytes, rest) = a.split_at(N);
 
         let mut me = [0u8; N];
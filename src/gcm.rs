@@ -0,0 +1,402 @@
+use crate::aead::Aead;
+use crate::block_cipher::BlockEncrypt;
+use crate::constant_time::ct_eq;
+use crate::ctr::apply_keystream;
+use crate::error::{Error, Result};
+
+// GCM is defined for 128-bit blocks, which is RC5 with 64-bit words.
+const BLOCK_SIZE: usize = 16;
+// Only 96-bit nonces are supported, they map directly onto the initial counter block.
+pub const GCM_NONCE_SIZE: usize = 12;
+// SP 800-38D allows at most 2^32 - 2 blocks of plaintext, past that the 32-bit counter wraps.
+const MAX_MESSAGE_LEN: u64 = ((1 << 32) - 2) * BLOCK_SIZE as u64;
+
+/*
+Galois/Counter Mode as specified in NIST SP 800-38D, for ciphers with a 128-bit block such as Rc5<u64>.
+
+Tags are 16 bytes by default, truncation to 12 to 15 bytes or to the
+application specific lengths of 8 and 4 bytes is supported.
+*/
+pub struct Gcm<'a, C> {
+    cipher: &'a C,
+    // The hash subkey H = E(0^128).
+    h: u128,
+    tag_len: usize,
+}
+
+impl<'a, C> Gcm<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C, tag_len: usize) -> Result<Gcm<'a, C>> {
+        if C::BLOCK_SIZE != BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize(C::BLOCK_SIZE));
+        }
+        if !matches!(tag_len, 4 | 8 | 12..=16) {
+            return Err(Error::InvalidTagLength(tag_len));
+        }
+
+        let mut h = [0; BLOCK_SIZE];
+        cipher.encrypt_block(&mut h);
+
+        Ok(Self {
+            cipher,
+            h: u128::from_be_bytes(h),
+            tag_len,
+        })
+    }
+
+    // J0 = nonce || 0^31 || 1
+    fn initial_counter(nonce: &[u8]) -> Result<[u8; BLOCK_SIZE]> {
+        if nonce.len() != GCM_NONCE_SIZE {
+            return Err(Error::InvalidNonceLength(nonce.len()));
+        }
+
+        let mut j0 = [0; BLOCK_SIZE];
+        j0[..GCM_NONCE_SIZE].copy_from_slice(nonce);
+        j0[BLOCK_SIZE - 1] = 1;
+        Ok(j0)
+    }
+
+    fn tag(&self, j0: &[u8; BLOCK_SIZE], associated_data: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut ghash = Ghash::new(self.h);
+        ghash.update_padded(associated_data);
+        ghash.update_padded(ciphertext);
+
+        let mut lengths = [0; BLOCK_SIZE];
+        lengths[..8].copy_from_slice(&(associated_data.len() as u64 * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&(ciphertext.len() as u64 * 8).to_be_bytes());
+        ghash.update_padded(&lengths);
+
+        let mut tag = *j0;
+        self.cipher.encrypt_block(&mut tag);
+        (u128::from_be_bytes(tag) ^ ghash.y).to_be_bytes()
+    }
+
+    fn check_message_len(len: u64) -> Result<()> {
+        if len > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong);
+        }
+        Ok(())
+    }
+
+    // The counter for the first block of data, inc32(J0).
+    fn first_counter(j0: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+        let mut counter = *j0;
+        crate::ctr::increment(&mut counter[BLOCK_SIZE - 4..]);
+        counter
+    }
+}
+
+impl<'a, C> Aead for Gcm<'a, C>
+where
+    C: BlockEncrypt,
+{
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+        Self::check_message_len(buffer.len() as u64)?;
+
+        let j0 = Self::initial_counter(nonce)?;
+        let mut counter = Self::first_counter(&j0);
+        apply_keystream(self.cipher, &mut counter, 4, buffer);
+
+        let full_tag = self.tag(&j0, associated_data, buffer);
+        tag.copy_from_slice(&full_tag[..self.tag_len]);
+        Ok(())
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+        Self::check_message_len(buffer.len() as u64)?;
+
+        let j0 = Self::initial_counter(nonce)?;
+        let expected = self.tag(&j0, associated_data, buffer);
+        if !ct_eq(&expected[..self.tag_len], tag) {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        let mut counter = Self::first_counter(&j0);
+        apply_keystream(self.cipher, &mut counter, 4, buffer);
+        Ok(())
+    }
+}
+
+// The GHASH universal hash, blocks are processed as big-endian 128-bit integers.
+struct Ghash {
+    h: u128,
+    y: u128,
+}
+
+impl Ghash {
+    fn new(h: u128) -> Self {
+        Self { h, y: 0 }
+    }
+
+    // Hashes data, zero padding the last block.
+    fn update_padded(&mut self, data: &[u8]) {
+        for chunk in data.chunks(BLOCK_SIZE) {
+            let mut block = [0; BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.y = gf_mul(self.y ^ u128::from_be_bytes(block), self.h);
+        }
+    }
+}
+
+/*
+Multiplication in GF(2^128) with the GCM bit order, where the most significant bit of
+the integer is the coefficient of x^0. Algorithm 1 of SP 800-38D, without secret branches.
+*/
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xE1 << 120;
+
+    let mut z = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        let bit = (x >> i) & 1;
+        z ^= v & bit.wrapping_neg();
+        let lsb = v & 1;
+        v = (v >> 1) ^ (R & lsb.wrapping_neg());
+    }
+    z
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::testing::{bytes, rc5, rc5_with_key, Aes};
+
+    /*
+    GHASH from test case 2 of the original GCM specification, where AES-128 with a zero key
+    gives H = 66e94bd4..., and the tag is GHASH xor E(K, J0) with E(K, J0) being the tag of test case 1.
+    */
+    #[test]
+    fn ghash_aes_test_case_2() {
+        let h = u128::from_str_radix("66e94bd4ef8a2c3b884cfa59ca342b2e", 16).unwrap();
+        let mut ghash = Ghash::new(h);
+        ghash.update_padded(&decode_hex("0388dace60b6a392f328c2b971b2fe78").unwrap());
+        let mut lengths = [0; 16];
+        lengths[15] = 128;
+        ghash.update_padded(&lengths);
+
+        let tag = u128::from_str_radix("ab6e47d42cec13bdf53a67b21257bddf", 16).unwrap();
+        let ek_j0 = u128::from_str_radix("58e2fccefa7e3061367f1d57a4e7455a", 16).unwrap();
+        assert_eq!(ghash.y, tag ^ ek_j0);
+    }
+
+    // Test cases 2 to 4 of the GCM specification, which use AES-128.
+    #[test]
+    fn aes_test_cases() {
+        let pt = decode_hex(
+            "D9313225F88406E5A55909C5AFF5269A86A7A9531534F7DA2E4C303D8A318A72\
+             1C3C0C95956809532FCF0E2449A6B525B16AEDF5AA0DE657BA637B391AAFD255",
+        )
+        .unwrap();
+        let ct = decode_hex(
+            "42831EC2217774244B7221B784D0D49CE3AA212F2C02A4E035C17E2329ACA12E\
+             21D514B25466931C7D8F6A5AAC84AA051BA30B396A0AAC973D58E091473F5985",
+        )
+        .unwrap();
+        let key = decode_hex("FEFFE9928665731C6D6A8F9467308308").unwrap();
+        let nonce = decode_hex("CAFEBABEFACEDBADDECAF888").unwrap();
+        let ad = decode_hex("FEEDFACEDEADBEEFFEEDFACEDEADBEEFABADDAD2").unwrap();
+
+        let vectors = [
+            (
+                vec![0; 16],
+                vec![0; 12],
+                vec![],
+                vec![0; 16],
+                decode_hex("0388DACE60B6A392F328C2B971B2FE78").unwrap(),
+                "AB6E47D42CEC13BDF53A67B21257BDDF",
+            ),
+            (
+                key.clone(),
+                nonce.clone(),
+                vec![],
+                pt.clone(),
+                ct.clone(),
+                "4D5C2AF327CD64A62CF35ABD2BA6FAB4",
+            ),
+            (
+                key,
+                nonce,
+                ad,
+                pt[..60].to_vec(),
+                ct[..60].to_vec(),
+                "5BC94FBC3221A5DB94FAE95AE7121A47",
+            ),
+        ];
+
+        for (key, nonce, ad, pt, ct, tag) in vectors.iter() {
+            let aes = Aes::new(key);
+            let gcm = Gcm::new(&aes, 16).unwrap();
+            let mut expected = ct.clone();
+            expected.extend(decode_hex(tag).unwrap());
+
+            let sealed = gcm.encrypt(nonce, ad, pt).unwrap();
+            assert_eq!(sealed, expected);
+            assert_eq!(&gcm.decrypt(nonce, ad, &sealed).unwrap(), pt);
+        }
+    }
+
+    // Known answers for RC5-64/24/24, generated by `python3 tests/vectors/rc5ref.py gcm`.
+    #[test]
+    fn known_answers() {
+        let cipher = rc5::<u64>(24, 24);
+        let gcm = Gcm::new(&cipher, 16).unwrap();
+        let nonce = bytes(100, 12);
+
+        let vectors = [
+            (0, 0, "", "E4CC2A555CDDBB3AF40ED3D2128CF5F8"),
+            (
+                0,
+                16,
+                "66CD3B06BFDE1756ED001B317C71A881",
+                "AFE79AC3CC7E321C900B269B3EAC33C0",
+            ),
+            (
+                20,
+                60,
+                "66CD3B06BFDE1756ED001B317C71A8817ED356B2A245E9C12A3898E6228F3AD1FCE7F659AE2BB16BBFE6F17CD390203EA01378960625F3A63A3AC37B",
+                "CB92FA56BE4D43500C2D3024DFEF3B16",
+            ),
+            (13, 5, "66CD3B06BF", "12727514FFBE3CAC752A837C0A32F5E8"),
+        ];
+
+        for (ad_len, len, ct, tag) in vectors {
+            let ad = bytes(200, ad_len);
+            let pt = bytes(0, len);
+
+            let sealed = gcm.encrypt(&nonce, &ad, &pt).unwrap();
+            let mut expected = decode_hex(ct).unwrap();
+            expected.extend(decode_hex(tag).unwrap());
+            assert_eq!(sealed, expected);
+            assert_eq!(gcm.decrypt(&nonce, &ad, &sealed).unwrap(), pt);
+        }
+    }
+
+    // Also from the reference script.
+    #[test]
+    fn zero_key() {
+        let cipher = rc5_with_key::<u64>(12, &[0; 16]);
+        let gcm = Gcm::new(&cipher, 16).unwrap();
+
+        let sealed = gcm.encrypt(&[0; 12], &[], &[0; 16]).unwrap();
+        assert_eq!(
+            sealed,
+            decode_hex("F6870F283F8B1EA22B4F877CF35A5146B6322125A5BAF96DF2AF038B7621C084").unwrap()
+        );
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let cipher = rc5::<u64>(24, 24);
+        let gcm = Gcm::new(&cipher, 16).unwrap();
+        let nonce = bytes(100, 12);
+        let sealed = gcm.encrypt(&nonce, b"header", b"attack at dawn").unwrap();
+
+        for i in 0..sealed.len() {
+            for bit in 0..8 {
+                let mut forged = sealed.clone();
+                forged[i] ^= 1 << bit;
+                assert!(matches!(
+                    gcm.decrypt(&nonce, b"header", &forged),
+                    Err(Error::AuthenticationFailed)
+                ));
+            }
+        }
+
+        let mut other_nonce = nonce.clone();
+        other_nonce[11] ^= 1;
+        assert!(matches!(
+            gcm.decrypt(&other_nonce, b"header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            gcm.decrypt(&nonce, b"headers", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+
+        // Moving bytes between the associated data and the ciphertext changes the length block.
+        let mut moved = b"r".to_vec();
+        moved.extend(&sealed);
+        assert!(matches!(
+            gcm.decrypt(&nonce, b"heade", &moved),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn no_plaintext_on_failure() {
+        let cipher = rc5::<u64>(24, 24);
+        let gcm = Gcm::new(&cipher, 12).unwrap();
+        let nonce = bytes(100, 12);
+
+        let mut buffer = b"secret message".to_vec();
+        let mut tag = [0; 12];
+        gcm.encrypt_in_place(&nonce, b"", &mut buffer, &mut tag)
+            .unwrap();
+        let ciphertext = buffer.clone();
+
+        tag[11] ^= 1;
+        assert!(gcm
+            .decrypt_in_place(&nonce, b"", &mut buffer, &tag)
+            .is_err());
+        assert_eq!(buffer, ciphertext);
+    }
+
+    #[test]
+    fn message_length_limit() {
+        type Rc5Gcm<'a> = Gcm<'a, Rc5<u64>>;
+        assert_eq!(MAX_MESSAGE_LEN, 0xF_FFFF_FFE0);
+        assert!(Rc5Gcm::check_message_len(MAX_MESSAGE_LEN).is_ok());
+        assert!(matches!(
+            Rc5Gcm::check_message_len(MAX_MESSAGE_LEN + 1),
+            Err(Error::MessageTooLong)
+        ));
+    }
+
+    #[test]
+    fn parameters() {
+        let cipher = rc5::<u64>(24, 24);
+        assert!(matches!(
+            Gcm::new(&cipher, 11),
+            Err(Error::InvalidTagLength(11))
+        ));
+
+        let gcm = Gcm::new(&cipher, 16).unwrap();
+        assert!(matches!(
+            gcm.encrypt(&[0; 16], b"", b""),
+            Err(Error::InvalidNonceLength(16))
+        ));
+
+        let mut rc5_32: Rc5<u32> = Rc5::new(12, 16).unwrap();
+        rc5_32.setup(&[0; 16]);
+        assert!(matches!(
+            Gcm::new(&rc5_32, 16),
+            Err(Error::UnsupportedBlockSize(8))
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::error::{Error, Result};
+
+/*
+Arithmetic on blocks viewed as elements of GF(2^n), the first byte holding the highest powers of x.
+
+Each supported block size uses the lexicographically first irreducible polynomial of minimum weight,
+the same convention that gives the CMAC constants for 64- and 128-bit blocks.
+The returned value holds the terms below x^n.
+*/
+pub fn reduction_polynomial(block_size: usize) -> Result<u16> {
+    match block_size {
+        // x^16 + x^5 + x^3 + x + 1
+        2 => Ok(0x2B),
+        // x^32 + x^7 + x^3 + x^2 + 1
+        4 => Ok(0x8D),
+        // x^64 + x^4 + x^3 + x + 1
+        8 => Ok(0x1B),
+        // x^128 + x^7 + x^2 + x + 1
+        16 => Ok(0x87),
+        // x^256 + x^10 + x^5 + x^2 + 1
+        32 => Ok(0x425),
+        // x^512 + x^8 + x^5 + x^2 + 1
+        64 => Ok(0x125),
+        _ => Err(Error::UnsupportedBlockSize(block_size)),
+    }
+}
+
+// Multiplies block by x, reducing by the polynomial from reduction_polynomial.
+pub fn dbl(block: &mut [u8], polynomial: u16) {
+    let carry = block[0] >> 7;
+
+    for i in 0..block.len() - 1 {
+        block[i] = (block[i] << 1) | (block[i + 1] >> 7);
+    }
+    let last = block.len() - 1;
+    block[last] <<= 1;
+
+    // Reduce without branching on the secret carry bit.
+    let mask = carry.wrapping_neg();
+    let [high, low] = polynomial.to_be_bytes();
+    block[last] ^= low & mask;
+    if last > 0 {
+        block[last - 1] ^= high & mask;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    // The AES-128 subkeys from the NIST SP 800-38B examples.
+    #[test]
+    fn cmac_subkeys_128() {
+        let poly = reduction_polynomial(16).unwrap();
+        let mut k = decode_hex("7df76b0c1ab899b33e42f047b91b546f").unwrap();
+        dbl(&mut k, poly);
+        assert_eq!(k, decode_hex("fbeed618357133667c85e08f7236a8de").unwrap());
+        dbl(&mut k, poly);
+        assert_eq!(k, decode_hex("f7ddac306ae266ccf90bc11ee46d513b").unwrap());
+    }
+
+    #[test]
+    fn wide_polynomial() {
+        let poly = reduction_polynomial(32).unwrap();
+        let mut k = [0; 32];
+        k[0] = 0x80;
+        dbl(&mut k, poly);
+        assert_eq!(k[30..], [0x04, 0x25]);
+        assert!(k[..30].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn unsupported_block_size() {
+        assert!(matches!(
+            reduction_polynomial(6),
+            Err(Error::UnsupportedBlockSize(6))
+        ));
+    }
+}
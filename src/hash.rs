@@ -0,0 +1,308 @@
+use crate::block_cipher::MAX_BLOCK_SIZE;
+use crate::byte_order::LittleEndian;
+use crate::error::{Error, Result};
+use crate::mac::xor;
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+// Narrower blocks give digests too short to resist collisions.
+const MIN_BLOCK_SIZE: usize = 16;
+
+// The message length is appended as a 64-bit count of bits.
+const LENGTH_BYTES: usize = 8;
+
+/*
+The ways to turn the block cipher E into a one-way compression function of the
+chaining value H and the message block m, all of them one block wide.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    // E_m(H) xor H, the message block is the key.
+    DaviesMeyer,
+    // E_H(m) xor m, the chaining value is the key.
+    MatyasMeyerOseas,
+    // E_H(m) xor m xor H.
+    MiyaguchiPreneel,
+}
+
+/*
+A Merkle-Damgard hash built from RC5, for targets where RC5 is the only primitive available.
+Rc5<u64> gives a 128-bit digest and Rc5<u128> a 256-bit one.
+
+The message is padded with 0x80, zeros and its length in bits as a big-endian u64, and
+the chaining value starts from the magic constants Pw and Qw. Every block rekeys the
+cipher, so hashing is far slower than encrypting. Like any Merkle-Damgard hash it is open
+to length extension, so use Cmac rather than hashing a secret followed by the message.
+*/
+pub struct Rc5Hash<T> {
+    cipher: Rc5<T>,
+    compression: Compression,
+    state: [u8; MAX_BLOCK_SIZE],
+    buffer: [u8; MAX_BLOCK_SIZE],
+    buffer_len: usize,
+    // The message length so far, in bytes.
+    length: u64,
+}
+
+impl<T> Clone for Rc5Hash<T>
+where
+    T: UInt,
+{
+    fn clone(&self) -> Self {
+        // The cipher is rekeyed before every block, so a fresh one will do.
+        Self {
+            cipher: Rc5::new(self.cipher.rounds(), Self::block_len())
+                .expect("the rounds were already checked"),
+            compression: self.compression,
+            state: self.state,
+            buffer: self.buffer,
+            buffer_len: self.buffer_len,
+            length: self.length,
+        }
+    }
+}
+
+impl<T> Rc5Hash<T>
+where
+    T: UInt,
+{
+    pub fn new(rounds: u8, compression: Compression) -> Result<Rc5Hash<T>> {
+        let n = Self::block_len();
+        if n < MIN_BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize(n));
+        }
+
+        let mut state = [0; MAX_BLOCK_SIZE];
+        T::pw().write_bytes::<LittleEndian>(&mut state[..T::BYTES]);
+        T::qw().write_bytes::<LittleEndian>(&mut state[T::BYTES..n]);
+
+        Ok(Self {
+            cipher: Rc5::new(rounds, n)?,
+            compression,
+            state,
+            buffer: [0; MAX_BLOCK_SIZE],
+            buffer_len: 0,
+            length: 0,
+        })
+    }
+
+    fn block_len() -> usize {
+        2 * T::BYTES
+    }
+
+    // The digest length in bytes, one block.
+    pub fn output_len() -> usize {
+        Self::block_len()
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        let n = Self::block_len();
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        while !data.is_empty() {
+            let take = (n - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len == n {
+                let block = self.buffer;
+                self.compress(&block[..n]);
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    // Writes the digest, out must be exactly output_len() bytes.
+    pub fn finalize_into(mut self, out: &mut [u8]) -> Result<()> {
+        let n = Self::block_len();
+        if out.len() != n {
+            return Err(Error::InvalidLength {
+                expected: n,
+                found: out.len(),
+            });
+        }
+
+        let bits = self.length.wrapping_mul(8);
+        let mut block = self.buffer;
+        block[self.buffer_len] = 0x80;
+        for byte in &mut block[self.buffer_len + 1..n] {
+            *byte = 0;
+        }
+        // The length gets a block of its own when it doesn't fit after the 0x80.
+        if self.buffer_len + 1 > n - LENGTH_BYTES {
+            self.compress(&block[..n]);
+            block = [0; MAX_BLOCK_SIZE];
+        }
+        block[n - LENGTH_BYTES..n].copy_from_slice(&bits.to_be_bytes());
+        self.compress(&block[..n]);
+
+        out.copy_from_slice(&self.state[..n]);
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn finalize(self) -> Vec<u8> {
+        let mut out = vec![0; Self::output_len()];
+        self.finalize_into(&mut out)
+            .expect("the output is one block");
+        out
+    }
+
+    // Hashes data in one call.
+    #[cfg(feature = "std")]
+    pub fn digest(rounds: u8, compression: Compression, data: &[u8]) -> Result<Vec<u8>> {
+        let mut hash = Self::new(rounds, compression)?;
+        hash.update(data);
+        Ok(hash.finalize())
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let n = Self::block_len();
+        let mut out = [0; MAX_BLOCK_SIZE];
+
+        match self.compression {
+            Compression::DaviesMeyer => {
+                self.cipher.setup(block);
+                out[..n].copy_from_slice(&self.state[..n]);
+                self.cipher.encrypt_block(&mut out[..n]);
+                xor(&mut out[..n], &self.state[..n]);
+            }
+            Compression::MatyasMeyerOseas => {
+                self.cipher.setup(&self.state[..n]);
+                out[..n].copy_from_slice(block);
+                self.cipher.encrypt_block(&mut out[..n]);
+                xor(&mut out[..n], block);
+            }
+            Compression::MiyaguchiPreneel => {
+                self.cipher.setup(&self.state[..n]);
+                out[..n].copy_from_slice(block);
+                self.cipher.encrypt_block(&mut out[..n]);
+                xor(&mut out[..n], block);
+                xor(&mut out[..n], &self.state[..n]);
+            }
+        }
+
+        self.state = out;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::bytes;
+
+    const MODES: [Compression; 3] = [
+        Compression::DaviesMeyer,
+        Compression::MatyasMeyerOseas,
+        Compression::MiyaguchiPreneel,
+    ];
+
+    fn check<T: UInt>(digests: [[&str; 3]; 3]) {
+        let long = bytes(0, 100);
+        let messages: [&[u8]; 3] = [b"", b"abc", &long];
+
+        for (&mode, expected) in MODES.iter().zip(digests.iter()) {
+            for (message, digest) in messages.iter().zip(expected.iter()) {
+                let hash = Rc5Hash::<T>::digest(24, mode, message).unwrap();
+                assert_eq!(hash, decode_hex(digest).unwrap());
+            }
+        }
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py hash`.
+    #[test]
+    fn rc5_64() {
+        check::<u64>([
+            [
+                "787188532A81FAFF8FB95736D571CCE7",
+                "17451E47E2A9683E0017CFD64CBC1565",
+                "60670D6CEB24E79A5587102703673A91",
+            ],
+            [
+                "8B9FD8A23228BB4F5815623743BD92FD",
+                "A11176598648C1544EED39BC1D0D3BD1",
+                "C95B7A51F0047DFEACBF4C6D90C957E2",
+            ],
+            [
+                "E0B5352850795AF84D692848FAC4A563",
+                "CA3B9BD3E41920E35B9173C3A4740C4F",
+                "5854326368DC5C164924806CD368A704",
+            ],
+        ]);
+    }
+
+    #[test]
+    fn rc5_128() {
+        check::<u128>([
+            [
+                "4D311467549741FC0095F0AD94493CA7B85D3BAD1467855708876D39717FB5A4",
+                "4CEC307019C6EA75FF66A8EBD151F6FFFDCE969F1D9D72DBC92FB67C716030BE",
+                "A607BBAF450AE00A136E9B4F22E5C30AC005BBA68E928FD97F219AA310D63F05",
+            ],
+            [
+                "D7096E70B9F515E7F6ACCFED5ABAC90B386202726F960E72E42048643D3729FB",
+                "18D445779F87730DE184B25FB4D25022277D728F7F2D24B5EA725973061AB67E",
+                "F70069113D02F848EE27E7597FEE5D71A5ED0C88A29B8B1AD822CF4CE04A340A",
+            ],
+            [
+                "10FA9AEC39AD64589C86226738EB28BC0DAAEF2E0F569281F15C021B844E1E65",
+                "DF27B1EB1FDF02B28BAE5FD5D683B19512B59FD31FEDB846FF0E130CBF6381E0",
+                "B612678AE5D7EE10CD69201E1D6A10BBB8C7C5D2CB63323CFA0C9B278ED4A433",
+            ],
+        ]);
+    }
+
+    // Feeding the message in pieces gives the same digest, whatever the split.
+    #[test]
+    fn incremental() {
+        let message: Vec<u8> = (0..77).collect();
+        for &mode in &MODES {
+            let expected = Rc5Hash::<u64>::digest(12, mode, &message).unwrap();
+            for split in 0..message.len() {
+                let mut hash = Rc5Hash::<u64>::new(12, mode).unwrap();
+                hash.update(&message[..split]);
+                let copy = hash.clone();
+                hash.update(&message[split..]);
+                assert_eq!(hash.finalize(), expected);
+
+                let mut copy = copy;
+                copy.update(&message[split..]);
+                assert_eq!(copy.finalize(), expected);
+            }
+        }
+    }
+
+    // Messages that only differ in length or padding hash differently.
+    #[test]
+    fn padding() {
+        let mut digests = std::collections::HashSet::new();
+        for len in 0..40 {
+            let message = vec![0; len];
+            assert!(digests
+                .insert(Rc5Hash::<u64>::digest(12, Compression::DaviesMeyer, &message).unwrap()));
+        }
+        assert_ne!(
+            Rc5Hash::<u64>::digest(12, Compression::DaviesMeyer, b"\x80").unwrap(),
+            Rc5Hash::<u64>::digest(12, Compression::DaviesMeyer, b"").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert!(matches!(
+            Rc5Hash::<u32>::new(12, Compression::DaviesMeyer),
+            Err(Error::UnsupportedBlockSize(8))
+        ));
+        assert!(matches!(
+            Rc5Hash::<u64>::new(12, Compression::DaviesMeyer)
+                .unwrap()
+                .finalize_into(&mut [0; 8]),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 8
+            })
+        ));
+    }
+}
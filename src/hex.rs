@@ -1,16 +1,513 @@
-use std::{fmt::Write, num::ParseIntError};
+use std::fmt::Write;
 
-pub fn decode_hex(s: &str) -> Result<Vec<u8>, ParseIntError> {
-    (0..s.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
-        .collect()
+use crate::checksum::crc24;
+use crate::encoding::{Decoder, Encoding};
+use crate::error::{Error, Result};
+
+// Hex in either case, see Encoding::Hex.
+pub fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    Encoding::Hex.decode(s)
 }
 
 pub fn encode_hex(bytes: &[u8]) -> String {
-    let mut s = String::with_capacity(bytes.len() * 2);
-    for &b in bytes {
-        write!(&mut s, "{:02x}", b).unwrap();
+    Encoding::Hex.encode(bytes)
+}
+
+/*
+ASCII armor for pasting binary data into tickets and config files, in the layout of
+OpenPGP armor (RFC 4880 section 6.2):
+
+    -----BEGIN RC5 MESSAGE-----
+    Word-Size: 32
+    Rounds: 12
+
+    base64 of the data, wrapped at 64 characters
+    =CRC-24 of the data, in base64
+    -----END RC5 MESSAGE-----
+
+Decoding skips text before the BEGIN line and whitespace anywhere in the body, so the
+armor survives being indented or rewrapped. The checksum only catches damage in transit,
+the data itself should be authenticated, e.g. by sealing it in an EAX envelope first.
+*/
+pub const ARMOR_LABEL: &str = "RC5 MESSAGE";
+
+const ARMOR_LINE_LEN: usize = 64;
+const WORD_SIZE_HEADER: &str = "Word-Size";
+const ROUNDS_HEADER: &str = "Rounds";
+
+const BASE64: Encoding = Encoding::Base64 { padded: true };
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Armor {
+    label: String,
+    headers: Vec<(String, String)>,
+    data: Vec<u8>,
+}
+
+impl Armor {
+    // The label is what goes after BEGIN and END: uppercase letters, digits and single spaces.
+    pub fn new(label: &str, data: &[u8]) -> Result<Armor> {
+        if label.is_empty() {
+            return Err(Error::InvalidLength {
+                expected: 1,
+                found: 0,
+            });
+        }
+        let bytes = label.as_bytes();
+        for (position, &c) in bytes.iter().enumerate() {
+            let space_ok =
+                position > 0 && position < bytes.len() - 1 && bytes[position - 1] != b' ';
+            if !(c.is_ascii_uppercase() || c.is_ascii_digit() || (c == b' ' && space_ok)) {
+                return Err(Error::InvalidCharacter { position });
+            }
+        }
+
+        Ok(Self {
+            label: label.to_string(),
+            headers: Vec::new(),
+            data: data.to_vec(),
+        })
+    }
+
+    /*
+    Adds a "name: value" header line. Names are printable ASCII without colons, values
+    can't contain line breaks, and Word-Size and Rounds values must be ones that
+    rc5_parameters() accepts. Errors give the position in the header line.
+    */
+    pub fn add_header(&mut self, name: &str, value: &str) -> Result<()> {
+        if name.is_empty() {
+            return Err(Error::InvalidCharacter { position: 0 });
+        }
+        if let Some(position) = name
+            .bytes()
+            .position(|c| !c.is_ascii_graphic() || c == b':')
+        {
+            return Err(Error::InvalidCharacter { position });
+        }
+        if let Some(position) = value.bytes().position(|c| c == b'\r' || c == b'\n') {
+            return Err(Error::InvalidCharacter {
+                position: name.len() + 2 + position,
+            });
+        }
+        if !valid_header(name, value.trim()) {
+            return Err(Error::InvalidCharacter {
+                position: name.len() + 2,
+            });
+        }
+
+        self.headers
+            .push((name.to_string(), value.trim().to_string()));
+        Ok(())
+    }
+
+    // Records the word size in bits and the rounds of the cipher, replacing earlier values.
+    pub fn set_rc5_parameters(&mut self, word_size: u16, rounds: u8) -> Result<()> {
+        if !valid_word_size(word_size) {
+            return Err(Error::UnsupportedWordSize(word_size));
+        }
+        self.headers.retain(|(name, _)| {
+            !name.eq_ignore_ascii_case(WORD_SIZE_HEADER)
+                && !name.eq_ignore_ascii_case(ROUNDS_HEADER)
+        });
+        self.headers
+            .push((WORD_SIZE_HEADER.to_string(), word_size.to_string()));
+        self.headers
+            .push((ROUNDS_HEADER.to_string(), rounds.to_string()));
+        Ok(())
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    // The value of the first header with this name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    // The word size in bits and the rounds, if both headers are present.
+    pub fn rc5_parameters(&self) -> Option<(u16, u8)> {
+        let word_size = self.header(WORD_SIZE_HEADER)?.parse().ok()?;
+        let rounds = self.header(ROUNDS_HEADER)?.parse().ok()?;
+        Some((word_size, rounds))
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn encode(&self) -> String {
+        let mut s = String::new();
+        writeln!(&mut s, "-----BEGIN {}-----", self.label).unwrap();
+        for (name, value) in &self.headers {
+            writeln!(&mut s, "{}: {}", name, value).unwrap();
+        }
+        s.push('\n');
+
+        let body = BASE64.encode(&self.data);
+        for line in body.as_bytes().chunks(ARMOR_LINE_LEN) {
+            s.push_str(core::str::from_utf8(line).unwrap());
+            s.push('\n');
+        }
+
+        s.push('=');
+        s.push_str(&BASE64.encode(&crc24(&self.data).to_be_bytes()[1..]));
+        writeln!(&mut s, "\n-----END {}-----", self.label).unwrap();
+        s
+    }
+
+    /*
+    Parses the first armored block in text. Malformed structure is reported with its line,
+    counting from 1, bad characters in the body with their byte position in text.
+    */
+    pub fn decode(text: &str) -> Result<Armor> {
+        let mut lines = text
+            .split('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len() + 1;
+                Some((start, line))
+            })
+            .enumerate()
+            .map(|(i, (offset, line))| (i + 1, offset, line));
+
+        let mut armor = loop {
+            let (number, _, line) = lines.next().ok_or(Error::InvalidMagic)?;
+            let line = line.trim();
+            if let Some(label) = line
+                .strip_prefix("-----BEGIN ")
+                .and_then(|rest| rest.strip_suffix("-----"))
+            {
+                break Armor::new(label, &[]).map_err(|_| Error::InvalidArmor { line: number })?;
+            }
+        };
+
+        // Headers run up to the first line without a colon, usually a blank one.
+        let mut in_headers = true;
+        let mut body_start = None;
+        let mut body_end = text.len();
+        let mut checksum = None;
+        let mut end = None;
+        let mut last_line = 0;
+        for (number, offset, line) in &mut lines {
+            last_line = number;
+            let trimmed = line.trim();
+            if in_headers {
+                if let Some((name, value)) = trimmed.split_once(':') {
+                    armor
+                        .add_header(name.trim(), value)
+                        .map_err(|_| Error::InvalidArmor { line: number })?;
+                    continue;
+                }
+                in_headers = false;
+                body_start = Some(offset);
+            }
+
+            if trimmed.starts_with("-----") {
+                if checksum.is_none() {
+                    body_end = offset;
+                }
+                end = Some((number, trimmed));
+                break;
+            }
+            if checksum.is_some() {
+                return Err(Error::InvalidArmor { line: number });
+            }
+            // Padding can be wrapped onto a line of its own, the checksum is always 4 characters.
+            if trimmed.starts_with('=') && trimmed.len() == 5 {
+                body_end = offset;
+                let start = offset + line.find('=').unwrap() + 1;
+                checksum = Some((number, start, &trimmed[1..]));
+            }
+        }
+
+        let (end_line, end) = end.ok_or(Error::InvalidArmor {
+            line: last_line + 1,
+        })?;
+        if end != format!("-----END {}-----", armor.label) {
+            return Err(Error::InvalidArmor { line: end_line });
+        }
+        let (checksum_line, start, checksum) =
+            checksum.ok_or(Error::InvalidArmor { line: end_line })?;
+
+        let body_start = body_start.unwrap_or(body_end);
+        let body = &text.as_bytes()[body_start..body_end];
+        let mut decoder = Decoder::lenient(BASE64);
+        armor.data = vec![0; decoder.update_len(body.len())];
+        let len = decoder
+            .update(body, &mut armor.data)
+            .map_err(|err| shift(err, body_start))?;
+        decoder.finalize().map_err(|err| shift(err, body_start))?;
+        armor.data.truncate(len);
+
+        let mut crc = [0; 4];
+        let len = BASE64
+            .decode_into(checksum.as_bytes(), &mut crc[1..])
+            .map_err(|err| shift(err, start))?;
+        if len != 3 {
+            return Err(Error::InvalidArmor {
+                line: checksum_line,
+            });
+        }
+        if u32::from_be_bytes(crc) != crc24(&armor.data) {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(armor)
+    }
+}
+
+// The RC5 headers have to make sense if they're present.
+fn valid_header(name: &str, value: &str) -> bool {
+    if name.eq_ignore_ascii_case(WORD_SIZE_HEADER) {
+        value.parse().map(valid_word_size).unwrap_or(false)
+    } else if name.eq_ignore_ascii_case(ROUNDS_HEADER) {
+        value.parse::<u8>().is_ok()
+    } else {
+        true
+    }
+}
+
+fn valid_word_size(word_size: u16) -> bool {
+    word_size > 0 && word_size.is_multiple_of(8)
+}
+
+// Moves the position in a decoding error from the start of a piece of text to the start of text.
+fn shift(err: Error, offset: usize) -> Error {
+    match err {
+        Error::InvalidCharacter { position } => Error::InvalidCharacter {
+            position: offset + position,
+        },
+        Error::UnexpectedEnd { position } => Error::UnexpectedEnd {
+            position: offset + position,
+        },
+        err => err,
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    // Output of `gpg --enarmor`.
+    const GPG_ARMOR: &str = "-----BEGIN PGP ARMORED FILE-----
+Comment: Use \"gpg --dearmor\" for unpacking
+
+YXR0YWNrIGF0IGRhd24sIGJyaW5nIHNuYWNrcyBhbmQgYSBsb25nIHJvcGUgZm9y
+IHRoZSB3YWxscw==
+=ZtBB
+-----END PGP ARMORED FILE-----
+";
+    const GPG_DATA: &[u8] = b"attack at dawn, bring snacks and a long rope for the walls";
+
+    #[test]
+    fn matches_gpg() {
+        let armor = Armor::decode(GPG_ARMOR).unwrap();
+        assert_eq!(armor.label(), "PGP ARMORED FILE");
+        assert_eq!(
+            armor.header("comment"),
+            Some("Use \"gpg --dearmor\" for unpacking")
+        );
+        assert_eq!(armor.data(), GPG_DATA);
+        assert_eq!(armor.rc5_parameters(), None);
+
+        let mut armor = Armor::new("PGP ARMORED FILE", GPG_DATA).unwrap();
+        armor
+            .add_header("Comment", "Use \"gpg --dearmor\" for unpacking")
+            .unwrap();
+        assert_eq!(armor.encode(), GPG_ARMOR);
+    }
+
+    #[test]
+    fn round_trip() {
+        for len in 0..200 {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+            let mut armor = Armor::new(ARMOR_LABEL, &data).unwrap();
+            armor.set_rc5_parameters(64, 16).unwrap();
+            armor.set_rc5_parameters(32, 12).unwrap();
+            assert_eq!(armor.headers().len(), 2);
+
+            let decoded = Armor::decode(&armor.encode()).unwrap();
+            assert_eq!(decoded, armor);
+            assert_eq!(decoded.rc5_parameters(), Some((32, 12)));
+        }
+    }
+
+    #[test]
+    fn tolerates_whitespace_and_wrapping() {
+        let pasted = "See the attached key:\r\n\r\n\
+            \t-----BEGIN PGP ARMORED FILE-----\r\n\
+            \tComment: Use \"gpg --dearmor\" for unpacking\r\n\
+            \r\n\
+            \tYXR0YWNrIGF0IGRhd24sIGJyaW5n IHNuYWNrcyBhbmQg\r\n\
+            \tYSBsb25nIHJvcGUgZm9yIHRoZSB3YWxscw\r\n\
+            \t==\r\n\
+            \t=ZtBB   \r\n\
+            \t-----END PGP ARMORED FILE-----\r\n\
+            Thanks";
+        assert_eq!(Armor::decode(pasted).unwrap().data(), GPG_DATA);
+
+        // The blank line after the headers can get lost too.
+        let squashed = GPG_ARMOR.replacen("unpacking\n\n", "unpacking\n", 1);
+        assert_eq!(Armor::decode(&squashed).unwrap().data(), GPG_DATA);
+    }
+
+    #[test]
+    fn malformed_armor() {
+        let decode = |from: &str, to: &str| Armor::decode(&GPG_ARMOR.replacen(from, to, 1));
+
+        assert!(matches!(
+            decode("-----BEGIN", "-----START"),
+            Err(Error::InvalidMagic)
+        ));
+        assert!(matches!(
+            decode("PGP ARMORED", "pgp armored"),
+            Err(Error::InvalidArmor { line: 1 })
+        ));
+        assert!(matches!(
+            decode("END PGP", "END RC5"),
+            Err(Error::InvalidArmor { line: 7 })
+        ));
+        assert!(matches!(
+            decode("-----END PGP ARMORED FILE-----\n", ""),
+            Err(Error::InvalidArmor { line: 7 })
+        ));
+        assert!(matches!(
+            decode("=ZtBB\n", ""),
+            Err(Error::InvalidArmor { line: 6 })
+        ));
+        assert!(matches!(
+            decode("=ZtBB\n", "=ZtBB\nAAAA\n"),
+            Err(Error::InvalidArmor { line: 7 })
+        ));
+        assert!(matches!(
+            decode("Comment:", "Word-Size: 12\nComment:"),
+            Err(Error::InvalidArmor { line: 2 })
+        ));
+        assert!(matches!(
+            decode("Comment:", "Rounds: 256\nComment:"),
+            Err(Error::InvalidArmor { line: 2 })
+        ));
+        assert!(matches!(
+            decode("=ZtBB", "=ZtBC"),
+            Err(Error::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            decode("YXR0", "YXS0"),
+            Err(Error::ChecksumMismatch)
+        ));
+
+        // Body errors point at the character.
+        let body = GPG_ARMOR.find("YXR0").unwrap();
+        assert!(matches!(
+            decode("YXR0", "YX*0"),
+            Err(Error::InvalidCharacter { position }) if position == body + 2
+        ));
+        assert!(matches!(
+            decode("YXR0", "YX=0"),
+            Err(Error::InvalidCharacter { position }) if position == body + 3
+        ));
+        let padding = GPG_ARMOR.find("cw==").unwrap();
+        assert!(matches!(
+            decode("cw==", "cx=="),
+            Err(Error::InvalidCharacter { position }) if position == padding + 1
+        ));
+        assert!(matches!(
+            decode("cw==\n", "cw=\n"),
+            Err(Error::UnexpectedEnd { position }) if position == padding + 4
+        ));
+        assert!(matches!(
+            decode("=ZtBB", "=ZA=="),
+            Err(Error::InvalidArmor { line: 6 })
+        ));
+    }
+
+    #[test]
+    fn invalid_labels_and_headers() {
+        assert!(matches!(
+            Armor::new("", b""),
+            Err(Error::InvalidLength {
+                expected: 1,
+                found: 0
+            })
+        ));
+        for (label, position) in [
+            ("RC5 message", 4),
+            (" RC5", 0),
+            ("RC5 ", 3),
+            ("RC5  MESSAGE", 4),
+            ("RC5-MESSAGE", 3),
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                Armor::new(label, b""),
+                Err(Error::InvalidCharacter { position: p }) if p == *position
+            ));
+        }
+
+        let mut armor = Armor::new(ARMOR_LABEL, b"").unwrap();
+        assert!(matches!(
+            armor.add_header("Key:Id", "1"),
+            Err(Error::InvalidCharacter { position: 3 })
+        ));
+        assert!(matches!(
+            armor.add_header("Key Id", "1"),
+            Err(Error::InvalidCharacter { position: 3 })
+        ));
+        assert!(matches!(
+            armor.add_header("Key-Id", "1\n-----END"),
+            Err(Error::InvalidCharacter { position: 9 })
+        ));
+        assert!(armor.headers().is_empty());
+    }
+
+    // Whatever can be added to an armor decodes again, so the RC5 values are checked up front.
+    #[test]
+    fn rejected_rc5_parameters() {
+        let mut armor = Armor::new(ARMOR_LABEL, b"data").unwrap();
+        armor.set_rc5_parameters(32, 12).unwrap();
+        assert!(matches!(
+            armor.set_rc5_parameters(12, 12),
+            Err(Error::UnsupportedWordSize(12))
+        ));
+        assert!(matches!(
+            armor.set_rc5_parameters(0, 12),
+            Err(Error::UnsupportedWordSize(0))
+        ));
+        for (name, value) in [
+            ("Word-Size", "x"),
+            ("word-size", "12"),
+            ("Rounds", "256"),
+            ("Rounds", "-1"),
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                armor.add_header(name, value),
+                Err(Error::InvalidCharacter { position }) if position == name.len() + 2
+            ));
+            let text = armor
+                .encode()
+                .replacen("Rounds: 12", &format!("{}: {}", name, value), 1);
+            assert!(matches!(
+                Armor::decode(&text),
+                Err(Error::InvalidArmor { line: 3 })
+            ));
+        }
+        assert_eq!(armor.rc5_parameters(), Some((32, 12)));
+
+        let decoded = Armor::decode(&armor.encode()).unwrap();
+        assert_eq!(decoded, armor);
     }
-    s
 }
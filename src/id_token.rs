@@ -0,0 +1,350 @@
+use core::marker::PhantomData;
+
+use crate::block_cipher::{BlockDecrypt, BlockEncrypt};
+use crate::checksum::crc32;
+use crate::encoding::Encoding;
+use crate::error::{Error, Result};
+use crate::fpe::ALPHANUMERIC;
+
+const BASE32: Encoding = Encoding::Base32 { padded: false };
+
+// Tokens carry the first two bytes of the CRC-32 of the encrypted id.
+const CHECKSUM_LEN: usize = 2;
+
+// Integer ids that fill exactly one cipher block.
+pub trait Id: Copy {
+    const BYTES: usize;
+
+    fn write_be(self, out: &mut [u8]);
+    fn read_be(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_id {
+    ($($t:ty),*) => {$(
+        impl Id for $t {
+            const BYTES: usize = core::mem::size_of::<$t>();
+
+            fn write_be(self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_be_bytes());
+            }
+
+            fn read_be(bytes: &[u8]) -> Self {
+                let mut buf = [0; core::mem::size_of::<$t>()];
+                buf.copy_from_slice(bytes);
+                <$t>::from_be_bytes(buf)
+            }
+        }
+    )*};
+}
+
+impl_id!(u32, u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenEncoding {
+    // Digits, then upper and lower case letters, the shortest tokens.
+    Base62,
+    // The RFC 4648 alphabet without padding, case-insensitive when decoding.
+    Base32,
+}
+
+/*
+Turns sequential ids into opaque tokens and back, by encrypting the id as a single block.
+A u64 needs a 64-bit block such as Rc5<u32>, a u32 a 32-bit block such as Rc5<u16>.
+
+The checksum in a token catches mistyped and made up tokens before they are decrypted.
+It isn't a MAC, anyone can compute it, so a decoded id still has to be looked up.
+*/
+pub struct IdObfuscator<'a, C, I> {
+    cipher: &'a C,
+    encoding: TokenEncoding,
+    id: PhantomData<I>,
+}
+
+impl<'a, C, I> IdObfuscator<'a, C, I>
+where
+    C: BlockEncrypt + BlockDecrypt,
+    I: Id,
+{
+    pub fn new(cipher: &'a C, encoding: TokenEncoding) -> Result<IdObfuscator<'a, C, I>> {
+        if C::BLOCK_SIZE != I::BYTES {
+            return Err(Error::UnsupportedBlockSize(C::BLOCK_SIZE));
+        }
+
+        Ok(Self {
+            cipher,
+            encoding,
+            id: PhantomData,
+        })
+    }
+
+    pub fn encrypt(&self, id: I) -> I {
+        let mut block = [0; 8];
+        id.write_be(&mut block[..I::BYTES]);
+        self.cipher.encrypt_block(&mut block[..I::BYTES]);
+        I::read_be(&block[..I::BYTES])
+    }
+
+    pub fn decrypt(&self, id: I) -> I {
+        let mut block = [0; 8];
+        id.write_be(&mut block[..I::BYTES]);
+        self.cipher.decrypt_block(&mut block[..I::BYTES]);
+        I::read_be(&block[..I::BYTES])
+    }
+
+    // Tokens always have this many characters.
+    pub fn token_len(&self) -> usize {
+        let bits = 8 * (I::BYTES + CHECKSUM_LEN) as u32;
+        match self.encoding {
+            TokenEncoding::Base32 => bits.div_ceil(5) as usize,
+            TokenEncoding::Base62 => {
+                let mut len = 0;
+                let mut capacity: u128 = 1;
+                while capacity >> bits == 0 {
+                    capacity *= 62;
+                    len += 1;
+                }
+                len
+            }
+        }
+    }
+
+    // Writes the token for id to out and returns its length.
+    pub fn write_token(&self, id: I, out: &mut [u8]) -> Result<usize> {
+        let len = self.token_len();
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let payload_len = I::BYTES + CHECKSUM_LEN;
+        let mut payload = [0; 8 + CHECKSUM_LEN];
+        self.encrypt(id).write_be(&mut payload[..I::BYTES]);
+        let checksum = crc32(&payload[..I::BYTES]).to_be_bytes();
+        payload[I::BYTES..payload_len].copy_from_slice(&checksum[..CHECKSUM_LEN]);
+
+        let out = &mut out[..len];
+        match self.encoding {
+            TokenEncoding::Base32 => {
+                BASE32.encode_into(&payload[..payload_len], out)?;
+            }
+            TokenEncoding::Base62 => write_base62(&payload[..payload_len], out),
+        }
+        Ok(len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encode(&self, id: I) -> String {
+        let mut out = vec![0; self.token_len()];
+        self.write_token(id, &mut out)
+            .expect("the buffer is token_len bytes");
+        String::from_utf8(out).expect("tokens are ASCII")
+    }
+
+    // Checks the token's checksum and returns the id it stands for.
+    pub fn decode(&self, token: &str) -> Result<I> {
+        let token = token.as_bytes();
+        let len = self.token_len();
+        if token.len() != len {
+            return Err(Error::InvalidLength {
+                expected: len,
+                found: token.len(),
+            });
+        }
+
+        let payload_len = I::BYTES + CHECKSUM_LEN;
+        let mut payload = [0; 8 + CHECKSUM_LEN];
+        match self.encoding {
+            TokenEncoding::Base32 => {
+                BASE32.decode_into(token, &mut payload[..payload_len])?;
+            }
+            TokenEncoding::Base62 => read_base62(token, &mut payload[..payload_len])?,
+        }
+
+        let checksum = crc32(&payload[..I::BYTES]).to_be_bytes();
+        if payload[I::BYTES..payload_len] != checksum[..CHECKSUM_LEN] {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(self.decrypt(I::read_be(&payload[..I::BYTES])))
+    }
+}
+
+// The data as a big-endian number, written in base62 with leading zeros to fill out.
+fn write_base62(data: &[u8], out: &mut [u8]) {
+    let mut value = data.iter().fold(0u128, |v, &b| (v << 8) | b as u128);
+    for c in out.iter_mut().rev() {
+        *c = ALPHANUMERIC[(value % 62) as usize];
+        value /= 62;
+    }
+}
+
+fn read_base62(token: &[u8], out: &mut [u8]) -> Result<()> {
+    let mut value: u128 = 0;
+    for (position, &c) in token.iter().enumerate() {
+        let digit = ALPHANUMERIC
+            .iter()
+            .position(|&b| b == c)
+            .ok_or(Error::InvalidCharacter { position })?;
+        value = value * 62 + digit as u128;
+    }
+
+    // Numbers too large for the payload can't have come from write_base62.
+    if value >> (8 * out.len()) != 0 {
+        return Err(Error::ChecksumMismatch);
+    }
+    out.copy_from_slice(&value.to_be_bytes()[16 - out.len()..]);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::rc5::Rc5;
+
+    fn rc5_32() -> Rc5<u32> {
+        let mut rc5 = Rc5::new(12, 16).unwrap();
+        rc5.setup(b"an example key!!");
+        rc5
+    }
+
+    fn rc5_16() -> Rc5<u16> {
+        let mut rc5 = Rc5::new(16, 8).unwrap();
+        rc5.setup(b"shortkey");
+        rc5
+    }
+
+    #[test]
+    fn round_trip() {
+        let cipher = rc5_32();
+        for encoding in [TokenEncoding::Base62, TokenEncoding::Base32] {
+            let ids: IdObfuscator<'_, _, u64> = IdObfuscator::new(&cipher, encoding).unwrap();
+            for id in [0, 1, 2, 1000, u64::MAX] {
+                let token = ids.encode(id);
+                assert_eq!(token.len(), ids.token_len());
+                assert_eq!(ids.decode(&token).unwrap(), id);
+            }
+        }
+
+        let cipher = rc5_16();
+        for encoding in [TokenEncoding::Base62, TokenEncoding::Base32] {
+            let ids: IdObfuscator<'_, _, u32> = IdObfuscator::new(&cipher, encoding).unwrap();
+            for id in [0, 1, 2, 1000, u32::MAX] {
+                let token = ids.encode(id);
+                assert_eq!(token.len(), ids.token_len());
+                assert_eq!(ids.decode(&token).unwrap(), id);
+            }
+        }
+    }
+
+    #[test]
+    fn token_lengths() {
+        let cipher = rc5_32();
+        let base62: IdObfuscator<'_, _, u64> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base62).unwrap();
+        let base32: IdObfuscator<'_, _, u64> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base32).unwrap();
+        assert_eq!(base62.token_len(), 14);
+        assert_eq!(base32.token_len(), 16);
+
+        let cipher = rc5_16();
+        let base62: IdObfuscator<'_, _, u32> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base62).unwrap();
+        let base32: IdObfuscator<'_, _, u32> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base32).unwrap();
+        assert_eq!(base62.token_len(), 9);
+        assert_eq!(base32.token_len(), 10);
+    }
+
+    #[test]
+    fn sequential_ids_look_unrelated() {
+        let cipher = rc5_32();
+        let ids: IdObfuscator<'_, _, u64> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base62).unwrap();
+
+        let first = ids.encode(41);
+        let second = ids.encode(42);
+        let common = first
+            .bytes()
+            .zip(second.bytes())
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(common < 4);
+        assert_eq!(ids.encrypt(42), cipher_block(&cipher, 42));
+        assert_eq!(ids.decrypt(ids.encrypt(42)), 42);
+    }
+
+    fn cipher_block(cipher: &Rc5<u32>, id: u64) -> u64 {
+        let mut block = id.to_be_bytes();
+        cipher.encrypt_block(&mut block);
+        u64::from_be_bytes(block)
+    }
+
+    #[test]
+    fn bad_tokens_are_rejected() {
+        let cipher = rc5_32();
+        let ids: IdObfuscator<'_, _, u64> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base62).unwrap();
+        let token = ids.encode(12345);
+
+        // Single character substitutions are caught by the checksum or the range check.
+        for i in 0..token.len() {
+            for &c in ALPHANUMERIC {
+                let mut changed = token.clone().into_bytes();
+                if changed[i] == c {
+                    continue;
+                }
+                changed[i] = c;
+                let changed = String::from_utf8(changed).unwrap();
+                assert!(ids.decode(&changed).is_err(), "{}", changed);
+            }
+        }
+
+        assert!(matches!(
+            ids.decode(&token[1..]),
+            Err(Error::InvalidLength { .. })
+        ));
+        let mut bad = token.clone();
+        bad.replace_range(3..4, "-");
+        assert!(matches!(
+            ids.decode(&bad),
+            Err(Error::InvalidCharacter { position: 3 })
+        ));
+    }
+
+    #[test]
+    fn base32_is_case_insensitive() {
+        let cipher = rc5_16();
+        let ids: IdObfuscator<'_, _, u32> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base32).unwrap();
+
+        let token = ids.encode(7);
+        assert_eq!(ids.decode(&token.to_ascii_lowercase()).unwrap(), 7);
+        assert!(matches!(
+            ids.decode("ABCDEFGH1J"),
+            Err(Error::InvalidCharacter { position: 8 })
+        ));
+    }
+
+    // A padded token has the right length but decodes to fewer bytes than a payload.
+    #[test]
+    fn base32_padding_is_rejected() {
+        let cipher = rc5_32();
+        let ids: IdObfuscator<'_, _, u64> =
+            IdObfuscator::new(&cipher, TokenEncoding::Base32).unwrap();
+
+        // 15 characters hold 9 bytes, 'A' leaves the 3 bits over zero, and '=' ends the group.
+        let token = ids.encode(7);
+        let padded = format!("{}A=", &token[..14]);
+        assert!(matches!(
+            ids.decode(&padded),
+            Err(Error::InvalidCharacter { position: 15 })
+        ));
+    }
+
+    #[test]
+    fn block_size_must_match() {
+        let cipher = rc5_32();
+        assert!(matches!(
+            IdObfuscator::<'_, _, u32>::new(&cipher, TokenEncoding::Base62),
+            Err(Error::UnsupportedBlockSize(8))
+        ));
+    }
+}
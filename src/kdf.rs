@@ -0,0 +1,260 @@
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::constant_time::wipe;
+use crate::error::{Error, Result};
+use crate::key_size::MAX_KEY_SIZE;
+use crate::mac::Cmac;
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+// The output length is encoded as a 32-bit count of bits.
+const MAX_OUTPUT_LEN: usize = (u32::MAX / 8) as usize;
+
+enum Mode<'a> {
+    Counter,
+    Feedback { iv: &'a [u8] },
+}
+
+/*
+Key derivation in counter and feedback mode from NIST SP 800-108, with CMAC under a
+cipher keyed with the master key as the PRF.
+
+Block i is CMAC([i]_32 || Label || 0x00 || Context || [L]_32) in counter mode, and
+CMAC(K(i-1) || [i]_32 || Label || 0x00 || Context || [L]_32) in feedback mode, starting
+from K(0) = IV. The output length L is part of the input, so asking for a shorter key
+gives a different key, not a prefix.
+*/
+pub struct Kdf<'a, C> {
+    mac: Cmac<'a, C>,
+    mode: Mode<'a>,
+}
+
+impl<'a, C> Kdf<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn counter(cipher: &'a C) -> Result<Kdf<'a, C>> {
+        Ok(Self {
+            mac: Cmac::new(cipher)?,
+            mode: Mode::Counter,
+        })
+    }
+
+    // The IV is either empty or one block.
+    pub fn feedback(cipher: &'a C, iv: &'a [u8]) -> Result<Kdf<'a, C>> {
+        let n = C::BLOCK_SIZE;
+        if !iv.is_empty() && iv.len() != n {
+            return Err(Error::InvalidLength {
+                expected: n,
+                found: iv.len(),
+            });
+        }
+
+        Ok(Self {
+            mac: Cmac::new(cipher)?,
+            mode: Mode::Feedback { iv },
+        })
+    }
+
+    // Fills out with key material for the given label and context.
+    pub fn derive_into(&self, label: &[u8], context: &[u8], out: &mut [u8]) -> Result<()> {
+        let n = C::BLOCK_SIZE;
+        if out.len() > MAX_OUTPUT_LEN {
+            return Err(Error::RequestTooLarge {
+                max: MAX_OUTPUT_LEN,
+            });
+        }
+        let bits = (8 * out.len() as u32).to_be_bytes();
+
+        let mut previous = [0; MAX_BLOCK_SIZE];
+        let mut previous_len = 0;
+        if let Mode::Feedback { iv } = self.mode {
+            previous[..iv.len()].copy_from_slice(iv);
+            previous_len = iv.len();
+        }
+
+        for (i, chunk) in out.chunks_mut(n).enumerate() {
+            let mut mac = self.mac.clone();
+            if let Mode::Feedback { .. } = self.mode {
+                mac.update(&previous[..previous_len]);
+            }
+            mac.update(&(i as u32 + 1).to_be_bytes());
+            mac.update(label);
+            mac.update(&[0]);
+            mac.update(context);
+            mac.update(&bits);
+            mac.finalize_into(&mut previous[..n])?;
+            previous_len = n;
+
+            chunk.copy_from_slice(&previous[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn derive(&self, label: &[u8], context: &[u8], len: usize) -> Result<Vec<u8>> {
+        let mut out = vec![0; len];
+        self.derive_into(label, context, &mut out)?;
+        Ok(out)
+    }
+
+    // Derives a key_len byte key and sets up an RC5 cipher with it.
+    pub fn derive_rc5<T: UInt>(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        rounds: u8,
+        key_len: usize,
+    ) -> Result<Rc5<T>> {
+        let mut rc5 = Rc5::new(rounds, key_len)?;
+
+        let mut key = [0; MAX_KEY_SIZE];
+        self.derive_into(label, context, &mut key[..key_len])?;
+        rc5.setup(&key[..key_len]);
+
+        wipe(&mut key);
+        Ok(rc5)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::{bytes, rc5, Aes};
+
+    // OpenSSL 3's KBKDF with CMAC over AES-128, see check_kdf in tests/vectors/rc5ref.py.
+    #[test]
+    fn aes_openssl() {
+        let aes = Aes::new(&bytes(0, 16));
+        assert_eq!(
+            Kdf::counter(&aes)
+                .unwrap()
+                .derive(b"tenant-42", b"encryption", 35)
+                .unwrap(),
+            decode_hex("852BD62E069FC9F944D08B085176B509F97ED76C710003F4373529EBBD36A28AD0E390")
+                .unwrap()
+        );
+
+        let iv = bytes(100, 16);
+        assert_eq!(
+            Kdf::feedback(&aes, &iv)
+                .unwrap()
+                .derive(b"tenant-42", b"encryption", 35)
+                .unwrap(),
+            decode_hex("266F146C3EB45EEDC615D2F910F7E9C4662A0BBBF4F9EB4381CCEC2900E0B7505BD0A5")
+                .unwrap()
+        );
+        assert_eq!(
+            Kdf::feedback(&aes, &[])
+                .unwrap()
+                .derive(b"tenant-42", b"encryption", 20)
+                .unwrap(),
+            decode_hex("C617B77CC950E55157A2CE32A3D2B6BD5D067CE1").unwrap()
+        );
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py kdf`.
+    #[test]
+    fn counter_mode() {
+        let rc5_32 = rc5::<u32>(12, 16);
+        let kdf = Kdf::counter(&rc5_32).unwrap();
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"encryption", 16).unwrap(),
+            decode_hex("4B9B7AF34D6A5B86A3D9CEB2C284E987").unwrap()
+        );
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"encryption", 35).unwrap(),
+            decode_hex("B81EF5BB994AAB8518729EEB3F5697B4502A9D21665BF8A43AC5A55EB645736574F39E")
+                .unwrap()
+        );
+
+        let rc5_64 = rc5::<u64>(24, 16);
+        let kdf = Kdf::counter(&rc5_64).unwrap();
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"mac", 40).unwrap(),
+            decode_hex(
+                "6FB7F380B70EDD473E11EFC5B3F1045D4091111399CF93F5AC328809CCFC4353DF093FC4ABCA0F11"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn feedback_mode() {
+        let rc5_32 = rc5::<u32>(12, 16);
+        let iv = bytes(100, 8);
+        let kdf = Kdf::feedback(&rc5_32, &iv).unwrap();
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"encryption", 35).unwrap(),
+            decode_hex("00D981372F591BB17691C021E8DEA113C307FAD5B7AB564B72E7AB4CEA34777F5F9C97")
+                .unwrap()
+        );
+
+        let kdf = Kdf::feedback(&rc5_32, &[]).unwrap();
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"encryption", 20).unwrap(),
+            decode_hex("7930F0BDFB881242C1D99FBDB955B05DEB6C242A").unwrap()
+        );
+
+        let rc5_64 = rc5::<u64>(24, 16);
+        let iv = bytes(100, 16);
+        let kdf = Kdf::feedback(&rc5_64, &iv).unwrap();
+        assert_eq!(
+            kdf.derive(b"tenant-42", b"mac", 40).unwrap(),
+            decode_hex(
+                "AFFB39F26D782053D9A884F99D13CA01B6C34F36FE916F74D508D8517E9FD7147F707A0EE2AD56B5"
+            )
+            .unwrap()
+        );
+
+        assert!(matches!(
+            Kdf::feedback(&rc5_64, &iv[..8]),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn inputs_separate_keys() {
+        let master = rc5::<u32>(12, 16);
+        let kdf = Kdf::counter(&master).unwrap();
+
+        let base = kdf.derive(b"tenant-1", b"encryption", 16).unwrap();
+        assert_ne!(base, kdf.derive(b"tenant-2", b"encryption", 16).unwrap());
+        assert_ne!(base, kdf.derive(b"tenant-1", b"mac", 16).unwrap());
+        // The zero byte keeps the label and context apart.
+        assert_ne!(
+            kdf.derive(b"ab", b"c", 16).unwrap(),
+            kdf.derive(b"a", b"bc", 16).unwrap()
+        );
+        // The length is an input, so a shorter key isn't a prefix of a longer one.
+        assert_ne!(
+            base[..8],
+            kdf.derive(b"tenant-1", b"encryption", 8).unwrap()[..]
+        );
+    }
+
+    // The derived RC5 cipher is keyed with the derived bytes.
+    #[test]
+    fn derive_rc5() {
+        let master = rc5::<u32>(12, 16);
+        let kdf = Kdf::counter(&master).unwrap();
+
+        let derived: Rc5<u64> = kdf.derive_rc5(b"tenant-42", b"disk", 20, 24).unwrap();
+        let mut expected: Rc5<u64> = Rc5::new(20, 24).unwrap();
+        expected.setup(&kdf.derive(b"tenant-42", b"disk", 24).unwrap());
+
+        let mut a = bytes(0, 16);
+        let mut b = a.clone();
+        derived.encrypt_block(&mut a);
+        expected.encrypt_block(&mut b);
+        assert_eq!(a, b);
+
+        assert!(matches!(
+            kdf.derive_rc5::<u32>(b"", b"", 12, MAX_KEY_SIZE + 1),
+            Err(Error::KeySizeError(_))
+        ));
+    }
+}
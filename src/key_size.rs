@@ -1,5 +1,8 @@
 use crate::error::{Error, Result};
 
+// The longest key RC5 accepts, in bytes.
+pub const MAX_KEY_SIZE: usize = 255;
+
 pub struct KeySize(pub u32);
 
 impl Default for KeySize {
@@ -10,8 +13,8 @@ impl Default for KeySize {
 
 impl KeySize {
     pub fn new(size: u32) -> Result<KeySize> {
-        if size > 2040 {
-            Err(Error::KeySizeError("Key size is too large".to_string()))
+        if size as usize > MAX_KEY_SIZE {
+            Err(Error::KeySizeError("Key size is too large"))
         } else {
             Ok(KeySize(size))
         }
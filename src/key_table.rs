@@ -0,0 +1,68 @@
+use core::ops::{Index, IndexMut};
+
+use crate::uint::UInt;
+
+/*
+The expanded key table S, stored inline so the cipher doesn't need an allocator.
+It has room for 2*(R+1) words: the two words added to the block before the first
+round, followed by one pair of words for each of the R rounds.
+*/
+#[derive(Clone)]
+pub struct KeyTable<T, const R: usize> {
+    head: [T; 2],
+    rounds: [[T; 2]; R],
+}
+
+impl<T, const R: usize> KeyTable<T, R>
+where
+    T: UInt,
+{
+    // The number of words the table can hold.
+    pub const CAPACITY: usize = 2 * (R + 1);
+
+    pub fn new() -> Self {
+        Self {
+            head: [T::zero(); 2],
+            rounds: [[T::zero(); 2]; R],
+        }
+    }
+
+    // S[0] and S[1], used for the initial key addition.
+    pub fn head(&self) -> &[T; 2] {
+        &self.head
+    }
+
+    // S[2i] and S[2i+1] for each round i in 1..=R.
+    pub fn rounds(&self) -> &[[T; 2]; R] {
+        &self.rounds
+    }
+}
+
+impl<T, const R: usize> Default for KeyTable<T, R>
+where
+    T: UInt,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const R: usize> Index<usize> for KeyTable<T, R> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match i {
+            0 | 1 => &self.head[i],
+            _ => &self.rounds[i / 2 - 1][i % 2],
+        }
+    }
+}
+
+impl<T, const R: usize> IndexMut<usize> for KeyTable<T, R> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        match i {
+            0 | 1 => &mut self.head[i],
+            _ => &mut self.rounds[i / 2 - 1][i % 2],
+        }
+    }
+}
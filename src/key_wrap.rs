@@ -0,0 +1,430 @@
+use crate::block_cipher::{BlockDecrypt, BlockEncrypt};
+use crate::constant_time::{ct_eq, wipe};
+use crate::error::{Error, Result};
+
+// Key wrap works on 64-bit semiblocks of a 128-bit block, which is RC5 with 64-bit words.
+const BLOCK_SIZE: usize = 16;
+const SEMIBLOCK: usize = 8;
+
+// The default initial value of RFC 3394.
+const IV: [u8; SEMIBLOCK] = [0xA6; SEMIBLOCK];
+// The first half of the alternative initial value of RFC 5649, followed by the key length.
+const AIV_PREFIX: [u8; 4] = [0xA6, 0x59, 0x59, 0xA6];
+
+/*
+Key wrapping as specified in RFC 3394, and with padding for keys of any length as in RFC 5649,
+for ciphers with a 128-bit block such as Rc5<u64>.
+
+A wrapped key is 8 bytes longer than the key, padded to a multiple of 8 for RFC 5649.
+Unwrapping checks the initial value and fails with Error::UnwrapFailed if it doesn't match.
+*/
+pub struct KeyWrap<'a, C> {
+    cipher: &'a C,
+}
+
+impl<'a, C> KeyWrap<'a, C>
+where
+    C: BlockEncrypt + BlockDecrypt,
+{
+    pub fn new(cipher: &'a C) -> Result<KeyWrap<'a, C>> {
+        if C::BLOCK_SIZE != BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize(C::BLOCK_SIZE));
+        }
+        Ok(Self { cipher })
+    }
+
+    // The length of the wrapped form of a key of key_len bytes, with RFC 5649 padding.
+    pub fn padded_wrapped_len(key_len: usize) -> usize {
+        key_len.div_ceil(SEMIBLOCK) * SEMIBLOCK + SEMIBLOCK
+    }
+
+    /*
+    Wraps key into out as in RFC 3394 and returns the wrapped length, key.len() + 8.
+    The key must be a multiple of 8 bytes, and at least 16.
+    */
+    pub fn wrap_key_into(&self, key: &[u8], out: &mut [u8]) -> Result<usize> {
+        if key.len() < 2 * SEMIBLOCK || !key.len().is_multiple_of(SEMIBLOCK) {
+            return Err(Error::InvalidLength {
+                expected: key.len().max(2 * SEMIBLOCK).next_multiple_of(SEMIBLOCK),
+                found: key.len(),
+            });
+        }
+        let len = key.len() + SEMIBLOCK;
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        out[..SEMIBLOCK].copy_from_slice(&IV);
+        out[SEMIBLOCK..len].copy_from_slice(key);
+        self.wrap(&mut out[..len]);
+        Ok(len)
+    }
+
+    // Unwraps an RFC 3394 wrapped key into out and returns the key length.
+    pub fn unwrap_key_into(&self, wrapped: &[u8], out: &mut [u8]) -> Result<usize> {
+        if wrapped.len() < 3 * SEMIBLOCK || !wrapped.len().is_multiple_of(SEMIBLOCK) {
+            return Err(Error::UnwrapFailed);
+        }
+        let len = wrapped.len() - SEMIBLOCK;
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        let mut iv = [0; SEMIBLOCK];
+        iv.copy_from_slice(&wrapped[..SEMIBLOCK]);
+        out[..len].copy_from_slice(&wrapped[SEMIBLOCK..]);
+        self.unwrap(&mut iv, &mut out[..len]);
+
+        if !ct_eq(&iv, &IV) {
+            wipe(&mut out[..len]);
+            return Err(Error::UnwrapFailed);
+        }
+        Ok(len)
+    }
+
+    // Wraps a key of any non-zero length as in RFC 5649 and returns the wrapped length.
+    pub fn wrap_key_padded_into(&self, key: &[u8], out: &mut [u8]) -> Result<usize> {
+        if key.is_empty() || key.len() > u32::MAX as usize {
+            return Err(Error::InvalidLength {
+                expected: 1,
+                found: key.len(),
+            });
+        }
+        let len = Self::padded_wrapped_len(key.len());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        out[..4].copy_from_slice(&AIV_PREFIX);
+        out[4..SEMIBLOCK].copy_from_slice(&(key.len() as u32).to_be_bytes());
+        out[SEMIBLOCK..SEMIBLOCK + key.len()].copy_from_slice(key);
+        for byte in &mut out[SEMIBLOCK + key.len()..len] {
+            *byte = 0;
+        }
+
+        // A single semiblock of key is encrypted as one block, without the wrapping rounds.
+        if len == BLOCK_SIZE {
+            self.cipher.encrypt_block(&mut out[..BLOCK_SIZE]);
+        } else {
+            self.wrap(&mut out[..len]);
+        }
+        Ok(len)
+    }
+
+    // Unwraps an RFC 5649 wrapped key into out and returns the key length.
+    pub fn unwrap_key_padded_into(&self, wrapped: &[u8], out: &mut [u8]) -> Result<usize> {
+        if wrapped.len() < BLOCK_SIZE || !wrapped.len().is_multiple_of(SEMIBLOCK) {
+            return Err(Error::UnwrapFailed);
+        }
+        let padded_len = wrapped.len() - SEMIBLOCK;
+        if out.len() < padded_len {
+            return Err(Error::BufferTooSmall { needed: padded_len });
+        }
+
+        let mut aiv = [0; SEMIBLOCK];
+        if wrapped.len() == BLOCK_SIZE {
+            let mut block = [0; BLOCK_SIZE];
+            block.copy_from_slice(wrapped);
+            self.cipher.decrypt_block(&mut block);
+            aiv.copy_from_slice(&block[..SEMIBLOCK]);
+            out[..SEMIBLOCK].copy_from_slice(&block[SEMIBLOCK..]);
+            wipe(&mut block);
+        } else {
+            aiv.copy_from_slice(&wrapped[..SEMIBLOCK]);
+            out[..padded_len].copy_from_slice(&wrapped[SEMIBLOCK..]);
+            self.unwrap(&mut aiv, &mut out[..padded_len]);
+        }
+
+        // The length has to fall in the last semiblock, and the padding has to be zero.
+        let mut len = [0; 4];
+        len.copy_from_slice(&aiv[4..]);
+        let len = u32::from_be_bytes(len) as usize;
+        let valid = ct_eq(&aiv[..4], &AIV_PREFIX)
+            && len <= padded_len
+            && len + SEMIBLOCK > padded_len
+            && out[len..padded_len].iter().all(|&b| b == 0);
+        if !valid {
+            wipe(&mut out[..padded_len]);
+            return Err(Error::UnwrapFailed);
+        }
+        Ok(len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; key.len() + SEMIBLOCK];
+        self.wrap_key_into(key, &mut out)?;
+        Ok(out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn unwrap_key(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; wrapped.len().saturating_sub(SEMIBLOCK)];
+        let len = self.unwrap_key_into(wrapped, &mut out)?;
+        out.truncate(len);
+        Ok(out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn wrap_key_padded(&self, key: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; Self::padded_wrapped_len(key.len())];
+        self.wrap_key_padded_into(key, &mut out)?;
+        Ok(out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn unwrap_key_padded(&self, wrapped: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; wrapped.len().saturating_sub(SEMIBLOCK)];
+        let len = self.unwrap_key_padded_into(wrapped, &mut out)?;
+        out.truncate(len);
+        Ok(out)
+    }
+
+    // W, the wrapping function, on the initial value followed by the semiblocks of the key.
+    fn wrap(&self, data: &mut [u8]) {
+        let (a, r) = data.split_at_mut(SEMIBLOCK);
+        let n = r.len() / SEMIBLOCK;
+        let mut block = [0; BLOCK_SIZE];
+
+        for j in 0..6 {
+            for (i, semiblock) in r.chunks_exact_mut(SEMIBLOCK).enumerate() {
+                // B = E(A | R[i]), A = MSB(B) ^ t, R[i] = LSB(B)
+                block[..SEMIBLOCK].copy_from_slice(a);
+                block[SEMIBLOCK..].copy_from_slice(semiblock);
+                self.cipher.encrypt_block(&mut block);
+
+                let t = (n * j + i + 1) as u64;
+                a.copy_from_slice(&block[..SEMIBLOCK]);
+                xor_counter(a, t);
+                semiblock.copy_from_slice(&block[SEMIBLOCK..]);
+            }
+        }
+        wipe(&mut block);
+    }
+
+    // W^-1, leaving the recovered initial value in a and the key in r.
+    fn unwrap(&self, a: &mut [u8; SEMIBLOCK], r: &mut [u8]) {
+        let n = r.len() / SEMIBLOCK;
+        let mut block = [0; BLOCK_SIZE];
+
+        for j in (0..6).rev() {
+            for (i, semiblock) in r.chunks_exact_mut(SEMIBLOCK).enumerate().rev() {
+                // B = D((A ^ t) | R[i]), A = MSB(B), R[i] = LSB(B)
+                let t = (n * j + i + 1) as u64;
+                xor_counter(a, t);
+                block[..SEMIBLOCK].copy_from_slice(a);
+                block[SEMIBLOCK..].copy_from_slice(semiblock);
+                self.cipher.decrypt_block(&mut block);
+
+                a.copy_from_slice(&block[..SEMIBLOCK]);
+                semiblock.copy_from_slice(&block[SEMIBLOCK..]);
+            }
+        }
+        wipe(&mut block);
+    }
+}
+
+fn xor_counter(a: &mut [u8], t: u64) {
+    for (x, y) in a.iter_mut().zip(t.to_be_bytes()) {
+        *x ^= y;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::testing::{bytes, rc5, rc5_with_key, Aes};
+
+    fn rc5_64() -> Rc5<u64> {
+        rc5(24, 24)
+    }
+
+    fn key(len: usize) -> Vec<u8> {
+        bytes(0x10, len)
+    }
+
+    // RFC 3394 sections 4.1 and 4.6, and the two examples of RFC 5649 section 6.
+    #[test]
+    fn aes_rfcs() {
+        let key =
+            decode_hex("00112233445566778899AABBCCDDEEFF000102030405060708090A0B0C0D0E0F").unwrap();
+        for (kek_len, key_len, wrapped) in [
+            (16, 16, "1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5"),
+            (
+                32,
+                32,
+                "28C9F404C4B810F4CBCCB35CFB87F8263F5786E2D80ED326CBC7F0E71A99F43BFB988B9B7A02DD21",
+            ),
+        ] {
+            let aes = Aes::new(&bytes(0, kek_len));
+            let kw = KeyWrap::new(&aes).unwrap();
+            let wrapped = decode_hex(wrapped).unwrap();
+            assert_eq!(kw.wrap_key(&key[..key_len]).unwrap(), wrapped);
+            assert_eq!(kw.unwrap_key(&wrapped).unwrap(), key[..key_len]);
+        }
+
+        let aes =
+            Aes::new(&decode_hex("5840DF6E29B02AF1AB493B705BF16EA1AE8338F4DCC176A8").unwrap());
+        let kw = KeyWrap::new(&aes).unwrap();
+        for (key, wrapped) in [
+            (
+                "C37B7E6492584340BED12207808941155068F738",
+                "138BDEAA9B8FA7FC61F97742E72248EE5AE6AE5360D1AE6A5F54F373FA543B6A",
+            ),
+            ("466F7250617369", "AFBEB0F07DFBF5419200F2CCB50BB24F"),
+        ] {
+            let key = decode_hex(key).unwrap();
+            let wrapped = decode_hex(wrapped).unwrap();
+            assert_eq!(kw.wrap_key_padded(&key).unwrap(), wrapped);
+            assert_eq!(kw.unwrap_key_padded(&wrapped).unwrap(), key);
+        }
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py key_wrap`.
+    #[test]
+    fn rfc_3394() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+
+        let vectors = [
+            (16, "3BEFF0EE8621700AE3EC4D6FDCF4C84D233C8C5A452F6CA8"),
+            (
+                24,
+                "32921A9728140DD0EB4BE87F14F5BA6FDABA2BA04022FB9586A1B52F20F99F75",
+            ),
+            (
+                32,
+                "0C3A9B97D6BF49D359458879A6445B6536C7CF7D175E807305341F783D20B5C65617C648E09A1191",
+            ),
+        ];
+        for (len, wrapped) in vectors {
+            let wrapped = decode_hex(wrapped).unwrap();
+            assert_eq!(kw.wrap_key(&key(len)).unwrap(), wrapped);
+            assert_eq!(kw.unwrap_key(&wrapped).unwrap(), key(len));
+        }
+    }
+
+    #[test]
+    fn rfc_5649() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+
+        let vectors = [
+            (1, "F229A43B76959F777311556003D93B71"),
+            (7, "F887624B814B0BE56A58DB214BFC8788"),
+            (8, "4F15BB038A47716FA243B68823DCF79E"),
+            (9, "36B1E4A157AF0E674C9143A84FBE311D279DC7B8BE9EB7C7"),
+            (
+                20,
+                "85FAB51AF51C6008EFAED02645979B1E93B27DD24FA7201F5162B2B89B5FB05C",
+            ),
+        ];
+        for (len, wrapped) in vectors {
+            let wrapped = decode_hex(wrapped).unwrap();
+            assert_eq!(kw.wrap_key_padded(&key(len)).unwrap(), wrapped);
+            assert_eq!(kw.unwrap_key_padded(&wrapped).unwrap(), key(len));
+        }
+    }
+
+    #[test]
+    fn corruption_is_detected() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+
+        let wrapped = kw.wrap_key(&key(32)).unwrap();
+        for i in 0..wrapped.len() {
+            let mut corrupted = wrapped.clone();
+            corrupted[i] ^= 0x80;
+            assert!(matches!(
+                kw.unwrap_key(&corrupted),
+                Err(Error::UnwrapFailed)
+            ));
+        }
+        assert!(matches!(
+            kw.unwrap_key(&wrapped[..32]),
+            Err(Error::UnwrapFailed)
+        ));
+
+        for len in [5, 8, 20] {
+            let wrapped = kw.wrap_key_padded(&key(len)).unwrap();
+            for i in 0..wrapped.len() {
+                let mut corrupted = wrapped.clone();
+                corrupted[i] ^= 0x01;
+                assert!(matches!(
+                    kw.unwrap_key_padded(&corrupted),
+                    Err(Error::UnwrapFailed)
+                ));
+            }
+        }
+    }
+
+    // The two formats have different initial values, so neither unwraps as the other.
+    #[test]
+    fn formats_are_distinct() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+
+        let wrapped = kw.wrap_key(&key(16)).unwrap();
+        assert!(matches!(
+            kw.unwrap_key_padded(&wrapped),
+            Err(Error::UnwrapFailed)
+        ));
+        let padded = kw.wrap_key_padded(&key(16)).unwrap();
+        assert!(matches!(kw.unwrap_key(&padded), Err(Error::UnwrapFailed)));
+    }
+
+    #[test]
+    fn wrong_key() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+        let wrapped = kw.wrap_key(&key(16)).unwrap();
+
+        let other = rc5_with_key::<u64>(24, &[0; 24]);
+        let other = KeyWrap::new(&other).unwrap();
+
+        let mut out = [0xFF; 16];
+        assert!(matches!(
+            other.unwrap_key_into(&wrapped, &mut out),
+            Err(Error::UnwrapFailed)
+        ));
+        // Nothing of the bad unwrap is left in the output.
+        assert_eq!(out, [0; 16]);
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let cipher = rc5_64();
+        let kw = KeyWrap::new(&cipher).unwrap();
+
+        assert!(matches!(
+            kw.wrap_key(&key(8)),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 8
+            })
+        ));
+        assert!(matches!(
+            kw.wrap_key(&key(20)),
+            Err(Error::InvalidLength {
+                expected: 24,
+                found: 20
+            })
+        ));
+        assert!(matches!(
+            kw.wrap_key_padded(&[]),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            kw.wrap_key_into(&key(16), &mut [0; 23]),
+            Err(Error::BufferTooSmall { needed: 24 })
+        ));
+
+        let rc5_32 = rc5::<u32>(12, 16);
+        assert!(matches!(
+            KeyWrap::new(&rc5_32),
+            Err(Error::UnsupportedBlockSize(8))
+        ));
+    }
+}
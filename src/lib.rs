@@ -1,26 +1,99 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod aead;
+mod block_cipher;
+mod byte_order;
+mod cbc;
+mod ccm;
+mod checksum;
+mod constant_time;
+mod ctr;
 mod custom_uint;
+mod der;
+mod drbg;
+mod eax;
+mod encoding;
+mod envelope;
 mod error;
+mod fpe;
 mod from_bytes;
+mod gcm;
+mod gf;
+mod hash;
+#[cfg(feature = "std")]
 mod hex;
+mod id_token;
+mod kdf;
 mod key_size;
+mod key_table;
+mod key_wrap;
+mod mac;
+mod md5;
+mod ocb;
+mod openssl;
+mod pbes2;
 mod rc5;
+mod rc5_fixed;
+mod rng;
+mod schedule;
+mod sha256;
+mod siv;
+#[cfg(all(test, feature = "std"))]
+mod testing;
 mod uint;
+mod xts;
 
-pub use rc5::Rc5;
+pub use aead::Aead;
+pub use block_cipher::{BlockCipher, BlockDecrypt, BlockEncrypt, MAX_BLOCK_SIZE};
+pub use byte_order::{BigEndian, ByteOrder, LittleEndian};
+pub use ccm::Ccm;
+pub use rc5::{Rc5, MAX_ROUNDS};
+pub use rc5_fixed::Rc5Fixed;
+pub use schedule::{schedule_len, SCHEDULE_MAGIC, SCHEDULE_VERSION};
 
+pub use cbc::Cbc;
 pub use custom_uint::{U128, U16, U24, U256, U32, U64, U8, U80};
+pub use der::{Rc5Algorithm, Rc5AlgorithmIdentifier, MAX_ALGORITHM_IDENTIFIER_LEN};
+pub use drbg::{CtrDrbg, DRBG_KEY_LEN};
+pub use eax::Eax;
+pub use encoding::{Decoder, Encoder, Encoding};
+pub use envelope::{
+    Envelope, EnvelopeHeader, EnvelopeMode, ENVELOPE_MAGIC, ENVELOPE_VERSION, MIN_TAG_LEN,
+};
+pub use error::{Error, Result};
+pub use fpe::{Fpe, ALPHANUMERIC, DECIMAL, LOWERCASE, MIN_FPE_DOMAIN};
+pub use gcm::{Gcm, GCM_NONCE_SIZE};
+pub use hash::{Compression, Rc5Hash};
+#[cfg(feature = "std")]
+pub use hex::{Armor, ARMOR_LABEL};
+pub use id_token::{Id, IdObfuscator, TokenEncoding};
+pub use kdf::Kdf;
+pub use key_size::MAX_KEY_SIZE;
+pub use key_table::KeyTable;
+pub use key_wrap::KeyWrap;
+pub use mac::{CbcMac, Cmac};
+pub use ocb::Ocb;
+pub use openssl::{
+    OpenSslKdf, OpenSslRc5, OPENSSL_MAGIC, OPENSSL_PBKDF2_ITERATIONS, OPENSSL_SALT_LEN,
+};
+pub use pbes2::{pbkdf2_hmac_sha256, Pbes2, Rc5CbcParameters, RC5_CBC_VERSION};
+pub use rng::{CounterRng, RNG_ROUNDS};
+pub use sha256::{HmacSha256, Sha256, SHA256_BLOCK_LEN, SHA256_OUTPUT_LEN};
+pub use siv::Siv;
 pub use uint::UInt;
+pub use xts::Xts;
 
 /*
 Encode the plaintext using the RC5 algorithm with the given key and number of rounds.
 The word size is determined by the type T, which must implement the UInt trait.
 */
+#[cfg(feature = "std")]
 pub fn encode<T>(rounds: u8, key: Vec<u8>, plaintext: Vec<u8>) -> Vec<u8>
 where
     T: UInt,
 {
     let mut rc5: Rc5<T> = Rc5::new(rounds, key.len()).unwrap();
-    rc5.setup(key);
+    rc5.setup(&key);
     let mut ciphertext = Vec::new();
     rc5.encode(plaintext, &mut ciphertext);
     ciphertext
@@ -30,18 +103,21 @@ where
 Decode the plaintext using the RC5 algorithm with the given key and number of rounds.
 The word size is determined by the type T, which must implement the UInt trait.
 */
+#[cfg(feature = "std")]
 pub fn decode<T>(rounds: u8, key: Vec<u8>, ciphertext: Vec<u8>) -> Vec<u8>
 where
     T: UInt,
 {
     let mut rc5: Rc5<T> = Rc5::new(rounds, key.len()).unwrap();
-    rc5.setup(key);
+    rc5.setup(&key);
     let mut plaintext = Vec::new();
     rc5.decode(ciphertext, &mut plaintext);
     plaintext
 }
 
-#[cfg(test)]
+// These tests predate running clippy on the test code, so their style lints are allowed.
+#[cfg(all(test, feature = "std"))]
+#[allow(clippy::op_ref, clippy::useless_vec)]
 mod tests {
     use crate::custom_uint::{U128, U16, U24, U32, U64, U8, U80};
     use crate::error::Result;
@@ -0,0 +1,466 @@
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::constant_time::ct_eq;
+use crate::error::{Error, Result};
+use crate::gf::{dbl, reduction_polynomial};
+
+/*
+CMAC (OMAC1) as specified in NIST SP 800-38B, for any block size with a known reduction polynomial.
+Tags can be truncated by finalizing into, or verifying, fewer than BLOCK_SIZE bytes.
+*/
+pub struct Cmac<'a, C> {
+    cipher: &'a C,
+    k1: [u8; MAX_BLOCK_SIZE],
+    k2: [u8; MAX_BLOCK_SIZE],
+    state: [u8; MAX_BLOCK_SIZE],
+    // The last block seen so far, kept back until we know whether it is the final one.
+    buffer: [u8; MAX_BLOCK_SIZE],
+    buffer_len: usize,
+}
+
+impl<'a, C> Clone for Cmac<'a, C> {
+    fn clone(&self) -> Self {
+        Self {
+            cipher: self.cipher,
+            k1: self.k1,
+            k2: self.k2,
+            state: self.state,
+            buffer: self.buffer,
+            buffer_len: self.buffer_len,
+        }
+    }
+}
+
+impl<'a, C> Cmac<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C) -> Result<Cmac<'a, C>> {
+        let n = C::BLOCK_SIZE;
+        let polynomial = reduction_polynomial(n)?;
+
+        let mut k1 = [0; MAX_BLOCK_SIZE];
+        cipher.encrypt_block(&mut k1[..n]);
+        dbl(&mut k1[..n], polynomial);
+        let mut k2 = k1;
+        dbl(&mut k2[..n], polynomial);
+
+        Ok(Self {
+            cipher,
+            k1,
+            k2,
+            state: [0; MAX_BLOCK_SIZE],
+            buffer: [0; MAX_BLOCK_SIZE],
+            buffer_len: 0,
+        })
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        let n = C::BLOCK_SIZE;
+
+        while !data.is_empty() {
+            if self.buffer_len == n {
+                xor(&mut self.state[..n], &self.buffer[..n]);
+                self.cipher.encrypt_block(&mut self.state[..n]);
+                self.buffer_len = 0;
+            }
+
+            let take = (n - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+        }
+    }
+
+    // Writes the first tag.len() bytes of the tag, tag must be 1 to BLOCK_SIZE bytes long.
+    pub fn finalize_into(mut self, tag: &mut [u8]) -> Result<()> {
+        let n = C::BLOCK_SIZE;
+        check_tag_length(tag.len(), n)?;
+
+        if self.buffer_len == n {
+            xor(&mut self.buffer[..n], &self.k1[..n]);
+        } else {
+            self.buffer[self.buffer_len] = 0x80;
+            for byte in &mut self.buffer[self.buffer_len + 1..n] {
+                *byte = 0;
+            }
+            xor(&mut self.buffer[..n], &self.k2[..n]);
+        }
+
+        xor(&mut self.state[..n], &self.buffer[..n]);
+        self.cipher.encrypt_block(&mut self.state[..n]);
+
+        tag.copy_from_slice(&self.state[..tag.len()]);
+        Ok(())
+    }
+
+    // Checks a possibly truncated tag in constant time.
+    pub fn verify(self, tag: &[u8]) -> Result<()> {
+        check_tag_length(tag.len(), C::BLOCK_SIZE)?;
+        let mut expected = [0; MAX_BLOCK_SIZE];
+        self.finalize_into(&mut expected[..tag.len()])?;
+        check_tag(&expected[..tag.len()], tag)
+    }
+}
+
+/*
+CBC-MAC with the message length prepended as the first block, which makes it secure
+for messages of varying length. The length has to be known before the message is processed.
+*/
+pub struct CbcMac<'a, C> {
+    cipher: &'a C,
+    state: [u8; MAX_BLOCK_SIZE],
+    buffer: [u8; MAX_BLOCK_SIZE],
+    buffer_len: usize,
+    message_len: u64,
+    remaining: u64,
+}
+
+impl<'a, C> CbcMac<'a, C>
+where
+    C: BlockEncrypt,
+{
+    // Starts a MAC over a message of exactly message_len bytes.
+    pub fn new(cipher: &'a C, message_len: u64) -> Result<CbcMac<'a, C>> {
+        let n = C::BLOCK_SIZE;
+
+        // The length is encoded big-endian in a single block, it must fit.
+        let mut state = [0; MAX_BLOCK_SIZE];
+        let len_bytes = message_len.to_be_bytes();
+        let used = len_bytes.iter().take_while(|&&b| b == 0).count();
+        if 8 - used > n {
+            return Err(Error::MessageTooLong);
+        }
+        let width = n.min(8);
+        state[n - width..n].copy_from_slice(&len_bytes[8 - width..]);
+        cipher.encrypt_block(&mut state[..n]);
+
+        Ok(Self {
+            cipher,
+            state,
+            buffer: [0; MAX_BLOCK_SIZE],
+            buffer_len: 0,
+            message_len,
+            remaining: message_len,
+        })
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) -> Result<()> {
+        if data.len() as u64 > self.remaining {
+            return Err(Error::MessageTooLong);
+        }
+        self.remaining -= data.len() as u64;
+
+        let n = C::BLOCK_SIZE;
+        while !data.is_empty() {
+            let take = (n - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len == n {
+                self.process_buffer();
+            }
+        }
+        Ok(())
+    }
+
+    fn process_buffer(&mut self) {
+        let n = C::BLOCK_SIZE;
+        xor(&mut self.state[..n], &self.buffer[..n]);
+        self.cipher.encrypt_block(&mut self.state[..n]);
+        self.buffer_len = 0;
+    }
+
+    // Writes the first tag.len() bytes of the tag, tag must be 1 to BLOCK_SIZE bytes long.
+    pub fn finalize_into(mut self, tag: &mut [u8]) -> Result<()> {
+        let n = C::BLOCK_SIZE;
+        check_tag_length(tag.len(), n)?;
+
+        if self.remaining != 0 {
+            return Err(Error::InvalidLength {
+                expected: self.message_len as usize,
+                found: (self.message_len - self.remaining) as usize,
+            });
+        }
+
+        // Zero pad the final partial block.
+        if self.buffer_len > 0 {
+            for byte in &mut self.buffer[self.buffer_len..n] {
+                *byte = 0;
+            }
+            self.process_buffer();
+        }
+
+        tag.copy_from_slice(&self.state[..tag.len()]);
+        Ok(())
+    }
+
+    // Checks a possibly truncated tag in constant time.
+    pub fn verify(self, tag: &[u8]) -> Result<()> {
+        check_tag_length(tag.len(), C::BLOCK_SIZE)?;
+        let mut expected = [0; MAX_BLOCK_SIZE];
+        self.finalize_into(&mut expected[..tag.len()])?;
+        check_tag(&expected[..tag.len()], tag)
+    }
+}
+
+fn check_tag_length(len: usize, block_size: usize) -> Result<()> {
+    if len == 0 || len > block_size {
+        Err(Error::InvalidTagLength(len))
+    } else {
+        Ok(())
+    }
+}
+
+fn check_tag(expected: &[u8], tag: &[u8]) -> Result<()> {
+    if ct_eq(expected, tag) {
+        Ok(())
+    } else {
+        Err(Error::AuthenticationFailed)
+    }
+}
+
+pub(crate) fn xor(a: &mut [u8], b: &[u8]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x ^= y;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::custom_uint::{U24, U64};
+    use crate::hex::decode_hex;
+    use crate::testing::{bytes, rc5, Aes};
+
+    fn cmac<C: BlockEncrypt>(cipher: &C, message: &[u8]) -> Vec<u8> {
+        let mut mac = Cmac::new(cipher).unwrap();
+        mac.update(message);
+        let mut tag = vec![0; C::BLOCK_SIZE];
+        mac.finalize_into(&mut tag).unwrap();
+        tag
+    }
+
+    fn cbc_mac<C: BlockEncrypt>(cipher: &C, message: &[u8]) -> Vec<u8> {
+        let mut mac = CbcMac::new(cipher, message.len() as u64).unwrap();
+        mac.update(message).unwrap();
+        let mut tag = vec![0; C::BLOCK_SIZE];
+        mac.finalize_into(&mut tag).unwrap();
+        tag
+    }
+
+    // Generated by `python3 tests/vectors/rc5ref.py mac`, for each block size.
+    fn check<C: BlockEncrypt>(cipher: &C, vectors: &[(usize, &str, &str)]) {
+        for (len, cmac_tag, cbc_mac_tag) in vectors {
+            let m = bytes(0, *len);
+            assert_eq!(cmac(cipher, &m), decode_hex(cmac_tag).unwrap());
+            assert_eq!(cbc_mac(cipher, &m), decode_hex(cbc_mac_tag).unwrap());
+        }
+    }
+
+    // The examples of RFC 4493 section 4.
+    #[test]
+    fn aes_rfc_4493() {
+        let aes = Aes::new(&decode_hex("2B7E151628AED2A6ABF7158809CF4F3C").unwrap());
+        let m = decode_hex(
+            "6BC1BEE22E409F96E93D7E117393172AAE2D8A571E03AC9C9EB76FAC45AF8E51\
+             30C81C46A35CE411E5FBC1191A0A52EFF69F2445DF4F9B17AD2B417BE66C3710",
+        )
+        .unwrap();
+        for (len, tag) in [
+            (0, "BB1D6929E95937287FA37D129B756746"),
+            (16, "070A16B46B4D4144F79BDD9DD04A287C"),
+            (40, "DFA66747DE9AE63030CA32611497C827"),
+            (64, "51F0BEBF7E3B9D92FC49741779363CFE"),
+        ] {
+            assert_eq!(cmac(&aes, &m[..len]), decode_hex(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn rc5_8() {
+        check(
+            &rc5::<u8>(12, 4),
+            &[
+                (0, "ABAF", "B591"),
+                (2, "D61E", "D438"),
+                (5, "7E61", "669B"),
+                (40, "92C7", "1354"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_16() {
+        check(
+            &rc5::<u16>(16, 8),
+            &[
+                (0, "0DE69D95", "88D6C683"),
+                (4, "91335E84", "4CD44709"),
+                (7, "A83166B4", "BCD2447D"),
+                (40, "EC158C62", "2B5F0AB5"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_32() {
+        check(
+            &rc5::<u32>(12, 16),
+            &[
+                (0, "06C002BDB08B3C68", "B05F67ED0913B5A2"),
+                (8, "D204DC3D863CB307", "B15B39EBF6F614F4"),
+                (11, "6272D3B9048A84DB", "E39A316ED00A2B16"),
+                (40, "10D6975C03A3D1F9", "2C174000FEFD6198"),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        let vectors = [
+            (
+                0,
+                "688F689FD6F391FA7833405F58E036B9",
+                "D00C266D198FDE63588F0F062C2649AE",
+            ),
+            (
+                16,
+                "0465A80293A05C635D31C81A0EBDC406",
+                "A5EB6129758C057FA14821DFCA59F711",
+            ),
+            (
+                19,
+                "70BC2BA231E518C39A37088048B390A9",
+                "AEC7F56AB960AABBE2009187F6847CAD",
+            ),
+            (
+                40,
+                "FD66CCDBD2E65E41E73B744FE8CA8A8A",
+                "E7B9521B5D3E7E78027CC25592CBEF82",
+            ),
+        ];
+        check(&rc5::<u64>(24, 24), &vectors);
+        check(&rc5::<U64>(24, 24), &vectors[..1]);
+    }
+
+    #[test]
+    fn rc5_128() {
+        check(
+            &rc5::<u128>(28, 32),
+            &[
+                (
+                    0,
+                    "B8D9CBE4A9968CB116F99860CCA2452D4938C472E09BC96E18EEB3499F2635BD",
+                    "1474A759EE123E49CFCB456C88940EA96F8B3684424DE8126A0A68C366363668",
+                ),
+                (
+                    35,
+                    "5C2EAD0857081A81588EB78FE4EF48ECC6C05815CE315A487F9B86131B765224",
+                    "BEFCC22C7AF4622BA5617D5736CDF11072309C912A145F7093CA39EE47349B36",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let cipher = rc5::<u32>(12, 16);
+        let m = bytes(0, 40);
+
+        let mut mac = Cmac::new(&cipher).unwrap();
+        let mut cbc = CbcMac::new(&cipher, m.len() as u64).unwrap();
+        for chunk in m.chunks(3) {
+            mac.update(chunk);
+            cbc.update(chunk).unwrap();
+        }
+
+        let mut tag = [0; 8];
+        mac.finalize_into(&mut tag).unwrap();
+        assert_eq!(tag.to_vec(), cmac(&cipher, &m));
+        cbc.finalize_into(&mut tag).unwrap();
+        assert_eq!(tag.to_vec(), cbc_mac(&cipher, &m));
+    }
+
+    #[test]
+    fn truncated_tags() {
+        let cipher = rc5::<u64>(24, 24);
+        let m = bytes(0, 19);
+        let full = cmac(&cipher, &m);
+
+        let mut mac = Cmac::new(&cipher).unwrap();
+        mac.update(&m);
+        let mut tag = [0; 8];
+        mac.clone().finalize_into(&mut tag).unwrap();
+        assert_eq!(tag, full[..8]);
+        mac.clone().verify(&full[..8]).unwrap();
+        mac.clone().verify(&full).unwrap();
+
+        assert!(matches!(
+            mac.clone().finalize_into(&mut [0; 17]),
+            Err(Error::InvalidTagLength(17))
+        ));
+        assert!(matches!(
+            mac.clone().verify(&[0; 17]),
+            Err(Error::InvalidTagLength(17))
+        ));
+        assert!(matches!(
+            mac.clone().verify(&[0; MAX_BLOCK_SIZE + 1]),
+            Err(Error::InvalidTagLength(65))
+        ));
+        assert!(matches!(mac.verify(&[]), Err(Error::InvalidTagLength(0))));
+
+        for (tag, len) in [(&[0; MAX_BLOCK_SIZE + 1][..], 65), (&[], 0)] {
+            let mut mac = CbcMac::new(&cipher, m.len() as u64).unwrap();
+            mac.update(&m).unwrap();
+            assert!(matches!(mac.verify(tag), Err(Error::InvalidTagLength(l)) if l == len));
+        }
+    }
+
+    #[test]
+    fn tampered_tag() {
+        let cipher = rc5::<u32>(12, 16);
+        let m = bytes(0, 11);
+
+        let mut tag = cmac(&cipher, &m);
+        tag[0] ^= 1;
+        let mut mac = Cmac::new(&cipher).unwrap();
+        mac.update(&m);
+        assert!(matches!(mac.verify(&tag), Err(Error::AuthenticationFailed)));
+
+        let mut tag = cbc_mac(&cipher, &m);
+        tag[7] ^= 0x80;
+        let mut mac = CbcMac::new(&cipher, m.len() as u64).unwrap();
+        mac.update(&m).unwrap();
+        assert!(matches!(mac.verify(&tag), Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn cbc_mac_length() {
+        let cipher = rc5::<u8>(12, 4);
+        assert!(matches!(
+            CbcMac::new(&cipher, 1 << 16),
+            Err(Error::MessageTooLong)
+        ));
+
+        let mut mac = CbcMac::new(&cipher, 4).unwrap();
+        assert!(matches!(mac.update(&[0; 5]), Err(Error::MessageTooLong)));
+        mac.update(&[0; 3]).unwrap();
+        assert!(matches!(
+            mac.finalize_into(&mut [0; 2]),
+            Err(Error::InvalidLength {
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn unsupported_block_size() {
+        let cipher = rc5::<U24>(4, 0);
+        assert!(matches!(
+            Cmac::new(&cipher),
+            Err(Error::UnsupportedBlockSize(6))
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+pub(crate) const MD5_OUTPUT_LEN: usize = 16;
+const MD5_BLOCK_LEN: usize = 64;
+
+const S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/*
+MD5 from RFC 1321, only for reading and writing files whose keys were derived with it,
+like `openssl enc -md md5`. It is broken as a hash and must not be used for anything new.
+*/
+#[derive(Clone)]
+pub(crate) struct Md5 {
+    state: [u32; 4],
+    buffer: [u8; MD5_BLOCK_LEN],
+    buffer_len: usize,
+    length: u64,
+}
+
+impl Md5 {
+    pub(crate) fn new() -> Md5 {
+        Self {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; MD5_BLOCK_LEN],
+            buffer_len: 0,
+            length: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        while !data.is_empty() {
+            let take = (MD5_BLOCK_LEN - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len == MD5_BLOCK_LEN {
+                let block = self.buffer;
+                self.compress(&block);
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    // The same padding as SHA-256, except that the length is little-endian.
+    pub(crate) fn finalize(mut self) -> [u8; MD5_OUTPUT_LEN] {
+        let bits = self.length.wrapping_mul(8);
+
+        let mut block = self.buffer;
+        block[self.buffer_len] = 0x80;
+        for byte in &mut block[self.buffer_len + 1..] {
+            *byte = 0;
+        }
+        if self.buffer_len + 1 > MD5_BLOCK_LEN - 8 {
+            self.compress(&block);
+            block = [0; MD5_BLOCK_LEN];
+        }
+        block[MD5_BLOCK_LEN - 8..].copy_from_slice(&bits.to_le_bytes());
+        self.compress(&block);
+
+        let mut out = [0; MD5_OUTPUT_LEN];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; MD5_BLOCK_LEN]) {
+        let mut m = [0u32; 16];
+        for (word, chunk) in m.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(K[i])
+                .wrapping_add(m[g])
+                .rotate_left(S[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d].iter()) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    // Test suite from RFC 1321 appendix A.5.
+    #[test]
+    fn rfc_1321() {
+        let md5 = |data: &[u8]| {
+            let mut hash = Md5::new();
+            hash.update(data);
+            hash.finalize().to_vec()
+        };
+
+        for (data, digest) in [
+            (&b""[..], "D41D8CD98F00B204E9800998ECF8427E"),
+            (b"abc", "900150983CD24FB0D6963F7D28E17F72"),
+            (b"message digest", "F96B697D7CB7938D525A2F31AAF161D0"),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57EDF4A22BE3C955AC49DA2E2107B67A",
+            ),
+        ]
+        .iter()
+        {
+            assert_eq!(md5(data), decode_hex(digest).unwrap());
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::aead::Aead;
+use crate::block_cipher::{BlockDecrypt, BlockEncrypt};
+use crate::constant_time::ct_eq;
+use crate::error::{Error, Result};
+use crate::gf::{dbl, reduction_polynomial};
+use crate::mac::xor;
+
+// Blocks are 8 or 16 bytes, held in a 16-byte array of which the first BLOCK_SIZE bytes are used.
+type Block = [u8; 16];
+
+// One L_i for every possible number of trailing zeros of a block index.
+const L_COUNT: usize = usize::BITS as usize;
+
+/*
+OCB3 authenticated encryption, following the structure of RFC 7253.
+
+For 128-bit blocks such as Rc5<u64> this is OCB as specified in the RFC.
+RFC 7253 only defines 128-bit blocks, for 64-bit blocks such as Rc5<u32> the offsets are
+doubled with x^64 + x^4 + x^3 + x + 1 and the nonce is stretched as Ktop || (Ktop xor (Ktop << 25)).
+
+Nonces are 1 to BLOCK_SIZE - 1 bytes and must never repeat under one key.
+Tags are tag_len bytes, at most one block, and tag_len is bound into the ciphertext.
+*/
+pub struct Ocb<'a, C> {
+    cipher: &'a C,
+    l_star: Block,
+    l_dollar: Block,
+    l: [Block; L_COUNT],
+    tag_len: usize,
+}
+
+impl<'a, C> Ocb<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(cipher: &'a C, tag_len: usize) -> Result<Ocb<'a, C>> {
+        let n = C::BLOCK_SIZE;
+        if n != 8 && n != 16 {
+            return Err(Error::UnsupportedBlockSize(n));
+        }
+        if tag_len == 0 || tag_len > n {
+            return Err(Error::InvalidTagLength(tag_len));
+        }
+
+        let polynomial = reduction_polynomial(n)?;
+
+        // L_* = E(0^n), L_$ = double(L_*), L_0 = double(L_$), L_i = double(L_{i-1})
+        let mut l_star = [0; 16];
+        cipher.encrypt_block(&mut l_star[..n]);
+        let mut l_dollar = l_star;
+        dbl(&mut l_dollar[..n], polynomial);
+
+        let mut l = [[0; 16]; L_COUNT];
+        let mut previous = l_dollar;
+        for l_i in l.iter_mut() {
+            dbl(&mut previous[..n], polynomial);
+            *l_i = previous;
+        }
+
+        Ok(Self {
+            cipher,
+            l_star,
+            l_dollar,
+            l,
+            tag_len,
+        })
+    }
+
+    // L_{ntz(i)} for the 1-based block index i.
+    fn l_for(&self, i: usize) -> &[u8] {
+        &self.l[i.trailing_zeros() as usize][..C::BLOCK_SIZE]
+    }
+
+    // Offset_0, derived from the nonce through Ktop and Stretch.
+    fn initial_offset(&self, nonce: &[u8]) -> Result<Block> {
+        let n = C::BLOCK_SIZE;
+        if nonce.is_empty() || nonce.len() >= n {
+            return Err(Error::InvalidNonceLength(nonce.len()));
+        }
+
+        // Nonce = num2str(TAGLEN mod BLOCKLEN, 7) || zeros || 1 || N
+        let mut block = [0; 16];
+        block[n - nonce.len()..n].copy_from_slice(nonce);
+        block[n - nonce.len() - 1] |= 1;
+        block[0] |= (((self.tag_len * 8) % (n * 8)) as u8) << 1;
+
+        let bottom = (block[n - 1] & 0x3F) as usize;
+        block[n - 1] &= 0xC0;
+        self.cipher.encrypt_block(&mut block[..n]);
+
+        // Stretch = Ktop || the 64 bits following it.
+        let mut stretch = [0; 24];
+        stretch[..n].copy_from_slice(&block[..n]);
+        if n == 16 {
+            for i in 0..8 {
+                stretch[16 + i] = block[i] ^ block[i + 1];
+            }
+        } else {
+            let mut ktop = [0; 8];
+            ktop.copy_from_slice(&block[..8]);
+            let ktop = u64::from_be_bytes(ktop);
+            stretch[8..16].copy_from_slice(&(ktop ^ (ktop << 25)).to_be_bytes());
+        }
+
+        // Offset_0 = Stretch[1 + bottom..BLOCKLEN + bottom]
+        let (skip, shift) = (bottom / 8, bottom % 8);
+        let mut offset = [0; 16];
+        for (i, byte) in offset[..n].iter_mut().enumerate() {
+            *byte = stretch[skip + i] << shift;
+            if shift > 0 {
+                *byte |= stretch[skip + i + 1] >> (8 - shift);
+            }
+        }
+        Ok(offset)
+    }
+
+    // HASH(K, A), the sum over the associated data.
+    fn hash(&self, associated_data: &[u8]) -> Block {
+        let n = C::BLOCK_SIZE;
+        let mut offset = [0; 16];
+        let mut sum = [0; 16];
+
+        let mut blocks = associated_data.chunks_exact(n);
+        for (i, chunk) in (&mut blocks).enumerate() {
+            xor(&mut offset[..n], self.l_for(i + 1));
+            let mut block = offset;
+            xor(&mut block[..n], chunk);
+            self.cipher.encrypt_block(&mut block[..n]);
+            xor(&mut sum[..n], &block[..n]);
+        }
+
+        let rest = blocks.remainder();
+        if !rest.is_empty() {
+            xor(&mut offset[..n], &self.l_star[..n]);
+            let mut block = padded(rest);
+            xor(&mut block[..n], &offset[..n]);
+            self.cipher.encrypt_block(&mut block[..n]);
+            xor(&mut sum[..n], &block[..n]);
+        }
+        sum
+    }
+
+    fn tag(&self, final_block: &mut Block, associated_data: &[u8]) -> Block {
+        let n = C::BLOCK_SIZE;
+        self.cipher.encrypt_block(&mut final_block[..n]);
+        xor(&mut final_block[..n], &self.hash(associated_data)[..n]);
+        *final_block
+    }
+}
+
+impl<'a, C> Ocb<'a, C>
+where
+    C: BlockEncrypt + BlockDecrypt,
+{
+    /*
+    The single pass over the data, encrypting or decrypting buffer in place.
+    Returns Checksum xor Offset_m xor L_$, the block that is encrypted to form the tag.
+    */
+    fn crypt(&self, nonce: &[u8], buffer: &mut [u8], decrypt: bool) -> Result<Block> {
+        let n = C::BLOCK_SIZE;
+        let mut offset = self.initial_offset(nonce)?;
+        let mut checksum = [0; 16];
+
+        let mut blocks = buffer.chunks_exact_mut(n);
+        for (i, block) in (&mut blocks).enumerate() {
+            xor(&mut offset[..n], self.l_for(i + 1));
+            if !decrypt {
+                xor(&mut checksum[..n], block);
+            }
+
+            xor(block, &offset[..n]);
+            if decrypt {
+                self.cipher.decrypt_block(block);
+            } else {
+                self.cipher.encrypt_block(block);
+            }
+            xor(block, &offset[..n]);
+
+            if decrypt {
+                xor(&mut checksum[..n], block);
+            }
+        }
+
+        let rest = blocks.into_remainder();
+        if !rest.is_empty() {
+            xor(&mut offset[..n], &self.l_star[..n]);
+            let mut pad = offset;
+            self.cipher.encrypt_block(&mut pad[..n]);
+
+            if !decrypt {
+                xor(&mut checksum[..n], &padded(rest)[..n]);
+            }
+            xor(rest, &pad[..rest.len()]);
+            if decrypt {
+                xor(&mut checksum[..n], &padded(rest)[..n]);
+            }
+        }
+
+        xor(&mut checksum[..n], &offset[..n]);
+        xor(&mut checksum[..n], &self.l_dollar[..n]);
+        Ok(checksum)
+    }
+}
+
+// A partial block followed by a single 1 bit and zeros.
+fn padded(data: &[u8]) -> Block {
+    let mut block = [0; 16];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    block
+}
+
+impl<'a, C> Aead for Ocb<'a, C>
+where
+    C: BlockEncrypt + BlockDecrypt,
+{
+    fn tag_len(&self) -> usize {
+        self.tag_len
+    }
+
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let mut final_block = self.crypt(nonce, buffer, false)?;
+        let full_tag = self.tag(&mut final_block, associated_data);
+        tag.copy_from_slice(&full_tag[..self.tag_len]);
+        Ok(())
+    }
+
+    /*
+    The checksum is over the plaintext, so OCB has to decrypt before it can check the tag.
+    On failure the buffer is encrypted again under the same offsets, restoring the ciphertext.
+    */
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if tag.len() != self.tag_len {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let mut final_block = self.crypt(nonce, buffer, true)?;
+        let expected = self.tag(&mut final_block, associated_data);
+        if !ct_eq(&expected[..self.tag_len], tag) {
+            self.crypt(nonce, buffer, false)?;
+            return Err(Error::AuthenticationFailed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::testing::{bytes, rc5, Aes};
+
+    // (nonce length, associated data length, message length, ciphertext, tag),
+    // generated by `python3 tests/vectors/rc5ref.py ocb`.
+    type Vector = (usize, usize, usize, &'static str, &'static str);
+
+    fn check<C: BlockEncrypt + BlockDecrypt>(cipher: &C, vectors: &[Vector]) {
+        let ocb = Ocb::new(cipher, C::BLOCK_SIZE).unwrap();
+
+        for &(nonce_len, ad_len, len, ct, tag) in vectors {
+            let nonce = bytes(100, nonce_len);
+            let ad = bytes(200, ad_len);
+            let pt = bytes(0, len);
+
+            let sealed = ocb.encrypt(&nonce, &ad, &pt).unwrap();
+            let mut expected = decode_hex(ct).unwrap();
+            expected.extend(decode_hex(tag).unwrap());
+            assert_eq!(sealed, expected);
+
+            assert_eq!(ocb.decrypt(&nonce, &ad, &sealed).unwrap(), pt);
+        }
+    }
+
+    // The first five vectors of RFC 7253 appendix A, and the one with a 96-bit tag.
+    #[test]
+    fn aes_rfc_7253() {
+        let aes = Aes::new(&bytes(0, 16));
+        let ocb = Ocb::new(&aes, 16).unwrap();
+        for (i, ad_len, len, sealed) in [
+            (0, 0, 0, "785407BFFFC8AD9EDCC5520AC9111EE6"),
+            (1, 8, 8, "6820B3657B6F615A5725BDA0D3B4EB3A257C9AF1F8F03009"),
+            (2, 8, 0, "81017F8203F081277152FADE694A0A00"),
+            (3, 0, 8, "45DD69F8F5AAE72414054CD1F35D82760B2CD00D2F99BFA9"),
+            (
+                4,
+                16,
+                16,
+                "571D535B60B277188BE5147170A9A22C3AD7A4FF3835B8C5701C1CCEC8FC3358",
+            ),
+        ] {
+            let mut nonce = decode_hex("BBAA99887766554433221100").unwrap();
+            nonce[11] = i;
+            let sealed = decode_hex(sealed).unwrap();
+            assert_eq!(
+                ocb.encrypt(&nonce, &bytes(0, ad_len), &bytes(0, len))
+                    .unwrap(),
+                sealed
+            );
+            assert_eq!(
+                ocb.decrypt(&nonce, &bytes(0, ad_len), &sealed).unwrap(),
+                bytes(0, len)
+            );
+        }
+
+        let key: Vec<u8> = bytes(0, 16).into_iter().rev().collect();
+        let aes = Aes::new(&key);
+        let ocb = Ocb::new(&aes, 12).unwrap();
+        let nonce = decode_hex("BBAA9988776655443322110D").unwrap();
+        assert_eq!(
+            ocb.encrypt(&nonce, &bytes(0, 40), &bytes(0, 40)).unwrap(),
+            decode_hex(
+                "1792A4E31E0755FB03E31B22116E6C2DDF9EFD6E33D536F1A0124B0A55BAE884\
+                 ED93481529C76B6AD0C515F4D1CDD4FDAC4F02AA"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn rc5_32() {
+        check(
+            &rc5::<u32>(12, 16),
+            &[
+                (1, 0, 0, "", "212AE358CCB11E31"),
+                (
+                    7,
+                    5,
+                    21,
+                    "336A163E55C0C9958EBD83FF81B893E790AE7F2D9A",
+                    "6BB0722CDE168AFB",
+                ),
+                (
+                    3,
+                    0,
+                    16,
+                    "8292FE2B5FCBADB451A4B4DC45F1630B",
+                    "637669D1D4806FB2",
+                ),
+                (7, 8, 8, "336A163E55C0C995", "5AF62AC7225F7C18"),
+                (
+                    5,
+                    25,
+                    27,
+                    "C35319044E662B4413A247A43087C135EC36A437DEA597261A10BB",
+                    "62E5B3E7CC7A8B38",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        check(
+            &rc5::<u64>(24, 24),
+            &[
+                (1, 0, 0, "", "7D0F1B151CA2DCFC407EED995A97C2B2"),
+                (
+                    12,
+                    5,
+                    21,
+                    "418F28D9843AC40A236387568CD353B4FE2913B692",
+                    "4A792451CFB5DA2B403D99C94D2DC76C",
+                ),
+                (
+                    3,
+                    0,
+                    32,
+                    "3618D722DE470F9BF1C8C5EE411199AE75DAF3C44EEB4B939A533EA13C9FBC6A",
+                    "0BB9C6DA03B5FB21A735981A86F51F31",
+                ),
+                (
+                    15,
+                    16,
+                    16,
+                    "C9DC7A4E099715E2356F33481A4FA7D0",
+                    "992FECE7E91CD6A9E8319A3312064C37",
+                ),
+                (
+                    5,
+                    49,
+                    51,
+                    "0FD835E05D3379026ACA018FB6E7C45781E348C86CFAEE5FBDA7506F534D322C9773B201D8C44A99150CBD650513677444DC27",
+                    "038842DF8F9C26A10408F2B69C980DC4",
+                ),
+            ],
+        );
+    }
+
+    // The tag length goes into the nonce block, so a short tag is not a prefix of the full one.
+    // The vectors are from the reference script too.
+    #[test]
+    fn short_tags() {
+        let cipher = rc5::<u32>(12, 16);
+        let ocb = Ocb::new(&cipher, 6).unwrap();
+        let sealed = ocb.encrypt(&bytes(100, 4), b"header", b"message").unwrap();
+        assert_eq!(sealed, decode_hex("3774DEF97F2987BCACF237CCB4").unwrap());
+
+        let cipher = rc5::<u64>(24, 24);
+        let ocb = Ocb::new(&cipher, 6).unwrap();
+        let sealed = ocb.encrypt(&bytes(100, 4), b"header", b"message").unwrap();
+        assert_eq!(sealed, decode_hex("CFF46A59A27DDE43589A5AF75C").unwrap());
+        assert_eq!(
+            ocb.decrypt(&bytes(100, 4), b"header", &sealed).unwrap(),
+            b"message"
+        );
+    }
+
+    #[test]
+    fn long_messages() {
+        let cipher = rc5::<u32>(12, 16);
+        let ocb = Ocb::new(&cipher, 8).unwrap();
+        let message: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+
+        let sealed = ocb.encrypt(b"nonce", &message, &message).unwrap();
+        assert_eq!(ocb.decrypt(b"nonce", &message, &sealed).unwrap(), message);
+    }
+
+    fn tamper<C: BlockEncrypt + BlockDecrypt>(cipher: &C) {
+        let ocb = Ocb::new(cipher, C::BLOCK_SIZE).unwrap();
+        let sealed = ocb.encrypt(b"nonce", b"header", b"attack at dawn").unwrap();
+
+        for i in 0..sealed.len() {
+            let mut forged = sealed.clone();
+            forged[i] ^= 0x01;
+            assert!(matches!(
+                ocb.decrypt(b"nonce", b"header", &forged),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+        assert!(matches!(
+            ocb.decrypt(b"nonce", b"Header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            ocb.decrypt(b"Nonce", b"header", &sealed),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            ocb.decrypt(b"nonce", b"header", &sealed[1..]),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn tampered_rc5_32() {
+        tamper(&rc5::<u32>(12, 16));
+    }
+
+    #[test]
+    fn tampered_rc5_64() {
+        tamper(&rc5::<u64>(24, 24));
+    }
+
+    #[test]
+    fn no_plaintext_on_failure() {
+        let cipher = rc5::<u64>(24, 24);
+        let ocb = Ocb::new(&cipher, 16).unwrap();
+
+        let mut buffer = b"a secret message spanning blocks".to_vec();
+        let mut tag = [0; 16];
+        ocb.encrypt_in_place(b"nonce", b"", &mut buffer, &mut tag)
+            .unwrap();
+        let ciphertext = buffer.clone();
+
+        tag[0] ^= 1;
+        assert!(matches!(
+            ocb.decrypt_in_place(b"nonce", b"", &mut buffer, &tag),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert_eq!(buffer, ciphertext);
+    }
+
+    #[test]
+    fn parameters() {
+        let cipher = rc5::<u32>(12, 16);
+        assert!(matches!(
+            Ocb::new(&cipher, 0),
+            Err(Error::InvalidTagLength(0))
+        ));
+        assert!(matches!(
+            Ocb::new(&cipher, 9),
+            Err(Error::InvalidTagLength(9))
+        ));
+
+        let ocb = Ocb::new(&cipher, 8).unwrap();
+        assert!(matches!(
+            ocb.encrypt(b"", b"", b""),
+            Err(Error::InvalidNonceLength(0))
+        ));
+        assert!(matches!(
+            ocb.encrypt(&[0; 8], b"", b""),
+            Err(Error::InvalidNonceLength(8))
+        ));
+
+        let cipher = rc5::<u16>(12, 16);
+        assert!(matches!(
+            Ocb::new(&cipher, 4),
+            Err(Error::UnsupportedBlockSize(4))
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::cbc::Cbc;
+use crate::constant_time::wipe;
+use crate::error::{Error, Result};
+use crate::md5::{Md5, MD5_OUTPUT_LEN};
+use crate::pbes2::pbkdf2_hmac_sha256;
+use crate::rc5::Rc5;
+use crate::sha256::{Sha256, SHA256_OUTPUT_LEN};
+
+pub const OPENSSL_MAGIC: &[u8; 8] = b"Salted__";
+pub const OPENSSL_SALT_LEN: usize = 8;
+
+// The iteration count `openssl enc -pbkdf2` uses when -iter isn't given.
+pub const OPENSSL_PBKDF2_ITERATIONS: u32 = 10_000;
+
+const HEADER_LEN: usize = OPENSSL_MAGIC.len() + OPENSSL_SALT_LEN;
+
+// OpenSSL's rc5-cbc is RC5-32/12 with a 128-bit key.
+const ROUNDS: u8 = 12;
+const KEY_LEN: usize = 16;
+const BLOCK_LEN: usize = 8;
+
+// How the key and IV are derived from the password and salt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpenSslKdf {
+    // EVP_BytesToKey with MD5, the default before OpenSSL 1.1.0 and `-md md5` since.
+    BytesToKeyMd5,
+    // EVP_BytesToKey with SHA-256, the default since OpenSSL 1.1.0.
+    BytesToKeySha256,
+    // PBKDF2 with HMAC-SHA-256, from `-pbkdf2` and `-iter`.
+    Pbkdf2 { iterations: u32 },
+}
+
+/*
+The file format of `openssl enc -rc5-cbc`: "Salted__", an 8 byte salt, then the message
+encrypted with RC5-32/12 in CBC mode with PKCS#7 padding, under a key and IV derived
+from the password and salt. Files written with -nosalt have no header and aren't supported.
+
+The format has no integrity check and the default key derivation is a single hash of
+the password, so it is only for exchanging files with tools that speak it.
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct OpenSslRc5 {
+    kdf: OpenSslKdf,
+}
+
+impl OpenSslRc5 {
+    pub fn new(kdf: OpenSslKdf) -> OpenSslRc5 {
+        Self { kdf }
+    }
+
+    // The file length for a message of msg_len bytes.
+    pub fn encrypted_len(msg_len: usize) -> usize {
+        HEADER_LEN + (msg_len / BLOCK_LEN + 1) * BLOCK_LEN
+    }
+
+    /*
+    Encrypts the first msg_len bytes of buffer into the file format, returning its length.
+    The buffer must have room for encrypted_len(msg_len) bytes.
+    */
+    pub fn encrypt_in_place(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+        buffer: &mut [u8],
+        msg_len: usize,
+    ) -> Result<usize> {
+        let len = Self::encrypted_len(msg_len);
+        if buffer.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+        let (cipher, iv) = self.cipher(password, salt)?;
+
+        buffer.copy_within(..msg_len, HEADER_LEN);
+        buffer[..OPENSSL_MAGIC.len()].copy_from_slice(OPENSSL_MAGIC);
+        buffer[OPENSSL_MAGIC.len()..HEADER_LEN].copy_from_slice(salt);
+
+        let ciphertext_len =
+            Cbc::new(&cipher).encrypt_padded_in_place(&iv, &mut buffer[HEADER_LEN..], msg_len)?;
+        Ok(HEADER_LEN + ciphertext_len)
+    }
+
+    // Decrypts a whole file in place and returns the length of the plaintext left at its start.
+    pub fn decrypt_in_place(&self, password: &[u8], buffer: &mut [u8]) -> Result<usize> {
+        if buffer.len() < OPENSSL_MAGIC.len() || &buffer[..OPENSSL_MAGIC.len()] != OPENSSL_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        if buffer.len() < HEADER_LEN {
+            return Err(Error::InvalidLength {
+                expected: HEADER_LEN,
+                found: buffer.len(),
+            });
+        }
+
+        let mut salt = [0; OPENSSL_SALT_LEN];
+        salt.copy_from_slice(&buffer[OPENSSL_MAGIC.len()..HEADER_LEN]);
+        let (cipher, iv) = self.cipher(password, &salt)?;
+
+        let len = Cbc::new(&cipher).decrypt_padded_in_place(&iv, &mut buffer[HEADER_LEN..])?;
+        buffer.copy_within(HEADER_LEN..HEADER_LEN + len, 0);
+        Ok(len)
+    }
+
+    // Encrypts with a fresh salt from the OS, as openssl does.
+    #[cfg(feature = "std")]
+    pub fn encrypt(&self, password: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let salt = crate::drbg::os_entropy(OPENSSL_SALT_LEN)?;
+        self.encrypt_with_salt(password, &salt, plaintext)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encrypt_with_salt(
+        &self,
+        password: &[u8],
+        salt: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut buffer = plaintext.to_vec();
+        buffer.resize(Self::encrypted_len(plaintext.len()), 0);
+        self.encrypt_in_place(password, salt, &mut buffer, plaintext.len())?;
+        Ok(buffer)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decrypt(&self, password: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = data.to_vec();
+        let len = self.decrypt_in_place(password, &mut buffer)?;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    fn cipher(&self, password: &[u8], salt: &[u8]) -> Result<(Rc5<u32>, [u8; BLOCK_LEN])> {
+        if salt.len() != OPENSSL_SALT_LEN {
+            return Err(Error::InvalidLength {
+                expected: OPENSSL_SALT_LEN,
+                found: salt.len(),
+            });
+        }
+
+        let mut derived = [0; KEY_LEN + BLOCK_LEN];
+        derive_key_and_iv(self.kdf, password, salt, &mut derived)?;
+
+        let mut cipher = Rc5::new(ROUNDS, KEY_LEN)?;
+        cipher.setup(&derived[..KEY_LEN]);
+        let mut iv = [0; BLOCK_LEN];
+        iv.copy_from_slice(&derived[KEY_LEN..]);
+
+        wipe(&mut derived);
+        Ok((cipher, iv))
+    }
+}
+
+// Fills out with the key followed by the IV.
+fn derive_key_and_iv(kdf: OpenSslKdf, password: &[u8], salt: &[u8], out: &mut [u8]) -> Result<()> {
+    match kdf {
+        OpenSslKdf::BytesToKeyMd5 => bytes_to_key(false, password, salt, out),
+        OpenSslKdf::BytesToKeySha256 => bytes_to_key(true, password, salt, out),
+        OpenSslKdf::Pbkdf2 { iterations } => {
+            pbkdf2_hmac_sha256(password, salt, iterations, out)?;
+        }
+    }
+    Ok(())
+}
+
+// EVP_BytesToKey with a count of one: D_i = H(D_(i-1) || password || salt), concatenated.
+fn bytes_to_key(sha256: bool, password: &[u8], salt: &[u8], out: &mut [u8]) {
+    let mut previous = [0; SHA256_OUTPUT_LEN];
+    let mut previous_len = 0;
+    let digest_len = if sha256 {
+        SHA256_OUTPUT_LEN
+    } else {
+        MD5_OUTPUT_LEN
+    };
+
+    for chunk in out.chunks_mut(digest_len) {
+        if sha256 {
+            let mut hash = Sha256::new();
+            hash.update(&previous[..previous_len]);
+            hash.update(password);
+            hash.update(salt);
+            previous = hash.finalize();
+            previous_len = SHA256_OUTPUT_LEN;
+        } else {
+            let mut hash = Md5::new();
+            hash.update(&previous[..previous_len]);
+            hash.update(password);
+            hash.update(salt);
+            previous[..MD5_OUTPUT_LEN].copy_from_slice(&hash.finalize());
+            previous_len = MD5_OUTPUT_LEN;
+        }
+        chunk.copy_from_slice(&previous[..chunk.len()]);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    const PASSWORD: &[u8] = b"partner-secret";
+    const SALT: [u8; 8] = [0x5E, 0xED, 0x5A, 0x17, 0xF0, 0x0D, 0xCA, 0xFE];
+    const PLAINTEXT: &[u8] = include_bytes!("../tests/fixtures/openssl/plaintext.txt");
+
+    const KDFS: [(OpenSslKdf, &[u8]); 3] = [
+        (
+            OpenSslKdf::BytesToKeyMd5,
+            include_bytes!("../tests/fixtures/openssl/rc5-cbc-md5.enc"),
+        ),
+        (
+            OpenSslKdf::BytesToKeySha256,
+            include_bytes!("../tests/fixtures/openssl/rc5-cbc-sha256.enc"),
+        ),
+        (
+            OpenSslKdf::Pbkdf2 {
+                iterations: OPENSSL_PBKDF2_ITERATIONS,
+            },
+            include_bytes!("../tests/fixtures/openssl/rc5-cbc-pbkdf2.enc"),
+        ),
+    ];
+
+    /*
+    The keys and IVs printed by `openssl enc -aes-128-cbc -P -S 5EED5A17F00DCAFE
+    -pass pass:partner-secret` with -md md5, -md sha256 and -pbkdf2 -iter 10000. AES-128
+    takes a key of the same length, and rc5-cbc's IV is the first 8 bytes of the longer
+    AES IV. OpenSSL 1.1.1w built with RC5 prints the same values for -rc5-cbc.
+    */
+    #[test]
+    fn key_derivation_matches_openssl() {
+        let expected = [
+            ("0AFC4F1FD2D9E6736B5786AF2F67EB41", "FDFD5DD317BE79D0"),
+            ("25223220B5F9B1B693B6AE72A7C53CD6", "DCEF578445DED116"),
+            ("64CACA3F8855BF259F6355CF6F32A0B4", "BA3ADCE71B9D4D2E"),
+        ];
+
+        for ((kdf, _), (key, iv)) in KDFS.iter().zip(expected.iter()) {
+            let mut derived = [0; KEY_LEN + BLOCK_LEN];
+            derive_key_and_iv(*kdf, PASSWORD, &SALT, &mut derived).unwrap();
+            assert_eq!(derived[..KEY_LEN], decode_hex(key).unwrap()[..]);
+            assert_eq!(derived[KEY_LEN..], decode_hex(iv).unwrap()[..]);
+        }
+    }
+
+    // The fixture files are described in tests/fixtures/openssl/README.md.
+    #[test]
+    fn fixtures() {
+        for (kdf, file) in KDFS.iter() {
+            let format = OpenSslRc5::new(*kdf);
+            assert_eq!(format.decrypt(PASSWORD, file).unwrap(), PLAINTEXT);
+            assert_eq!(
+                format
+                    .encrypt_with_salt(PASSWORD, &SALT, PLAINTEXT)
+                    .unwrap(),
+                *file
+            );
+        }
+    }
+
+    #[test]
+    fn random_salt() {
+        let format = OpenSslRc5::new(OpenSslKdf::BytesToKeySha256);
+        let first = format.encrypt(PASSWORD, b"hello").unwrap();
+        let second = format.encrypt(PASSWORD, b"hello").unwrap();
+
+        assert_eq!(&first[..8], OPENSSL_MAGIC);
+        assert_eq!(first.len(), OpenSslRc5::encrypted_len(5));
+        assert_ne!(first, second);
+        assert_eq!(format.decrypt(PASSWORD, &first).unwrap(), b"hello");
+        assert_eq!(format.decrypt(PASSWORD, &second).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn invalid_files() {
+        let format = OpenSslRc5::new(OpenSslKdf::BytesToKeyMd5);
+        let file = KDFS[0].1;
+
+        assert!(matches!(
+            format.decrypt(PASSWORD, &file[8..]),
+            Err(Error::InvalidMagic)
+        ));
+        assert!(matches!(
+            format.decrypt(PASSWORD, &file[..12]),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 12
+            })
+        ));
+        assert!(matches!(
+            format.decrypt(PASSWORD, &file[..file.len() - 1]),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            format.decrypt(b"wrong password", file),
+            Err(Error::InvalidPadding)
+        ));
+        // The right password with the wrong key derivation fails the same way.
+        assert!(matches!(
+            OpenSslRc5::new(OpenSslKdf::BytesToKeySha256).decrypt(PASSWORD, file),
+            Err(Error::InvalidPadding)
+        ));
+        assert!(matches!(
+            format.encrypt_with_salt(PASSWORD, &SALT[..4], b""),
+            Err(Error::InvalidLength {
+                expected: 8,
+                found: 4
+            })
+        ));
+    }
+}
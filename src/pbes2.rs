@@ -0,0 +1,355 @@
+use crate::block_cipher::BlockCipher;
+use crate::cbc::Cbc;
+use crate::constant_time::wipe;
+use crate::error::{Error, Result};
+use crate::key_size::MAX_KEY_SIZE;
+use crate::rc5::Rc5;
+use crate::sha256::{HmacSha256, SHA256_OUTPUT_LEN};
+use crate::uint::UInt;
+
+// The only version of RC5-CBC-Parameters, v1-0 in RFC 8018.
+pub const RC5_CBC_VERSION: u8 = 16;
+
+// The largest IV, for 128-bit blocks.
+const MAX_IV_LEN: usize = 16;
+
+// PBKDF2 from RFC 8018 with HMAC-SHA-256, filling out with derived key bytes.
+pub fn pbkdf2_hmac_sha256(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    out: &mut [u8],
+) -> Result<()> {
+    if iterations == 0 {
+        return Err(Error::InvalidIterationCount(iterations));
+    }
+
+    let prf = HmacSha256::new(password);
+    for (i, chunk) in out.chunks_mut(SHA256_OUTPUT_LEN).enumerate() {
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(i as u32 + 1).to_be_bytes());
+        let mut u = mac.finalize();
+        let mut t = u;
+
+        for _ in 1..iterations {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize();
+            for (t, u) in t.iter_mut().zip(u.iter()) {
+                *t ^= u;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+    Ok(())
+}
+
+/*
+RC5-CBC-Parameters from RFC 8018 appendix B.2.4: the rounds and block size of the
+cipher, and the CBC IV, which is one block. The version is always v1-0.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rc5CbcParameters {
+    rounds: u8,
+    block_size: usize,
+    iv: [u8; MAX_IV_LEN],
+}
+
+impl Rc5CbcParameters {
+    // rounds must be 8 to 127, and block_size_bits 64 or 128.
+    pub fn new(rounds: u8, block_size_bits: u16, iv: &[u8]) -> Result<Rc5CbcParameters> {
+        if !(8..=127).contains(&rounds) {
+            return Err(Error::InvalidRounds(rounds));
+        }
+        if block_size_bits != 64 && block_size_bits != 128 {
+            return Err(Error::UnsupportedBlockSize(block_size_bits as usize / 8));
+        }
+        let block_size = block_size_bits as usize / 8;
+        if iv.len() != block_size {
+            return Err(Error::InvalidLength {
+                expected: block_size,
+                found: iv.len(),
+            });
+        }
+
+        let mut params = Self {
+            rounds,
+            block_size,
+            iv: [0; MAX_IV_LEN],
+        };
+        params.iv[..block_size].copy_from_slice(iv);
+        Ok(params)
+    }
+
+    pub fn version(&self) -> u8 {
+        RC5_CBC_VERSION
+    }
+
+    pub fn rounds(&self) -> u8 {
+        self.rounds
+    }
+
+    pub fn block_size_bits(&self) -> u16 {
+        8 * self.block_size as u16
+    }
+
+    // The RC5 word size w, half the block size.
+    pub fn word_size_bits(&self) -> u16 {
+        4 * self.block_size as u16
+    }
+
+    pub fn iv(&self) -> &[u8] {
+        &self.iv[..self.block_size]
+    }
+}
+
+/*
+Password-based encryption with PBES2 from RFC 8018, using PBKDF2 with HMAC-SHA-256 to
+derive the key and rc5-CBC-Pad to encrypt. Each message should get a fresh random salt
+and IV, which are stored alongside the ciphertext.
+
+Nothing is authenticated: a wrong password is normally reported as InvalidPadding, but
+about one time in 256 it decrypts to garbage instead.
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct Pbes2<'a> {
+    salt: &'a [u8],
+    iterations: u32,
+    key_len: usize,
+    params: Rc5CbcParameters,
+}
+
+impl<'a> Pbes2<'a> {
+    // key_len is the RC5 key length in bytes, at most 255.
+    pub fn new(
+        salt: &'a [u8],
+        iterations: u32,
+        key_len: usize,
+        params: Rc5CbcParameters,
+    ) -> Result<Pbes2<'a>> {
+        if iterations == 0 {
+            return Err(Error::InvalidIterationCount(iterations));
+        }
+        if key_len > MAX_KEY_SIZE {
+            return Err(Error::KeySizeError("Key size is too large"));
+        }
+
+        Ok(Self {
+            salt,
+            iterations,
+            key_len,
+            params,
+        })
+    }
+
+    pub fn salt(&self) -> &'a [u8] {
+        self.salt
+    }
+
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    pub fn key_len(&self) -> usize {
+        self.key_len
+    }
+
+    pub fn parameters(&self) -> &Rc5CbcParameters {
+        &self.params
+    }
+
+    // The ciphertext length for a message of msg_len bytes.
+    pub fn padded_len(&self, msg_len: usize) -> usize {
+        let n = self.params.block_size;
+        (msg_len / n + 1) * n
+    }
+
+    /*
+    Encrypts the first msg_len bytes of buffer and returns the ciphertext length.
+    The buffer must have room for padded_len(msg_len) bytes.
+    */
+    pub fn encrypt_in_place(
+        &self,
+        password: &[u8],
+        buffer: &mut [u8],
+        msg_len: usize,
+    ) -> Result<usize> {
+        let iv = self.params.iv();
+        if self.params.block_size == Rc5::<u32>::BLOCK_SIZE {
+            let cipher = self.cipher::<u32>(password)?;
+            Cbc::new(&cipher).encrypt_padded_in_place(iv, buffer, msg_len)
+        } else {
+            let cipher = self.cipher::<u64>(password)?;
+            Cbc::new(&cipher).encrypt_padded_in_place(iv, buffer, msg_len)
+        }
+    }
+
+    // Decrypts buffer in place and returns the length of the plaintext left at its start.
+    pub fn decrypt_in_place(&self, password: &[u8], buffer: &mut [u8]) -> Result<usize> {
+        let iv = self.params.iv();
+        if self.params.block_size == Rc5::<u32>::BLOCK_SIZE {
+            let cipher = self.cipher::<u32>(password)?;
+            Cbc::new(&cipher).decrypt_padded_in_place(iv, buffer)
+        } else {
+            let cipher = self.cipher::<u64>(password)?;
+            Cbc::new(&cipher).decrypt_padded_in_place(iv, buffer)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encrypt(&self, password: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = plaintext.to_vec();
+        buffer.resize(self.padded_len(plaintext.len()), 0);
+        self.encrypt_in_place(password, &mut buffer, plaintext.len())?;
+        Ok(buffer)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decrypt(&self, password: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let mut buffer = ciphertext.to_vec();
+        let len = self.decrypt_in_place(password, &mut buffer)?;
+        buffer.truncate(len);
+        Ok(buffer)
+    }
+
+    fn cipher<T: UInt>(&self, password: &[u8]) -> Result<Rc5<T>> {
+        let mut key = [0; MAX_KEY_SIZE];
+        pbkdf2_hmac_sha256(
+            password,
+            self.salt,
+            self.iterations,
+            &mut key[..self.key_len],
+        )?;
+
+        let mut cipher = Rc5::new(self.params.rounds, self.key_len)?;
+        cipher.setup(&key[..self.key_len]);
+
+        wipe(&mut key);
+        Ok(cipher)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32, len: usize) -> Vec<u8> {
+        let mut out = vec![0; len];
+        pbkdf2_hmac_sha256(password, salt, iterations, &mut out).unwrap();
+        out
+    }
+
+    // The first vector is from RFC 7914 section 11, the others from Python's hashlib.
+    #[test]
+    fn pbkdf2_vectors() {
+        assert_eq!(
+            pbkdf2(b"passwd", b"salt", 1, 64),
+            decode_hex(
+                "55AC046E56E3089FEC1691C22544B605F94185216DDE0465E68B9D57C20DACBC\
+                 49CA9CCCF179B645991664B39D77EF317C71B845B1E30BD509112041D3A19783"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            pbkdf2(b"password", b"salt", 4096, 32),
+            decode_hex("C5E478D59288C841AA530DB6845C4C8D962893A001CE4E11A4963873AA98134A").unwrap()
+        );
+        assert_eq!(
+            pbkdf2(
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                100,
+                40
+            ),
+            decode_hex(
+                "642EA3CC424C6E709AF485B1E51692F0A7557AA922C0DDDC7561830ED30FD4E9205C287A1EFEBD76"
+            )
+            .unwrap()
+        );
+        assert!(matches!(
+            pbkdf2_hmac_sha256(b"", b"", 0, &mut [0; 16]),
+            Err(Error::InvalidIterationCount(0))
+        ));
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py pbes2`.
+    #[test]
+    fn known_answers() {
+        let salt = decode_hex("0123456789ABCDEF").unwrap();
+
+        let params =
+            Rc5CbcParameters::new(12, 64, &decode_hex("F0E1D2C3B4A59687").unwrap()).unwrap();
+        let pbes2 = Pbes2::new(&salt, 1000, 16, params).unwrap();
+        let ciphertext = pbes2.encrypt(b"correct horse", b"attack at dawn").unwrap();
+        assert_eq!(
+            ciphertext,
+            decode_hex("5765A60F6139E7AF993F20F6EF7233AA").unwrap()
+        );
+        assert_eq!(
+            pbes2.decrypt(b"correct horse", &ciphertext).unwrap(),
+            b"attack at dawn"
+        );
+
+        let iv = decode_hex("00112233445566778899AABBCCDDEEFF").unwrap();
+        let params = Rc5CbcParameters::new(16, 128, &iv).unwrap();
+        let pbes2 = Pbes2::new(&salt, 1000, 32, params).unwrap();
+        let ciphertext = pbes2.encrypt(b"correct horse", b"attack at dawn").unwrap();
+        assert_eq!(
+            ciphertext,
+            decode_hex("A8113DFFAE7029031C3BF9929859C558").unwrap()
+        );
+        assert_eq!(
+            pbes2.decrypt(b"correct horse", &ciphertext).unwrap(),
+            b"attack at dawn"
+        );
+    }
+
+    #[test]
+    fn wrong_password() {
+        let params = Rc5CbcParameters::new(12, 64, &[0; 8]).unwrap();
+        let pbes2 = Pbes2::new(b"salt", 10, 16, params).unwrap();
+        let ciphertext = pbes2.encrypt(b"right", b"some file contents").unwrap();
+        // This password happens to give a key that fails the padding check.
+        assert!(matches!(
+            pbes2.decrypt(b"wrong", &ciphertext),
+            Err(Error::InvalidPadding)
+        ));
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        assert!(matches!(
+            Rc5CbcParameters::new(7, 64, &[0; 8]),
+            Err(Error::InvalidRounds(7))
+        ));
+        assert!(matches!(
+            Rc5CbcParameters::new(128, 64, &[0; 8]),
+            Err(Error::InvalidRounds(128))
+        ));
+        assert!(matches!(
+            Rc5CbcParameters::new(12, 32, &[0; 4]),
+            Err(Error::UnsupportedBlockSize(4))
+        ));
+        assert!(matches!(
+            Rc5CbcParameters::new(12, 128, &[0; 8]),
+            Err(Error::InvalidLength {
+                expected: 16,
+                found: 8
+            })
+        ));
+
+        let params = Rc5CbcParameters::new(12, 64, &[0; 8]).unwrap();
+        assert_eq!(params.version(), RC5_CBC_VERSION);
+        assert_eq!(params.block_size_bits(), 64);
+        assert!(matches!(
+            Pbes2::new(b"salt", 0, 16, params),
+            Err(Error::InvalidIterationCount(0))
+        ));
+        assert!(matches!(
+            Pbes2::new(b"salt", 1, 256, params),
+            Err(Error::KeySizeError(_))
+        ));
+    }
+}
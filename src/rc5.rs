@@ -1,102 +1,148 @@
-use crate::error::Result;
-use crate::key_size::KeySize;
+use core::marker::PhantomData;
+
+use crate::block_cipher::MAX_BLOCK_SIZE;
+use crate::byte_order::{ByteOrder, LittleEndian};
+use crate::error::{Error, Result};
+use crate::key_size::{KeySize, MAX_KEY_SIZE};
+use crate::key_table::KeyTable;
 use crate::uint::UInt;
 
+// The largest number of rounds RC5 allows, and the default key table capacity.
+pub const MAX_ROUNDS: usize = u8::MAX as usize;
+
 /*
 RC5 implementation in Rust
 algorithm source:
     https://en.wikipedia.org/wiki/RC5
+
+R is the capacity of the key table in rounds. The default fits any round count,
+a smaller R keeps the cipher small when it lives on the stack of an embedded target.
+O is the byte order used to load the key and the block words, little-endian as in the spec.
 */
-pub struct Rc5<T> {
+pub struct Rc5<T, const R: usize = MAX_ROUNDS, O = LittleEndian> {
     // The size of the key in bytes.
     key_size: KeySize,
     // The number of rounds to use when encrypting data.
     rounds: u8,
     // The expanded key.
-    s: Vec<T>,
+    s: KeyTable<T, R>,
+    byte_order: PhantomData<O>,
 }
 
-impl<T> Default for Rc5<T> {
+impl<T, const R: usize, O> Default for Rc5<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
     fn default() -> Self {
         Self {
-            rounds: 12,
-            s: vec![],
+            rounds: R.min(12) as u8,
+            s: KeyTable::new(),
             key_size: KeySize::default(),
+            byte_order: PhantomData,
         }
     }
 }
 
-impl<T> Rc5<T>
+impl<T, const R: usize, O> Rc5<T, R, O>
 where
     T: UInt,
+    O: ByteOrder,
 {
-    pub fn new(rounds: u8, key_size: usize) -> Result<Rc5<T>> {
+    pub fn new(rounds: u8, key_size: usize) -> Result<Rc5<T, R, O>> {
+        if rounds as usize > R {
+            return Err(Error::InvalidRounds(rounds));
+        }
+
         Ok(Self {
             rounds,
             key_size: KeySize::new(key_size as u32)?,
-            s: vec![],
+            s: KeyTable::new(),
+            byte_order: PhantomData,
         })
     }
 
-    fn parse_bytes(&self, plaintext: Vec<u8>) -> (T, T) {
+    // The size of a block in bytes, two words.
+    pub fn block_size(&self) -> usize {
+        2 * T::range()
+    }
+
+    pub(crate) fn parse_bytes(&self, block: &[u8]) -> (T, T) {
         let range = T::range();
-        let mut slice_a: &[u8] = &plaintext[0..range];
-        let plaintext_a = T::from_bytes(&mut slice_a);
-        let mut slice_b: &[u8] = &plaintext[range..range * 2];
-        let plaintext_b = T::from_bytes(&mut slice_b);
-        (plaintext_a, plaintext_b)
+        let mut slice_a: &[u8] = &block[0..range];
+        let block_a = T::from_bytes::<O>(&mut slice_a);
+        let mut slice_b: &[u8] = &block[range..range * 2];
+        let block_b = T::from_bytes::<O>(&mut slice_b);
+        (block_a, block_b)
     }
 
-    pub fn encode(&self, plaintext: Vec<u8>, ciphertext: &mut Vec<u8>) {
-        let (plaintext_a, plaintext_b) = self.parse_bytes(plaintext);
+    pub(crate) fn write_bytes(&self, a: T, b: T, block: &mut [u8]) {
+        let (block_a, block_b) = block.split_at_mut(T::range());
+        a.write_bytes::<O>(block_a);
+        b.write_bytes::<O>(block_b);
+    }
+
+    // Encrypts a single block in place, block must be exactly block_size() bytes long.
+    pub fn encrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), self.block_size(), "invalid block size");
+
+        let (plaintext_a, plaintext_b) = self.parse_bytes(block);
 
         let mut a = self.s[0].wadd(plaintext_a);
         let mut b = self.s[1].wadd(plaintext_b);
 
-        #[cfg(test)]
-        println!("A: {:x}", a);
-        #[cfg(test)]
-        println!("B: {:x}", b);
-
-        for i in 1..(self.rounds + 1) as usize {
+        for i in 1..=self.rounds as usize {
             a = (a ^ b).rotl(b.into_u32()).wadd(self.s[2 * i]);
             b = (b ^ a).rotl(a.into_u32()).wadd(self.s[2 * i + 1]);
-
-            #[cfg(test)]
-            println!("A: {:x}", a);
-            #[cfg(test)]
-            println!("B: {:x}", b);
         }
 
-        ciphertext.extend(a.to_bytes());
-        ciphertext.extend(b.to_bytes());
+        self.write_bytes(a, b, block);
     }
 
-    pub fn decode(&self, ciphertext: Vec<u8>, plaintext: &mut Vec<u8>) {
-        let (ciphertext_a, ciphertext_b) = self.parse_bytes(ciphertext);
+    // Decrypts a single block in place, block must be exactly block_size() bytes long.
+    pub fn decrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), self.block_size(), "invalid block size");
+
+        let (ciphertext_a, ciphertext_b) = self.parse_bytes(block);
         let mut a = ciphertext_a;
         let mut b = ciphertext_b;
 
-        #[cfg(test)]
-        println!("A: {:x}", a);
-        #[cfg(test)]
-        println!("B: {:x}", b);
-
-        for i in (1..(self.rounds + 1) as usize).rev() {
+        for i in (1..=self.rounds as usize).rev() {
             b = ((b.wsub(self.s[2 * i + 1])).rotr(a.into_u32())) ^ a;
             a = ((a.wsub(self.s[2 * i])).rotr(b.into_u32())) ^ b;
-
-            #[cfg(test)]
-            println!("A: {:x}", a);
-            #[cfg(test)]
-            println!("B: {:x}", b);
         }
 
         a = a.wsub(self.s[0]);
         b = b.wsub(self.s[1]);
 
-        plaintext.extend(a.to_bytes());
-        plaintext.extend(b.to_bytes());
+        self.write_bytes(a, b, block);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encode(&self, plaintext: Vec<u8>, ciphertext: &mut Vec<u8>) {
+        let mut block = plaintext[..self.block_size()].to_vec();
+        self.encrypt_block(&mut block);
+        ciphertext.extend(block);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn decode(&self, ciphertext: Vec<u8>, plaintext: &mut Vec<u8>) {
+        let mut block = ciphertext[..self.block_size()].to_vec();
+        self.decrypt_block(&mut block);
+        plaintext.extend(block);
+    }
+
+    pub fn rounds(&self) -> u8 {
+        self.rounds
+    }
+
+    // The expanded key, only meaningful once setup has run.
+    pub(crate) fn key_table(&self) -> &KeyTable<T, R> {
+        &self.s
+    }
+
+    pub(crate) fn key_table_mut(&mut self) -> &mut KeyTable<T, R> {
+        &mut self.s
     }
 
     //The length of a word in bytes.
@@ -112,52 +158,36 @@ where
     // The length of the key in words (or 1, if b = 0).
     fn c(&self) -> usize {
         // length of key in words
-        (8_f32 * self.b() as f32 / T::w() as f32).ceil().max(1.) as usize
+        (8 * self.b()).div_ceil(T::w()).max(1)
     }
 
     // size of table S in blocks
     fn t(&self) -> usize {
-        (2 * (self.rounds + 1)) as usize
-    }
-
-    fn print_L(l: &Vec<T>) {
-        for i in 0..l.len() {
-            println!("L[{}] = {:x}", i, l[i]);
-        }
-    }
-
-    fn print_S(s: &Vec<T>) {
-        for i in 0..s.len() {
-            println!("S[{}] = {:x}", i, s[i]);
-        }
+        2 * (self.rounds as usize + 1)
     }
 
     // L is initially a c-length list of 0-valued w-length words
     // A temporary working array used during key scheduling. initialized to the key in words.
-    fn generate_L(&self, key: &Vec<u8>) -> Vec<T> {
-        let mut l: Vec<T> = vec![T::zero(); self.c()];
-        l[self.c() - 1] = T::zero();
-
-        for i in (0..self.b()).rev() {
-            let iu = i / self.u();
-
-            let r = l[iu].rotl(8);
-
-            let k = T::from_u8(key[i]);
-            let f = r + k;
-
-            l[iu] = f;
+    // Only the first c words are used, the key is at most MAX_KEY_SIZE bytes so c never exceeds it.
+    // Each word is loaded from u key bytes in the byte order O, the last word is padded with zeroes.
+    #[allow(non_snake_case)]
+    fn generate_L(&self, key: &[u8]) -> [T; MAX_KEY_SIZE] {
+        let mut l: [T; MAX_KEY_SIZE] = [T::zero(); MAX_KEY_SIZE];
+        let key = &key[..self.b()];
+
+        for (word, chunk) in l.iter_mut().zip(key.chunks(self.u())) {
+            let mut bytes = [0u8; MAX_BLOCK_SIZE / 2];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            *word = T::from_bytes::<O>(&mut &bytes[..self.u()]);
         }
 
-        #[cfg(test)]
-        Self::print_L(&l);
-
         l
     }
     //Initialize key-independent pseudorandom S array
     //S is initially a t=2(r+1) length list of undefined w-length words
-    fn generate_S(&self) -> Vec<T> {
-        let mut s: Vec<T> = vec![T::zero(); self.t()];
+    #[allow(non_snake_case)]
+    fn generate_S(&self) -> KeyTable<T, R> {
+        let mut s: KeyTable<T, R> = KeyTable::new();
 
         s[0] = T::pw();
         for i in 1..self.t() {
@@ -168,16 +198,13 @@ where
             }
         }
 
-        #[cfg(test)]
-        Self::print_S(&s);
-
         s
     }
 
     // setup the key, and generate the S and L tables for the cipher
-    pub fn setup(&mut self, key: Vec<u8>) {
-        let mut l: Vec<T> = self.generate_L(&key);
-        let mut s: Vec<T> = self.generate_S();
+    pub fn setup(&mut self, key: &[u8]) {
+        let mut l = self.generate_L(key);
+        let mut s = self.generate_S();
 
         let mut i = 0;
         let mut j = 0;
@@ -198,11 +225,6 @@ where
             l[j] = l[j].wadd(ab).rotl(ab.into_u32());
             b = l[j];
 
-            #[cfg(test)]
-            println!("S[{}] = {:x}", i, s[i]);
-            #[cfg(test)]
-            println!("L[{}] = {:x}", j, l[j]);
-
             i = (i + 1) % self.t();
             j = (j + 1) % self.c();
         }
@@ -215,6 +237,7 @@ The first magic constant, defined as  Odd((e-2)*2^w),
 where Odd is the nearest odd integer to the given input,
 e is the base of the natural logarithm, and w is defined above.
 */
+#[cfg(all(test, feature = "std"))]
 pub fn calculate_magic_constant_pw(w: u32) -> u128 {
     use std::f64::consts::E;
 
@@ -233,6 +256,7 @@ The second magic constant, defined as Odd((\phi - 1) * 2^w),
 where Odd is the nearest odd integer to the given input, where
 \phi  is the golden ratio, and w is defined above.
 */
+#[cfg(all(test, feature = "std"))]
 pub fn calculate_magic_constant_qw(w: u32) -> u128 {
     let golden_ratio = (1. + 5_f64.sqrt()) / 2.;
 
@@ -241,6 +265,7 @@ pub fn calculate_magic_constant_qw(w: u32) -> u128 {
 }
 
 // get nearest odd integer given a float
+#[cfg(all(test, feature = "std"))]
 fn odd(d: f64) -> u128 {
     (((d + 1.) / 2.) * 2. - 1.).round() as u128
 }
@@ -249,6 +274,36 @@ fn odd(d: f64) -> u128 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn fixed_capacity_key_table() {
+        let key = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D,
+            0x0E, 0x0F,
+        ];
+        let mut rc5: Rc5<u32, 12> = Rc5::new(12, key.len()).unwrap();
+        rc5.setup(&key);
+
+        let mut block = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+        rc5.encrypt_block(&mut block);
+        assert_eq!(block, [0x2D, 0xDC, 0x14, 0x9B, 0xCF, 0x08, 0x8B, 0x9E]);
+
+        rc5.decrypt_block(&mut block);
+        assert_eq!(block, [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]);
+    }
+
+    #[test]
+    fn rounds_exceed_capacity() {
+        let rc5: Result<Rc5<u16, 12>> = Rc5::new(16, 8);
+        assert!(matches!(rc5, Err(Error::InvalidRounds(16))));
+    }
+
+    #[test]
+    fn key_too_large() {
+        let rc5: Result<Rc5<u32>> = Rc5::new(12, MAX_KEY_SIZE + 1);
+        assert!(matches!(rc5, Err(Error::KeySizeError(_))));
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     #[ignore]
     fn calculate_pw() {
@@ -264,6 +319,7 @@ mod tests {
         // assert_eq!(pw, 0xB7E151628AED2A6ABF7158809CF4F3C7);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     #[ignore]
     fn calculate_qw() {
@@ -0,0 +1,193 @@
+use crate::byte_order::{ByteOrder, LittleEndian};
+use crate::error::Result;
+use crate::key_table::KeyTable;
+use crate::rc5::{Rc5, MAX_ROUNDS};
+use crate::uint::UInt;
+
+/*
+RC5 with the number of rounds fixed at compile time.
+
+The expanded key is exactly 2*(R+1) words, and the round loops walk the key table
+as an array of R word pairs. The trip count is known to the compiler, so the loops
+can be fully unrolled without any bounds checks on S.
+The output is identical to Rc5<T, R, O> running R rounds.
+*/
+pub struct Rc5Fixed<T, const R: usize, O = LittleEndian> {
+    rc5: Rc5<T, R, O>,
+}
+
+impl<T, const R: usize, O> Rc5Fixed<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    const VALID_ROUNDS: () = assert!(R <= MAX_ROUNDS, "RC5 allows at most 255 rounds");
+
+    pub fn new(key_size: usize) -> Result<Rc5Fixed<T, R, O>> {
+        let () = Self::VALID_ROUNDS;
+
+        Ok(Self {
+            rc5: Rc5::new(R as u8, key_size)?,
+        })
+    }
+
+    // The size of a block in bytes, two words.
+    pub fn block_size(&self) -> usize {
+        self.rc5.block_size()
+    }
+
+    // setup the key, and generate the S table for the cipher
+    pub fn setup(&mut self, key: &[u8]) {
+        self.rc5.setup(key);
+    }
+
+    fn s(&self) -> &KeyTable<T, R> {
+        self.rc5.key_table()
+    }
+
+    pub(crate) fn inner(&self) -> &Rc5<T, R, O> {
+        &self.rc5
+    }
+
+    pub(crate) fn inner_mut(&mut self) -> &mut Rc5<T, R, O> {
+        &mut self.rc5
+    }
+
+    // Encrypts a single block in place, block must be exactly block_size() bytes long.
+    pub fn encrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), self.block_size(), "invalid block size");
+
+        let (plaintext_a, plaintext_b) = self.rc5.parse_bytes(block);
+        let [s0, s1] = *self.s().head();
+
+        let mut a = s0.wadd(plaintext_a);
+        let mut b = s1.wadd(plaintext_b);
+
+        for &[s_a, s_b] in self.s().rounds().iter() {
+            a = (a ^ b).rotl(b.into_u32()).wadd(s_a);
+            b = (b ^ a).rotl(a.into_u32()).wadd(s_b);
+        }
+
+        self.rc5.write_bytes(a, b, block);
+    }
+
+    // Decrypts a single block in place, block must be exactly block_size() bytes long.
+    pub fn decrypt_block(&self, block: &mut [u8]) {
+        assert_eq!(block.len(), self.block_size(), "invalid block size");
+
+        let (ciphertext_a, ciphertext_b) = self.rc5.parse_bytes(block);
+        let [s0, s1] = *self.s().head();
+
+        let mut a = ciphertext_a;
+        let mut b = ciphertext_b;
+
+        for &[s_a, s_b] in self.s().rounds().iter().rev() {
+            b = ((b.wsub(s_b)).rotr(a.into_u32())) ^ a;
+            a = ((a.wsub(s_a)).rotr(b.into_u32())) ^ b;
+        }
+
+        a = a.wsub(s0);
+        b = b.wsub(s1);
+
+        self.rc5.write_bytes(a, b, block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::MAX_BLOCK_SIZE;
+    use crate::custom_uint::U32;
+
+    // Decodes hex into the start of out and returns how many bytes it held, without allocating.
+    fn hex<'a>(s: &str, out: &'a mut [u8; MAX_BLOCK_SIZE]) -> &'a [u8] {
+        let len = s.len() / 2;
+        for (i, byte) in out[..len].iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        &out[..len]
+    }
+
+    // Encrypts and decrypts with both Rc5Fixed and Rc5 and checks they agree with the vector.
+    fn check<T: UInt, const R: usize>(key: &str, pt: &str, ct: &str) {
+        let (mut key_buf, mut pt_buf, mut ct_buf) = (
+            [0; MAX_BLOCK_SIZE],
+            [0; MAX_BLOCK_SIZE],
+            [0; MAX_BLOCK_SIZE],
+        );
+        let key = hex(key, &mut key_buf);
+        let pt = hex(pt, &mut pt_buf);
+        let ct = hex(ct, &mut ct_buf);
+
+        let mut fixed: Rc5Fixed<T, R> = Rc5Fixed::new(key.len()).unwrap();
+        fixed.setup(key);
+        let mut rc5: Rc5<T> = Rc5::new(R as u8, key.len()).unwrap();
+        rc5.setup(key);
+
+        let mut block_buf = [0; MAX_BLOCK_SIZE];
+        let block = &mut block_buf[..pt.len()];
+        block.copy_from_slice(pt);
+        fixed.encrypt_block(block);
+        assert_eq!(block, ct);
+
+        let mut expected_buf = [0; MAX_BLOCK_SIZE];
+        let expected = &mut expected_buf[..pt.len()];
+        expected.copy_from_slice(pt);
+        rc5.encrypt_block(expected);
+        assert_eq!(block, expected);
+
+        fixed.decrypt_block(block);
+        assert_eq!(block, pt);
+    }
+
+    #[test]
+    fn rc5_8_12_4() {
+        check::<u8, 12>("00010203", "0001", "212A");
+    }
+
+    #[test]
+    fn rc5_16_16_8() {
+        check::<u16, 16>("0001020304050607", "00010203", "23A8D72E");
+    }
+
+    #[test]
+    fn rc5_32_12_16() {
+        check::<u32, 12>(
+            "000102030405060708090A0B0C0D0E0F",
+            "0011223344556677",
+            "2DDC149BCF088B9E",
+        );
+    }
+
+    #[test]
+    fn rc5_32_20_16() {
+        check::<u32, 20>(
+            "000102030405060708090A0B0C0D0E0F",
+            "0001020304050607",
+            "2A0EDC0E9431FF73",
+        );
+        check::<U32, 20>(
+            "000102030405060708090A0B0C0D0E0F",
+            "0001020304050607",
+            "2A0EDC0E9431FF73",
+        );
+    }
+
+    #[test]
+    fn rc5_64_24_24() {
+        check::<u64, 24>(
+            "000102030405060708090A0B0C0D0E0F1011121314151617",
+            "000102030405060708090A0B0C0D0E0F",
+            "A46772820EDBCE0235ABEA32AE7178DA",
+        );
+    }
+
+    #[test]
+    fn rc5_128_28_32() {
+        check::<u128, 28>(
+            "000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+            "000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F",
+            "ECA5910921A4F4CFDD7AD7AD20A1FCBA068EC7A7CD752D68FE914B7FE180B440",
+        );
+    }
+}
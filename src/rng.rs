@@ -0,0 +1,337 @@
+use core::ops::Range;
+
+use crate::block_cipher::MAX_BLOCK_SIZE;
+use crate::error::Result;
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+// Reduced rounds for simulations, where speed matters and the outputs aren't secret.
+pub const RNG_ROUNDS: u8 = 8;
+
+const KEY_LEN: usize = 16;
+
+/*
+A counter-based random number generator in the style of Philox: block n of a stream is
+the encryption of the counter n, with the key made from the seed and the stream number.
+
+Any position can be reached in constant time, and every (seed, stream) pair gives an
+independent stream, so parallel workers can each take their own stream and stay reproducible.
+The counter is one block, 2^64 blocks per stream with Rc5<u32> and 2^128 with Rc5<u64>.
+
+This isn't a cryptographic generator at reduced rounds, use CtrDrbg for keys and nonces.
+*/
+pub struct CounterRng<T> {
+    cipher: Rc5<T>,
+    seed: u64,
+    stream: u64,
+    rounds: u8,
+    // The counter of the next block to encrypt.
+    counter: u128,
+    buffer: [u8; MAX_BLOCK_SIZE],
+    // Bytes of buffer already handed out, the block size when it is empty.
+    used: usize,
+}
+
+impl<T> CounterRng<T>
+where
+    T: UInt,
+{
+    pub fn new(rounds: u8, seed: u64, stream: u64) -> Result<CounterRng<T>> {
+        let mut key = [0; KEY_LEN];
+        key[..8].copy_from_slice(&seed.to_le_bytes());
+        key[8..].copy_from_slice(&stream.to_le_bytes());
+
+        let mut cipher = Rc5::new(rounds, KEY_LEN)?;
+        cipher.setup(&key);
+
+        Ok(Self {
+            cipher,
+            seed,
+            stream,
+            rounds,
+            counter: 0,
+            buffer: [0; MAX_BLOCK_SIZE],
+            used: Self::block_len(),
+        })
+    }
+
+    fn block_len() -> usize {
+        2 * T::BYTES
+    }
+
+    // A generator for another stream under the same seed, starting at its first block.
+    pub fn stream(&self, stream: u64) -> CounterRng<T> {
+        Self::new(self.rounds, self.seed, stream).expect("the rounds were already checked")
+    }
+
+    pub fn stream_id(&self) -> u64 {
+        self.stream
+    }
+
+    // The index of the next block, any bytes left from the current block come first.
+    pub fn position(&self) -> u128 {
+        self.counter
+    }
+
+    // Moves to the start of block, dropping what is left of the current block.
+    pub fn seek(&mut self, block: u128) {
+        self.counter = block & self.counter_mask();
+        self.used = Self::block_len();
+    }
+
+    // Skips ahead by blocks blocks from the next block boundary.
+    pub fn advance(&mut self, blocks: u128) {
+        self.seek(self.counter.wrapping_add(blocks));
+    }
+
+    fn counter_mask(&self) -> u128 {
+        let bits = 8 * Self::block_len();
+        if bits >= 128 {
+            u128::MAX
+        } else {
+            (1 << bits) - 1
+        }
+    }
+
+    fn refill(&mut self) {
+        let n = Self::block_len();
+        let width = n.min(16);
+
+        self.buffer = [0; MAX_BLOCK_SIZE];
+        self.buffer[..width].copy_from_slice(&self.counter.to_le_bytes()[..width]);
+        self.cipher.encrypt_block(&mut self.buffer[..n]);
+
+        self.counter = self.counter.wrapping_add(1) & self.counter_mask();
+        self.used = 0;
+    }
+
+    pub fn fill_bytes(&mut self, mut out: &mut [u8]) {
+        let n = Self::block_len();
+        while !out.is_empty() {
+            if self.used == n {
+                self.refill();
+            }
+            let take = (n - self.used).min(out.len());
+            out[..take].copy_from_slice(&self.buffer[self.used..self.used + take]);
+            self.used += take;
+            out = &mut out[take..];
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    // Uniform in [0, 1), with all 53 bits of the mantissa random.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    // Uniform in [0, 1), with all 24 bits of the mantissa random.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    // Uniform in [0, n) without modulo bias, n must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+
+        // Lemire's method, the high half of a 128-bit product, rejecting the biased low values.
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = self.next_u64() as u128 * n as u128;
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+
+    // Uniform in the half-open range, which must not be empty.
+    pub fn range_u64(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range");
+        range.start + self.below(range.end - range.start)
+    }
+
+    pub fn range_i64(&mut self, range: Range<i64>) -> i64 {
+        assert!(range.start < range.end, "empty range");
+        let width = range.end.wrapping_sub(range.start) as u64;
+        range.start.wrapping_add(self.below(width) as i64)
+    }
+
+    // Uniform in the half-open range of floats.
+    pub fn range_f64(&mut self, range: Range<f64>) -> f64 {
+        assert!(range.start < range.end, "empty range");
+        let value = range.start + (range.end - range.start) * self.next_f64();
+        // Rounding can land on the end of the range, which it excludes.
+        if value < range.end {
+            value
+        } else {
+            range.start
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn rng<T: UInt>(stream: u64) -> CounterRng<T> {
+        CounterRng::new(RNG_ROUNDS, 42, stream).unwrap()
+    }
+
+    #[test]
+    fn reproducible() {
+        let mut a = rng::<u32>(0);
+        let mut b = rng::<u32>(0);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        // Block n is the encryption of the counter n.
+        let mut cipher: Rc5<u32> = Rc5::new(RNG_ROUNDS, KEY_LEN).unwrap();
+        let mut key = [0; 16];
+        key[..8].copy_from_slice(&42u64.to_le_bytes());
+        cipher.setup(&key);
+        let mut block = 5u64.to_le_bytes();
+        cipher.encrypt_block(&mut block);
+
+        let mut rng = rng::<u32>(0);
+        rng.seek(5);
+        assert_eq!(rng.next_u64().to_le_bytes(), block);
+    }
+
+    #[test]
+    fn jump_ahead() {
+        let mut sequential = rng::<u64>(3);
+        let mut skipped = rng::<u64>(3);
+
+        let mut bytes = vec![0; 16 * 1000];
+        sequential.fill_bytes(&mut bytes);
+        skipped.advance(1000);
+        assert_eq!(skipped.position(), 1000);
+        assert_eq!(sequential.next_u64(), skipped.next_u64());
+
+        // advance starts from the next block boundary, dropping the rest of the current block.
+        let mut partial = rng::<u64>(3);
+        partial.next_u32();
+        partial.advance(999);
+        let mut fresh = rng::<u64>(3);
+        fresh.seek(1000);
+        assert_eq!(partial.next_u64(), fresh.next_u64());
+
+        // The counter wraps around at the block size.
+        let mut wrapped = rng::<u32>(0);
+        wrapped.seek(u64::MAX as u128);
+        wrapped.next_u64();
+        assert_eq!(wrapped.position(), 0);
+    }
+
+    #[test]
+    fn streams_are_distinct() {
+        let parent = rng::<u32>(0);
+        let mut streams: Vec<_> = (0..8).map(|i| parent.stream(i)).collect();
+        let firsts: std::collections::HashSet<u64> =
+            streams.iter_mut().map(|s| s.next_u64()).collect();
+        assert_eq!(firsts.len(), 8);
+        assert_eq!(streams[5].stream_id(), 5);
+
+        let mut other_seed: CounterRng<u32> = CounterRng::new(RNG_ROUNDS, 43, 0).unwrap();
+        assert_ne!(other_seed.next_u64(), rng::<u32>(0).next_u64());
+    }
+
+    /*
+    Basic statistical checks, the chi-squared bounds are the 0.1% critical values,
+    so these fail by chance for about one key in a thousand, and never change between runs.
+    */
+    fn byte_frequencies<T: UInt>(rng: &mut CounterRng<T>) {
+        let mut bytes = vec![0; 1 << 18];
+        rng.fill_bytes(&mut bytes);
+
+        let mut counts = [0u32; 256];
+        for &b in &bytes {
+            counts[b as usize] += 1;
+        }
+        let expected = bytes.len() as f64 / 256.0;
+        let chi2: f64 = counts
+            .iter()
+            .map(|&c| (c as f64 - expected).powi(2) / expected)
+            .sum();
+        // 255 degrees of freedom
+        assert!(chi2 < 330.5, "chi2 = {}", chi2);
+
+        let ones: u32 = bytes.iter().map(|b| b.count_ones()).sum();
+        let bits = 8.0 * bytes.len() as f64;
+        // Within four standard deviations of half.
+        assert!((ones as f64 - bits / 2.0).abs() < 4.0 * (bits / 4.0).sqrt());
+    }
+
+    #[test]
+    fn statistics_rc5_32() {
+        byte_frequencies(&mut rng::<u32>(0));
+    }
+
+    #[test]
+    fn statistics_rc5_64() {
+        byte_frequencies(&mut rng::<u64>(0));
+    }
+
+    #[test]
+    fn bounded_integers() {
+        let mut rng = rng::<u32>(1);
+        let mut counts = [0u32; 10];
+        for _ in 0..100_000 {
+            counts[rng.below(10) as usize] += 1;
+        }
+        let chi2: f64 = counts
+            .iter()
+            .map(|&c| (c as f64 - 10_000.0).powi(2) / 10_000.0)
+            .sum();
+        // 9 degrees of freedom
+        assert!(chi2 < 27.88, "chi2 = {}", chi2);
+
+        for _ in 0..1000 {
+            let x = rng.range_u64(5..8);
+            assert!((5..8).contains(&x));
+            let y = rng.range_i64(-3..3);
+            assert!((-3..3).contains(&y));
+        }
+        assert_eq!(rng.range_u64(7..8), 7);
+        let full = rng.range_i64(i64::MIN..i64::MAX);
+        assert!(full < i64::MAX);
+    }
+
+    #[test]
+    fn floats() {
+        let mut rng = rng::<u64>(2);
+        let n = 100_000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            sum += x;
+
+            let y = rng.next_f32();
+            assert!((0.0..1.0).contains(&y));
+
+            let z = rng.range_f64(-2.0..3.0);
+            assert!((-2.0..3.0).contains(&z));
+        }
+        // The standard deviation of the mean is 1 / sqrt(12 n), about 0.0009.
+        let mean = sum / n as f64;
+        assert!((mean - 0.5).abs() < 0.004, "mean = {}", mean);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn empty_range() {
+        rng::<u32>(0).range_u64(3..3);
+    }
+}
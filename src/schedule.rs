@@ -0,0 +1,298 @@
+use crate::byte_order::{ByteOrder, LittleEndian};
+use crate::checksum::crc32;
+use crate::error::{Error, Result};
+use crate::rc5::Rc5;
+use crate::rc5_fixed::Rc5Fixed;
+use crate::uint::UInt;
+
+/*
+Binary format for a precomputed key schedule, so a cipher can be loaded without the raw key.
+
+    magic     4 bytes  "RC5S"
+    version   1 byte   SCHEDULE_VERSION
+    w         2 bytes  word size in bits, little-endian
+    r         1 byte   number of rounds
+    order     1 byte   byte order of the cipher's key and blocks, 0 little-endian, 1 big-endian
+    S         t words  the expanded key, t = 2*(r+1), each word little-endian
+    checksum  4 bytes  CRC-32 of everything before it, little-endian
+*/
+pub const SCHEDULE_MAGIC: [u8; 4] = *b"RC5S";
+pub const SCHEDULE_VERSION: u8 = 2;
+
+const HEADER_LEN: usize = 9;
+const CHECKSUM_LEN: usize = 4;
+
+// The length of an exported schedule for the given word size and number of rounds.
+pub fn schedule_len<T: UInt>(rounds: u8) -> usize {
+    HEADER_LEN + 2 * (rounds as usize + 1) * T::range() + CHECKSUM_LEN
+}
+
+impl<T, const R: usize, O> Rc5<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    // Writes the expanded key into out, returning the number of bytes written.
+    pub fn write_schedule(&self, out: &mut [u8]) -> Result<usize> {
+        let len = schedule_len::<T>(self.rounds());
+        if out.len() < len {
+            return Err(Error::BufferTooSmall { needed: len });
+        }
+
+        out[0..4].copy_from_slice(&SCHEDULE_MAGIC);
+        out[4] = SCHEDULE_VERSION;
+        out[5..7].copy_from_slice(&(T::w() as u16).to_le_bytes());
+        out[7] = self.rounds();
+        out[8] = O::BIG_ENDIAN as u8;
+
+        let s = self.key_table();
+        let words = out[HEADER_LEN..len - CHECKSUM_LEN].chunks_exact_mut(T::range());
+        for (i, word) in words.enumerate() {
+            s[i].write_bytes::<LittleEndian>(word);
+        }
+
+        let checksum = crc32(&out[..len - CHECKSUM_LEN]);
+        out[len - CHECKSUM_LEN..len].copy_from_slice(&checksum.to_le_bytes());
+        Ok(len)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn export_schedule(&self) -> Vec<u8> {
+        let mut out = vec![0; schedule_len::<T>(self.rounds())];
+        self.write_schedule(&mut out)
+            .expect("buffer is sized for the schedule");
+        out
+    }
+
+    /*
+    Replaces the expanded key with one previously exported by write_schedule.
+    The schedule must have been made for the same word size, number of rounds and byte order
+    as this cipher.
+    */
+    pub fn load_schedule(&mut self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::InvalidLength {
+                expected: HEADER_LEN,
+                found: bytes.len(),
+            });
+        }
+        if bytes[0..4] != SCHEDULE_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        if bytes[4] != SCHEDULE_VERSION {
+            return Err(Error::UnsupportedVersion(bytes[4]));
+        }
+
+        let w = u16::from_le_bytes([bytes[5], bytes[6]]) as usize;
+        if w != T::w() {
+            return Err(Error::WordSizeMismatch {
+                expected: T::w(),
+                found: w,
+            });
+        }
+        let rounds = bytes[7];
+        if rounds != self.rounds() {
+            return Err(Error::RoundsMismatch {
+                expected: self.rounds(),
+                found: rounds,
+            });
+        }
+        if bytes[8] != O::BIG_ENDIAN as u8 {
+            return Err(Error::ByteOrderMismatch {
+                expected: O::BIG_ENDIAN as u8,
+                found: bytes[8],
+            });
+        }
+
+        let len = schedule_len::<T>(rounds);
+        if bytes.len() != len {
+            return Err(Error::InvalidLength {
+                expected: len,
+                found: bytes.len(),
+            });
+        }
+
+        let mut checksum = [0; CHECKSUM_LEN];
+        checksum.copy_from_slice(&bytes[len - CHECKSUM_LEN..]);
+        if u32::from_le_bytes(checksum) != crc32(&bytes[..len - CHECKSUM_LEN]) {
+            return Err(Error::ChecksumMismatch);
+        }
+
+        let s = self.key_table_mut();
+        let mut words = &bytes[HEADER_LEN..len - CHECKSUM_LEN];
+        for i in 0..2 * (rounds as usize + 1) {
+            s[i] = T::from_bytes::<LittleEndian>(&mut words);
+        }
+        Ok(())
+    }
+}
+
+impl<T, const R: usize, O> Rc5Fixed<T, R, O>
+where
+    T: UInt,
+    O: ByteOrder,
+{
+    // Writes the expanded key into out, returning the number of bytes written.
+    pub fn write_schedule(&self, out: &mut [u8]) -> Result<usize> {
+        self.inner().write_schedule(out)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn export_schedule(&self) -> Vec<u8> {
+        self.inner().export_schedule()
+    }
+
+    // Replaces the expanded key with one exported for R rounds, the same word size and byte order.
+    pub fn load_schedule(&mut self, bytes: &[u8]) -> Result<()> {
+        self.inner_mut().load_schedule(bytes)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::byte_order::BigEndian;
+    use crate::custom_uint::U32;
+    use crate::rc5::MAX_ROUNDS;
+
+    const KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
+        0x0F,
+    ];
+
+    fn rc5<T: UInt>(rounds: u8) -> Rc5<T> {
+        let mut rc5 = Rc5::new(rounds, KEY.len()).unwrap();
+        rc5.setup(&KEY);
+        rc5
+    }
+
+    #[test]
+    fn round_trip() {
+        let schedule = rc5::<u32>(12).export_schedule();
+        assert_eq!(schedule.len(), schedule_len::<u32>(12));
+        assert_eq!(schedule[0..4], SCHEDULE_MAGIC);
+
+        let mut loaded: Rc5<u32> = Rc5::new(12, 0).unwrap();
+        loaded.load_schedule(&schedule).unwrap();
+
+        let mut block = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+        loaded.encrypt_block(&mut block);
+        assert_eq!(block, [0x2D, 0xDC, 0x14, 0x9B, 0xCF, 0x08, 0x8B, 0x9E]);
+    }
+
+    #[test]
+    fn custom_uint_schedule_matches_builtin() {
+        assert_eq!(
+            rc5::<U32>(20).export_schedule(),
+            rc5::<u32>(20).export_schedule()
+        );
+    }
+
+    #[test]
+    fn fixed_round_trip() {
+        let schedule = rc5::<u64>(24).export_schedule();
+
+        let mut fixed: Rc5Fixed<u64, 24> = Rc5Fixed::new(0).unwrap();
+        fixed.load_schedule(&schedule).unwrap();
+        assert_eq!(fixed.export_schedule(), schedule);
+    }
+
+    #[test]
+    fn write_into_small_buffer() {
+        let mut out = [0; 8];
+        let res = rc5::<u32>(12).write_schedule(&mut out);
+        assert!(matches!(res, Err(Error::BufferTooSmall { needed: 117 })));
+    }
+
+    #[test]
+    fn word_size_mismatch() {
+        let schedule = rc5::<u16>(12).export_schedule();
+        let mut loaded: Rc5<u32> = Rc5::new(12, 0).unwrap();
+        let res = loaded.load_schedule(&schedule);
+        assert!(matches!(
+            res,
+            Err(Error::WordSizeMismatch {
+                expected: 32,
+                found: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn rounds_mismatch() {
+        let schedule = rc5::<u32>(12).export_schedule();
+        let mut loaded: Rc5Fixed<u32, 20> = Rc5Fixed::new(0).unwrap();
+        let res = loaded.load_schedule(&schedule);
+        assert!(matches!(
+            res,
+            Err(Error::RoundsMismatch {
+                expected: 20,
+                found: 12
+            })
+        ));
+    }
+
+    #[test]
+    fn byte_order_mismatch() {
+        let mut big_endian: Rc5<u32, MAX_ROUNDS, BigEndian> = Rc5::new(12, KEY.len()).unwrap();
+        big_endian.setup(&KEY);
+        let schedule = big_endian.export_schedule();
+
+        let mut loaded: Rc5<u32> = Rc5::new(12, 0).unwrap();
+        assert!(matches!(
+            loaded.load_schedule(&schedule),
+            Err(Error::ByteOrderMismatch {
+                expected: 0,
+                found: 1
+            })
+        ));
+
+        let mut loaded: Rc5<u32, MAX_ROUNDS, BigEndian> = Rc5::new(12, 0).unwrap();
+        loaded.load_schedule(&schedule).unwrap();
+        let mut block = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77];
+        let mut expected = block;
+        loaded.encrypt_block(&mut block);
+        big_endian.encrypt_block(&mut expected);
+        assert_eq!(block, expected);
+    }
+
+    #[test]
+    fn corrupted_schedule() {
+        let mut schedule = rc5::<u32>(12).export_schedule();
+        schedule[20] ^= 1;
+        let mut loaded: Rc5<u32> = Rc5::new(12, 0).unwrap();
+        assert!(matches!(
+            loaded.load_schedule(&schedule),
+            Err(Error::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn malformed_header() {
+        let schedule = rc5::<u32>(12).export_schedule();
+        let mut loaded: Rc5<u32> = Rc5::new(12, 0).unwrap();
+
+        assert!(matches!(
+            loaded.load_schedule(&schedule[..5]),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            loaded.load_schedule(&schedule[..schedule.len() - 1]),
+            Err(Error::InvalidLength { .. })
+        ));
+
+        let mut bad_magic = schedule.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            loaded.load_schedule(&bad_magic),
+            Err(Error::InvalidMagic)
+        ));
+
+        let mut bad_version = schedule;
+        bad_version[4] = 1;
+        assert!(matches!(
+            loaded.load_schedule(&bad_version),
+            Err(Error::UnsupportedVersion(1))
+        ));
+    }
+}
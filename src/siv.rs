@@ -0,0 +1,466 @@
+use crate::aead::Aead;
+use crate::block_cipher::{BlockEncrypt, MAX_BLOCK_SIZE};
+use crate::constant_time::ct_eq;
+use crate::ctr::apply_keystream;
+use crate::error::{Error, Result};
+use crate::gf::{dbl, reduction_polynomial};
+use crate::mac::{xor, Cmac};
+
+/*
+SIV authenticated encryption following RFC 5297, with S2V over CMAC and CTR, for any
+block size CMAC supports. The MAC and CTR ciphers must be keyed independently.
+
+The synthetic IV is the tag, it is a whole block and like every tag in this crate it
+follows the ciphertext, where RFC 5297 puts it in front.
+
+Encrypting the same headers and plaintext twice gives the same ciphertext, which is what
+deterministic encryption of keys wants. Using a nonce as the last header gives
+nonce-based encryption that only leaks message equality if a nonce is repeated.
+*/
+pub struct Siv<'a, C> {
+    mac: Cmac<'a, C>,
+    ctr_cipher: &'a C,
+    polynomial: u16,
+}
+
+impl<'a, C> Siv<'a, C>
+where
+    C: BlockEncrypt,
+{
+    pub fn new(mac_cipher: &'a C, ctr_cipher: &'a C) -> Result<Siv<'a, C>> {
+        Ok(Self {
+            mac: Cmac::new(mac_cipher)?,
+            ctr_cipher,
+            polynomial: reduction_polynomial(C::BLOCK_SIZE)?,
+        })
+    }
+
+    // At most BLOCK_SIZE * 8 - 2 headers, one less than S2V can take with the plaintext.
+    pub fn max_headers() -> usize {
+        C::BLOCK_SIZE * 8 - 2
+    }
+
+    fn cmac(&self, data: &[u8], out: &mut [u8]) {
+        let mut mac = self.mac.clone();
+        mac.update(data);
+        mac.finalize_into(out)
+            .expect("a full block is a valid tag length");
+    }
+
+    // S2V over the headers followed by the plaintext.
+    fn s2v(&self, headers: &[&[u8]], plaintext: &[u8]) -> Result<[u8; MAX_BLOCK_SIZE]> {
+        let n = C::BLOCK_SIZE;
+        if headers.len() > Self::max_headers() {
+            return Err(Error::TooManyHeaders {
+                max: Self::max_headers(),
+                found: headers.len(),
+            });
+        }
+
+        let mut d = [0; MAX_BLOCK_SIZE];
+        let zero = [0; MAX_BLOCK_SIZE];
+        self.cmac(&zero[..n], &mut d[..n]);
+
+        let mut header_mac = [0; MAX_BLOCK_SIZE];
+        for header in headers {
+            dbl(&mut d[..n], self.polynomial);
+            self.cmac(header, &mut header_mac[..n]);
+            xor(&mut d[..n], &header_mac[..n]);
+        }
+
+        let mut mac = self.mac.clone();
+        if plaintext.len() >= n {
+            // T = plaintext xorend D
+            let (head, last) = plaintext.split_at(plaintext.len() - n);
+            mac.update(head);
+            xor(&mut d[..n], last);
+        } else {
+            // T = dbl(D) xor pad(plaintext)
+            dbl(&mut d[..n], self.polynomial);
+            xor(&mut d[..plaintext.len()], plaintext);
+            d[plaintext.len()] ^= 0x80;
+        }
+        mac.update(&d[..n]);
+
+        let mut v = [0; MAX_BLOCK_SIZE];
+        mac.finalize_into(&mut v[..n])
+            .expect("a full block is a valid tag length");
+        Ok(v)
+    }
+
+    /*
+    CTR keyed by the synthetic IV. As in RFC 5297 the top bits of the last two 32-bit words
+    are cleared, so the counter can be incremented as machine words without carries.
+    */
+    fn apply_keystream(&self, v: &[u8], data: &mut [u8]) {
+        let n = C::BLOCK_SIZE;
+        let mut q = [0; MAX_BLOCK_SIZE];
+        q[..n].copy_from_slice(v);
+        for word in [4, 8] {
+            if n >= word {
+                q[n - word] &= 0x7F;
+            }
+        }
+        apply_keystream(self.ctr_cipher, &mut q[..n], n, data);
+    }
+
+    // Encrypts buffer in place under any number of headers and writes the BLOCK_SIZE byte tag.
+    pub fn encrypt_headers_in_place(
+        &self,
+        headers: &[&[u8]],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        let n = C::BLOCK_SIZE;
+        if tag.len() != n {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+
+        let v = self.s2v(headers, buffer)?;
+        self.apply_keystream(&v[..n], buffer);
+        tag.copy_from_slice(&v[..n]);
+        Ok(())
+    }
+
+    /*
+    The tag is computed over the plaintext, so the buffer is decrypted before it is checked.
+    On failure the keystream is applied again, restoring the ciphertext.
+    */
+    pub fn decrypt_headers_in_place(
+        &self,
+        headers: &[&[u8]],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        let n = C::BLOCK_SIZE;
+        if tag.len() != n {
+            return Err(Error::InvalidTagLength(tag.len()));
+        }
+        if headers.len() > Self::max_headers() {
+            return Err(Error::TooManyHeaders {
+                max: Self::max_headers(),
+                found: headers.len(),
+            });
+        }
+
+        self.apply_keystream(tag, buffer);
+        let v = self.s2v(headers, buffer)?;
+        if !ct_eq(&v[..n], tag) {
+            self.apply_keystream(tag, buffer);
+            return Err(Error::AuthenticationFailed);
+        }
+        Ok(())
+    }
+
+    // Returns the ciphertext followed by the tag.
+    #[cfg(feature = "std")]
+    pub fn encrypt_headers(&self, headers: &[&[u8]], plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut out = plaintext.to_vec();
+        let mut tag = vec![0; C::BLOCK_SIZE];
+        self.encrypt_headers_in_place(headers, &mut out, &mut tag)?;
+        out.extend(tag);
+        Ok(out)
+    }
+
+    // Takes the ciphertext followed by the tag, and returns the plaintext if the tag is valid.
+    #[cfg(feature = "std")]
+    pub fn decrypt_headers(&self, headers: &[&[u8]], sealed: &[u8]) -> Result<Vec<u8>> {
+        let n = C::BLOCK_SIZE;
+        if sealed.len() < n {
+            return Err(Error::InvalidLength {
+                expected: n,
+                found: sealed.len(),
+            });
+        }
+
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - n);
+        let mut out = ciphertext.to_vec();
+        self.decrypt_headers_in_place(headers, &mut out, tag)?;
+        Ok(out)
+    }
+}
+
+/*
+As an Aead the headers are the associated data followed by the nonce.
+An empty nonce is left out, which gives deterministic encryption.
+*/
+impl<'a, C> Aead for Siv<'a, C>
+where
+    C: BlockEncrypt,
+{
+    fn tag_len(&self) -> usize {
+        C::BLOCK_SIZE
+    }
+
+    fn encrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<()> {
+        let headers = [associated_data, nonce];
+        let count = if nonce.is_empty() { 1 } else { 2 };
+        self.encrypt_headers_in_place(&headers[..count], buffer, tag)
+    }
+
+    fn decrypt_in_place(
+        &self,
+        nonce: &[u8],
+        associated_data: &[u8],
+        buffer: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        let headers = [associated_data, nonce];
+        let count = if nonce.is_empty() { 1 } else { 2 };
+        self.decrypt_headers_in_place(&headers[..count], buffer, tag)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::testing::{bytes, rc5_with_key, Aes};
+
+    // (header lengths, message length, ciphertext, tag),
+    // generated by `python3 tests/vectors/rc5ref.py siv`.
+    type Vector = (&'static [usize], usize, &'static str, &'static str);
+
+    fn check<C: BlockEncrypt>(mac_cipher: &C, ctr_cipher: &C, vectors: &[Vector]) {
+        let siv = Siv::new(mac_cipher, ctr_cipher).unwrap();
+
+        for &(header_lens, len, ct, tag) in vectors {
+            let headers: Vec<Vec<u8>> = header_lens
+                .iter()
+                .enumerate()
+                .map(|(i, &header_len)| bytes(200 - 50 * i as u8, header_len))
+                .collect();
+            let headers: Vec<&[u8]> = headers.iter().map(|h| &h[..]).collect();
+            let pt = bytes(0, len);
+
+            let sealed = siv.encrypt_headers(&headers, &pt).unwrap();
+            let mut expected = decode_hex(ct).unwrap();
+            expected.extend(decode_hex(tag).unwrap());
+            assert_eq!(sealed, expected);
+
+            assert_eq!(siv.decrypt_headers(&headers, &sealed).unwrap(), pt);
+        }
+    }
+
+    // The examples of RFC 5297 appendix A, the RFC puts the tag before the ciphertext.
+    #[test]
+    fn aes_rfc_5297() {
+        let key =
+            decode_hex("FFFEFDFCFBFAF9F8F7F6F5F4F3F2F1F0F0F1F2F3F4F5F6F7F8F9FAFBFCFDFEFF").unwrap();
+        let (mac_cipher, ctr_cipher) = (Aes::new(&key[..16]), Aes::new(&key[16..]));
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+        let ad = decode_hex("101112131415161718191A1B1C1D1E1F2021222324252627").unwrap();
+        let sealed = siv
+            .encrypt_headers(&[&ad], &decode_hex("112233445566778899AABBCCDDEE").unwrap())
+            .unwrap();
+        assert_eq!(
+            sealed,
+            decode_hex("40C02B9690C4DC04DAEF7F6AFE5C85632D07C6E8F37F950ACD320A2ECC93").unwrap()
+        );
+
+        let key =
+            decode_hex("7F7E7D7C7B7A79787776757473727170404142434445464748494A4B4C4D4E4F").unwrap();
+        let (mac_cipher, ctr_cipher) = (Aes::new(&key[..16]), Aes::new(&key[16..]));
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+        let headers = [
+            decode_hex(
+                "00112233445566778899AABBCCDDEEFFDEADDADADEADDADAFFEEDDCCBBAA99887766554433221100",
+            )
+            .unwrap(),
+            decode_hex("102030405060708090A0").unwrap(),
+            decode_hex("09F911029D74E35BD84156C5635688C0").unwrap(),
+        ];
+        let headers: Vec<&[u8]> = headers.iter().map(|h| &h[..]).collect();
+        let pt = b"this is some plaintext to encrypt using SIV-AES";
+        let sealed = siv.encrypt_headers(&headers, pt).unwrap();
+        assert_eq!(
+            sealed,
+            decode_hex(
+                "CB900F2FDDBE404326601965C889BF17DBA77CEB094FA663B7A3F748BA8AF829\
+                 EA64AD544A272E9C485B62A3FD5C0D7BDB6E3B432667EB06F4D14BFF2FBD0F"
+            )
+            .unwrap()
+        );
+        assert_eq!(siv.decrypt_headers(&headers, &sealed).unwrap(), pt);
+    }
+
+    #[test]
+    fn rc5_32() {
+        let key = bytes(0, 32);
+        check(
+            &rc5_with_key::<u32>(12, &key[..16]),
+            &rc5_with_key::<u32>(12, &key[16..]),
+            &[
+                (&[], 0, "", "7BD147E75B4B0581"),
+                (&[0], 5, "C39EB06590", "F03CA5C4C256E811"),
+                (&[10], 16, "8AEF517C2C470C9DB1C04541085B80D7", "D9034BD36FB79904"),
+                (&[3, 9, 0], 11, "4035B52F7980566E387A78", "5D8B7A3ED3D6E483"),
+                (
+                    &[20, 12],
+                    40,
+                    "9D0F0AF5D13F9D5788AEF8B8F7D4B7D6BAA9CC22BDDE39A9FFE65F9F0D8AE392AC82FB6F890387EF",
+                    "DFC4F40096A7D011",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn rc5_64() {
+        let key = bytes(0, 48);
+        check(
+            &rc5_with_key::<u64>(24, &key[..24]),
+            &rc5_with_key::<u64>(24, &key[24..]),
+            &[
+                (&[], 0, "", "88E9A9C6F1B0DECDD2CCB03A600C796A"),
+                (&[0], 5, "FF5A6801D5", "F35D0F2CBDE314B26C48D87D2247674F"),
+                (
+                    &[10],
+                    32,
+                    "327302F9C1CA8BC58AD911028332902615F400A3F2C6098E649F4491CE3EEFF0",
+                    "E3C4A0567D77E256168DCCCB2B51236D",
+                ),
+                (
+                    &[3, 17, 0],
+                    19,
+                    "4C1B16FE5AE229808E3B1715E154A1515BFFFA",
+                    "26D8E9213240553CC6D445B068C7158B",
+                ),
+                (
+                    &[20, 12],
+                    40,
+                    "5595E791B8DBB66BF98631985B56FFB427FCF95FA5CA43CA94E3D1E3EA96A4D84C927C02C7932F3E",
+                    "F1976536F72B0332D1E174913872D609",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn deterministic_and_nonce_based() {
+        let key = bytes(0, 32);
+        let (mac_cipher, ctr_cipher) = (
+            rc5_with_key::<u32>(12, &key[..16]),
+            rc5_with_key::<u32>(12, &key[16..]),
+        );
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+
+        // Without a nonce equal inputs give equal outputs, so encrypted keys can be looked up.
+        let first = siv.encrypt(b"", b"users", b"alice@example.com").unwrap();
+        let second = siv.encrypt(b"", b"users", b"alice@example.com").unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            siv.encrypt_headers(&[b"users"], b"alice@example.com")
+                .unwrap()
+        );
+
+        // The nonce is the last header.
+        let with_nonce = siv.encrypt(b"n1", b"users", b"alice@example.com").unwrap();
+        assert_ne!(with_nonce, first);
+        assert_eq!(
+            with_nonce,
+            siv.encrypt_headers(&[b"users", b"n1"], b"alice@example.com")
+                .unwrap()
+        );
+        assert_ne!(
+            siv.encrypt(b"n2", b"users", b"alice@example.com").unwrap(),
+            with_nonce
+        );
+        assert_eq!(
+            siv.decrypt(b"n1", b"users", &with_nonce).unwrap(),
+            b"alice@example.com"
+        );
+    }
+
+    // Splitting or reordering the headers changes the tag.
+    #[test]
+    fn header_boundaries() {
+        let key = bytes(0, 48);
+        let (mac_cipher, ctr_cipher) = (
+            rc5_with_key::<u64>(24, &key[..24]),
+            rc5_with_key::<u64>(24, &key[24..]),
+        );
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+
+        let sealed = siv.encrypt_headers(&[b"ab", b"cd"], b"message").unwrap();
+        for headers in [
+            &[&b"abcd"[..]][..],
+            &[b"cd", b"ab"],
+            &[b"ab", b"cd", b""],
+            &[],
+        ] {
+            assert!(matches!(
+                siv.decrypt_headers(headers, &sealed),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn forgeries_are_rejected() {
+        let key = bytes(0, 32);
+        let (mac_cipher, ctr_cipher) = (
+            rc5_with_key::<u32>(12, &key[..16]),
+            rc5_with_key::<u32>(12, &key[16..]),
+        );
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+        let sealed = siv.encrypt(b"nonce", b"header", b"attack at dawn").unwrap();
+
+        for i in 0..sealed.len() {
+            let mut forged = sealed.clone();
+            forged[i] ^= 0x01;
+            assert!(matches!(
+                siv.decrypt(b"nonce", b"header", &forged),
+                Err(Error::AuthenticationFailed)
+            ));
+        }
+    }
+
+    #[test]
+    fn no_plaintext_on_failure() {
+        let key = bytes(0, 32);
+        let (mac_cipher, ctr_cipher) = (
+            rc5_with_key::<u32>(12, &key[..16]),
+            rc5_with_key::<u32>(12, &key[16..]),
+        );
+        let siv = Siv::new(&mac_cipher, &ctr_cipher).unwrap();
+
+        let mut buffer = b"secret message".to_vec();
+        let mut tag = [0; 8];
+        siv.encrypt_headers_in_place(&[b"header"], &mut buffer, &mut tag)
+            .unwrap();
+        let ciphertext = buffer.clone();
+
+        tag[7] ^= 1;
+        assert!(matches!(
+            siv.decrypt_headers_in_place(&[b"header"], &mut buffer, &tag),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert_eq!(buffer, ciphertext);
+    }
+
+    #[test]
+    fn header_limit() {
+        let cipher = rc5_with_key::<u16>(16, &bytes(0, 8));
+        let siv = Siv::new(&cipher, &cipher).unwrap();
+        assert_eq!(Siv::<Rc5<u16>>::max_headers(), 30);
+
+        let headers = vec![&b"h"[..]; 30];
+        let sealed = siv.encrypt_headers(&headers, b"message").unwrap();
+        assert_eq!(siv.decrypt_headers(&headers, &sealed).unwrap(), b"message");
+
+        let headers = vec![&b"h"[..]; 31];
+        assert!(matches!(
+            siv.encrypt_headers(&headers, b"message"),
+            Err(Error::TooManyHeaders { max: 30, found: 31 })
+        ));
+    }
+}
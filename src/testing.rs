@@ -0,0 +1,208 @@
+use crate::block_cipher::{BlockCipher, BlockDecrypt, BlockEncrypt};
+use crate::rc5::Rc5;
+use crate::uint::UInt;
+
+/*
+Helpers shared by the tests.
+
+The known answers for RC5 come from tests/vectors/rc5ref.py, which builds its ciphers and inputs
+the same way as rc5 and bytes below. Aes lets the modes be checked against their published vectors.
+*/
+
+// An RC5 cipher keyed with the bytes 0, 1, ..., key_len - 1.
+pub(crate) fn rc5<T: UInt>(rounds: u8, key_len: u8) -> Rc5<T> {
+    rc5_with_key(rounds, &bytes(0, key_len as usize))
+}
+
+pub(crate) fn rc5_with_key<T: UInt>(rounds: u8, key: &[u8]) -> Rc5<T> {
+    let mut rc5 = Rc5::new(rounds, key.len()).unwrap();
+    rc5.setup(key);
+    rc5
+}
+
+// len bytes counting up from start, wrapping past 0xFF.
+pub(crate) fn bytes(start: u8, len: usize) -> Vec<u8> {
+    (0..len).map(|i| start.wrapping_add(i as u8)).collect()
+}
+
+/*
+AES as specified in FIPS 197, written for clarity rather than speed or constant time.
+It only exists so the modes can be tested against the AES vectors their specifications publish.
+*/
+pub(crate) struct Aes {
+    round_keys: Vec<[u8; 16]>,
+    sbox: [u8; 256],
+    inv_sbox: [u8; 256],
+}
+
+impl Aes {
+    // Takes a 16, 24 or 32 byte key.
+    pub(crate) fn new(key: &[u8]) -> Aes {
+        assert!(matches!(key.len(), 16 | 24 | 32), "invalid AES key length");
+        let (sbox, inv_sbox) = sboxes();
+
+        let nk = key.len() / 4;
+        let rounds = nk + 6;
+        let mut words: Vec<[u8; 4]> = key
+            .chunks_exact(4)
+            .map(|w| [w[0], w[1], w[2], w[3]])
+            .collect();
+        let mut rcon = 1;
+        for i in nk..4 * (rounds + 1) {
+            let mut word = words[i - 1];
+            if i % nk == 0 {
+                word.rotate_left(1);
+                word = word.map(|b| sbox[b as usize]);
+                word[0] ^= rcon;
+                rcon = gmul(rcon, 2);
+            } else if nk > 6 && i % nk == 4 {
+                word = word.map(|b| sbox[b as usize]);
+            }
+            for (b, prev) in word.iter_mut().zip(&words[i - nk]) {
+                *b ^= prev;
+            }
+            words.push(word);
+        }
+
+        let round_keys = words
+            .chunks_exact(4)
+            .map(|w| {
+                let mut key = [0; 16];
+                for (i, word) in w.iter().enumerate() {
+                    key[4 * i..4 * i + 4].copy_from_slice(word);
+                }
+                key
+            })
+            .collect();
+
+        Aes {
+            round_keys,
+            sbox,
+            inv_sbox,
+        }
+    }
+
+    fn add_round_key(&self, state: &mut [u8], round: usize) {
+        for (b, k) in state.iter_mut().zip(&self.round_keys[round]) {
+            *b ^= k;
+        }
+    }
+}
+
+impl BlockCipher for Aes {
+    const BLOCK_SIZE: usize = 16;
+}
+
+impl BlockEncrypt for Aes {
+    fn encrypt_block(&self, state: &mut [u8]) {
+        let rounds = self.round_keys.len() - 1;
+        self.add_round_key(state, 0);
+        for round in 1..=rounds {
+            for b in state.iter_mut() {
+                *b = self.sbox[*b as usize];
+            }
+            shift_rows(state, |r| r);
+            if round != rounds {
+                mix_columns(state, [2, 3, 1, 1]);
+            }
+            self.add_round_key(state, round);
+        }
+    }
+}
+
+impl BlockDecrypt for Aes {
+    fn decrypt_block(&self, state: &mut [u8]) {
+        let rounds = self.round_keys.len() - 1;
+        self.add_round_key(state, rounds);
+        for round in (0..rounds).rev() {
+            shift_rows(state, |r| 4 - r);
+            for b in state.iter_mut() {
+                *b = self.inv_sbox[*b as usize];
+            }
+            self.add_round_key(state, round);
+            if round != 0 {
+                mix_columns(state, [14, 11, 13, 9]);
+            }
+        }
+    }
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1.
+fn gmul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1B } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+// The S-box is the affine transform of each byte's multiplicative inverse.
+fn sboxes() -> ([u8; 256], [u8; 256]) {
+    let mut sbox = [0; 256];
+    let mut inv_sbox = [0; 256];
+    for x in 0..=255u8 {
+        let inverse = (1..=255u8).find(|&y| gmul(x, y) == 1).unwrap_or(0);
+        let s = inverse
+            ^ inverse.rotate_left(1)
+            ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3)
+            ^ inverse.rotate_left(4)
+            ^ 0x63;
+        sbox[x as usize] = s;
+        inv_sbox[s as usize] = x;
+    }
+    (sbox, inv_sbox)
+}
+
+// The state is stored column by column, row r is rotated left by shift(r) places.
+fn shift_rows(state: &mut [u8], shift: impl Fn(usize) -> usize) {
+    let mut old = [0; 16];
+    old.copy_from_slice(state);
+    for r in 1..4 {
+        for c in 0..4 {
+            state[r + 4 * c] = old[r + 4 * ((c + shift(r)) % 4)];
+        }
+    }
+}
+
+// Multiplies each column by the circulant matrix whose first row is m.
+fn mix_columns(state: &mut [u8], m: [u8; 4]) {
+    for column in state.chunks_exact_mut(4) {
+        let a = [column[0], column[1], column[2], column[3]];
+        for (r, b) in column.iter_mut().enumerate() {
+            *b = (0..4).fold(0, |acc, i| acc ^ gmul(a[i], m[(4 + i - r) % 4]));
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+
+    // The example vectors of FIPS 197 appendix C.
+    #[test]
+    fn aes_fips_197() {
+        let pt = decode_hex("00112233445566778899AABBCCDDEEFF").unwrap();
+        for (key_len, ct) in [
+            (16, "69C4E0D86A7B0430D8CDB78070B4C55A"),
+            (24, "DDA97CA4864CDFE06EAF70A0EC0D7191"),
+            (32, "8EA2B7CA516745BFEAFC49904B496089"),
+        ] {
+            let aes = Aes::new(&bytes(0, key_len));
+            let mut block = pt.clone();
+            aes.encrypt_block(&mut block);
+            assert_eq!(block, decode_hex(ct).unwrap());
+            aes.decrypt_block(&mut block);
+            assert_eq!(block, pt);
+        }
+    }
+
+    #[test]
+    fn bytes_wrap() {
+        assert_eq!(bytes(0xFE, 3), [0xFE, 0xFF, 0x00]);
+    }
+}
@@ -1,10 +1,14 @@
-use std::{
+use core::{
     fmt::{Binary, Display, LowerHex},
     ops::{Add, BitOr, BitXor, Sub},
 };
 
-use crate::{custom_uint::CustomUInt, from_bytes::FromBytes};
-use std::fmt::Debug;
+use crate::{
+    byte_order::{BigEndian, ByteOrder},
+    custom_uint::CustomUInt,
+    from_bytes::FromBytes,
+};
+use core::fmt::Debug;
 
 // The trait that all uints must implement, to be used in the RC5 algorithm.
 pub trait UInt:
@@ -27,9 +31,15 @@ pub trait UInt:
     fn rotl(self, rhs: u32) -> Self;
     fn rotr(self, rhs: u32) -> Self;
     fn into_u32(self) -> u32;
-    fn from_bytes(a: &mut &[u8]) -> Self;
-    fn to_bytes(&self) -> Vec<u8>;
-    fn range() -> usize;
+    // Reads a word from the front of a, advancing it past the bytes that were used.
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self;
+    // Writes the word into out, which must be exactly range() bytes long.
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]);
+    // The length of a word in bytes.
+    const BYTES: usize;
+    fn range() -> usize {
+        Self::BYTES
+    }
     // The length of a word in bits, typically 16, 32 or 64. Encryption is done in 2-word blocks.
     fn w() -> usize;
     /*
@@ -46,7 +56,19 @@ pub trait UInt:
     fn qw() -> Self;
 }
 
+// The 256-bit magic constants, as big-endian bytes since they don't fit in a u128.
+const PW_256: [u8; 32] = [
+    0xB7, 0xE1, 0x51, 0x62, 0x8A, 0xED, 0x2A, 0x6A, 0xBF, 0x71, 0x58, 0x80, 0x9C, 0xF4, 0xF3, 0xC7,
+    0x62, 0xE7, 0x16, 0x0F, 0x38, 0xB4, 0xDA, 0x56, 0xA7, 0x84, 0xD9, 0x04, 0x51, 0x90, 0xCF, 0xEF,
+];
+const QW_256: [u8; 32] = [
+    0x9E, 0x37, 0x79, 0xB9, 0x7F, 0x4A, 0x7C, 0x15, 0xF3, 0x9C, 0xC0, 0x60, 0x5C, 0xED, 0xC8, 0x34,
+    0x10, 0x82, 0x27, 0x6B, 0xF3, 0xA2, 0x72, 0x51, 0xF8, 0x6C, 0x6A, 0x11, 0xD0, 0xC1, 0x8E, 0x95,
+];
+
 impl<const N: usize> UInt for CustomUInt<N> {
+    const BYTES: usize = N / 8;
+
     fn zero() -> Self {
         Self::from_u128(0)
     }
@@ -79,16 +101,14 @@ impl<const N: usize> UInt for CustomUInt<N> {
         self.to_u32()
     }
 
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_bytes(&a, false)
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let (int_bytes, rest) = a.split_at(N / 8);
+        *a = rest;
+        Self::from_bytes::<O>(int_bytes)
     }
 
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_bytes(false)
-    }
-
-    fn range() -> usize {
-        N / 8
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        self.write_bytes::<O>(out)
     }
 
     fn w() -> usize {
@@ -97,10 +117,7 @@ impl<const N: usize> UInt for CustomUInt<N> {
 
     fn pw() -> Self {
         if N == 256 {
-            return Self::from_hex_str(
-                "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
-            )
-            .unwrap();
+            return Self::from_bytes::<BigEndian>(&PW_256);
         }
 
         let pw = match N {
@@ -118,10 +135,7 @@ impl<const N: usize> UInt for CustomUInt<N> {
 
     fn qw() -> Self {
         if N == 256 {
-            return Self::from_hex_str(
-                "9E3779B97F4A7C15F39CC0605CEDC8341082276BF3A27251F86C6A11D0C18E95",
-            )
-            .unwrap();
+            return Self::from_bytes::<BigEndian>(&QW_256);
         }
 
         let qw = match N {
@@ -143,6 +157,8 @@ impl<const N: usize> UInt for CustomUInt<N> {
 // not strictly necessary for the code to work because CustomUInt supports U8,U16,U32,U64,U128
 
 impl UInt for u8 {
+    const BYTES: usize = 1;
+
     fn zero() -> Self {
         0
     }
@@ -150,7 +166,7 @@ impl UInt for u8 {
         u as u8
     }
     fn from_u8(u: u8) -> Self {
-        u as u8
+        u
     }
     fn wadd(self, rhs: Self) -> Self {
         self.wrapping_add(rhs)
@@ -167,16 +183,22 @@ impl UInt for u8 {
     fn into_u32(self) -> u32 {
         self as u32
     }
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_le_bytes(FromBytes::from_le_bytes(a))
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let bytes = FromBytes::take_bytes(a);
+        if O::BIG_ENDIAN {
+            Self::from_be_bytes(bytes)
+        } else {
+            Self::from_le_bytes(bytes)
+        }
     }
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_le_bytes().to_vec()
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        if O::BIG_ENDIAN {
+            out.copy_from_slice(&self.to_be_bytes())
+        } else {
+            out.copy_from_slice(&self.to_le_bytes())
+        }
     }
 
-    fn range() -> usize {
-        1
-    }
     fn pw() -> Self {
         0xB7
     }
@@ -189,6 +211,8 @@ impl UInt for u8 {
 }
 
 impl UInt for u16 {
+    const BYTES: usize = 2;
+
     fn zero() -> Self {
         0
     }
@@ -213,14 +237,20 @@ impl UInt for u16 {
     fn into_u32(self) -> u32 {
         self as u32
     }
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_le_bytes(FromBytes::from_le_bytes(a))
-    }
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_le_bytes().to_vec()
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let bytes = FromBytes::take_bytes(a);
+        if O::BIG_ENDIAN {
+            Self::from_be_bytes(bytes)
+        } else {
+            Self::from_le_bytes(bytes)
+        }
     }
-    fn range() -> usize {
-        2
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        if O::BIG_ENDIAN {
+            out.copy_from_slice(&self.to_be_bytes())
+        } else {
+            out.copy_from_slice(&self.to_le_bytes())
+        }
     }
     fn pw() -> Self {
         0xB7E1
@@ -234,11 +264,13 @@ impl UInt for u16 {
 }
 
 impl UInt for u32 {
+    const BYTES: usize = 4;
+
     fn zero() -> Self {
         0
     }
     fn n(u: u32) -> Self {
-        u as u32
+        u
     }
     fn from_u8(u: u8) -> Self {
         u as u32
@@ -256,18 +288,24 @@ impl UInt for u32 {
         self.rotate_right(rhs)
     }
     fn into_u32(self) -> u32 {
-        self as u32
-    }
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_le_bytes(FromBytes::from_le_bytes(a))
+        self
+    }
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let bytes = FromBytes::take_bytes(a);
+        if O::BIG_ENDIAN {
+            Self::from_be_bytes(bytes)
+        } else {
+            Self::from_le_bytes(bytes)
+        }
     }
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_le_bytes().to_vec()
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        if O::BIG_ENDIAN {
+            out.copy_from_slice(&self.to_be_bytes())
+        } else {
+            out.copy_from_slice(&self.to_le_bytes())
+        }
     }
 
-    fn range() -> usize {
-        4
-    }
     fn pw() -> Self {
         0xB7E15163
     }
@@ -280,6 +318,8 @@ impl UInt for u32 {
 }
 
 impl UInt for u64 {
+    const BYTES: usize = 8;
+
     fn zero() -> Self {
         0
     }
@@ -304,14 +344,20 @@ impl UInt for u64 {
     fn into_u32(self) -> u32 {
         self as u32
     }
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_le_bytes(FromBytes::from_le_bytes(a))
-    }
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_le_bytes().to_vec()
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let bytes = FromBytes::take_bytes(a);
+        if O::BIG_ENDIAN {
+            Self::from_be_bytes(bytes)
+        } else {
+            Self::from_le_bytes(bytes)
+        }
     }
-    fn range() -> usize {
-        8
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        if O::BIG_ENDIAN {
+            out.copy_from_slice(&self.to_be_bytes())
+        } else {
+            out.copy_from_slice(&self.to_le_bytes())
+        }
     }
     fn pw() -> Self {
         0xB7E151628AED2A6B
@@ -325,6 +371,8 @@ impl UInt for u64 {
 }
 
 impl UInt for u128 {
+    const BYTES: usize = 16;
+
     fn zero() -> Self {
         0
     }
@@ -349,16 +397,22 @@ impl UInt for u128 {
     fn into_u32(self) -> u32 {
         self as u32
     }
-    fn from_bytes(a: &mut &[u8]) -> Self {
-        Self::from_le_bytes(FromBytes::from_le_bytes(a))
+    fn from_bytes<O: ByteOrder>(a: &mut &[u8]) -> Self {
+        let bytes = FromBytes::take_bytes(a);
+        if O::BIG_ENDIAN {
+            Self::from_be_bytes(bytes)
+        } else {
+            Self::from_le_bytes(bytes)
+        }
     }
-    fn to_bytes(&self) -> Vec<u8> {
-        self.to_le_bytes().to_vec()
+    fn write_bytes<O: ByteOrder>(&self, out: &mut [u8]) {
+        if O::BIG_ENDIAN {
+            out.copy_from_slice(&self.to_be_bytes())
+        } else {
+            out.copy_from_slice(&self.to_le_bytes())
+        }
     }
 
-    fn range() -> usize {
-        u128::BITS as usize / 8
-    }
     fn pw() -> Self {
         0xB7E151628AED2A6ABF7158809CF4F3C7
     }
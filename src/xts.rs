@@ -0,0 +1,266 @@
+use crate::block_cipher::{BlockDecrypt, BlockEncrypt};
+use crate::error::{Error, Result};
+use crate::mac::xor;
+
+// XTS is defined for 128-bit blocks, which is RC5 with 64-bit words.
+const BLOCK_SIZE: usize = 16;
+
+/*
+XTS as specified in IEEE 1619, for sector encryption with ciphers that have a 128-bit block
+such as Rc5<u64>. The data cipher and the tweak cipher must be keyed independently.
+
+Each sector is encrypted in place without expansion, the tweak is the sector number
+encrypted as a little-endian 128-bit block. A sector that isn't a whole number of blocks
+uses ciphertext stealing for its last two blocks.
+
+offset is the position of data within its sector, so part of a sector can be processed on its own.
+It has to be a multiple of the block size, and the data has to run to the end of the
+sector whenever the sector ends in a partial block.
+*/
+pub struct Xts<'a, C> {
+    data_cipher: &'a C,
+    tweak_cipher: &'a C,
+}
+
+impl<'a, C> Xts<'a, C>
+where
+    C: BlockEncrypt + BlockDecrypt,
+{
+    pub fn new(data_cipher: &'a C, tweak_cipher: &'a C) -> Result<Xts<'a, C>> {
+        if C::BLOCK_SIZE != BLOCK_SIZE {
+            return Err(Error::UnsupportedBlockSize(C::BLOCK_SIZE));
+        }
+
+        Ok(Self {
+            data_cipher,
+            tweak_cipher,
+        })
+    }
+
+    pub fn encrypt_sector(&self, sector: u128, offset: usize, data: &mut [u8]) -> Result<()> {
+        self.process(sector, offset, data, false)
+    }
+
+    pub fn decrypt_sector(&self, sector: u128, offset: usize, data: &mut [u8]) -> Result<()> {
+        self.process(sector, offset, data, true)
+    }
+
+    // The tweak for the block at offset, E2(sector) multiplied by alpha once per block.
+    fn tweak(&self, sector: u128, offset: usize) -> u128 {
+        let mut tweak = sector.to_le_bytes();
+        self.tweak_cipher.encrypt_block(&mut tweak);
+
+        let mut tweak = u128::from_le_bytes(tweak);
+        for _ in 0..offset / BLOCK_SIZE {
+            tweak = mul_alpha(tweak);
+        }
+        tweak
+    }
+
+    // C = E1(P xor T) xor T, or the inverse.
+    fn block(&self, block: &mut [u8], tweak: u128, decrypt: bool) {
+        let tweak = tweak.to_le_bytes();
+        xor(block, &tweak);
+        if decrypt {
+            self.data_cipher.decrypt_block(block);
+        } else {
+            self.data_cipher.encrypt_block(block);
+        }
+        xor(block, &tweak);
+    }
+
+    fn process(&self, sector: u128, offset: usize, data: &mut [u8], decrypt: bool) -> Result<()> {
+        if data.len() < BLOCK_SIZE {
+            return Err(Error::SectorTooShort(data.len()));
+        }
+        if !offset.is_multiple_of(BLOCK_SIZE) {
+            return Err(Error::UnalignedOffset(offset));
+        }
+
+        let mut tweak = self.tweak(sector, offset);
+        let partial = data.len() % BLOCK_SIZE;
+        // With a partial block at the end the last full block is handled by the stealing below.
+        let full_blocks = data.len() / BLOCK_SIZE - (partial != 0) as usize;
+
+        let (full, tail) = data.split_at_mut(full_blocks * BLOCK_SIZE);
+        for block in full.chunks_exact_mut(BLOCK_SIZE) {
+            self.block(block, tweak, decrypt);
+            tweak = mul_alpha(tweak);
+        }
+        if partial == 0 {
+            return Ok(());
+        }
+
+        // The last full block and the partial block, with tweaks T_(m-1) and T_m.
+        let (last, stolen) = tail.split_at_mut(BLOCK_SIZE);
+        let next_tweak = mul_alpha(tweak);
+        // Decryption undoes the swap, so it uses the tweaks in the opposite order.
+        let (first, second) = if decrypt {
+            (next_tweak, tweak)
+        } else {
+            (tweak, next_tweak)
+        };
+
+        // The partial block takes the head of the processed block, and the rest of it is
+        // processed again together with the partial block.
+        self.block(last, first, decrypt);
+        last[..partial].swap_with_slice(stolen);
+        self.block(last, second, decrypt);
+        Ok(())
+    }
+}
+
+// Multiplication by alpha in GF(2^128), on the little-endian tweak with x^128 + x^7 + x^2 + x + 1.
+fn mul_alpha(tweak: u128) -> u128 {
+    let carry = tweak >> 127;
+    (tweak << 1) ^ (0x87 & carry.wrapping_neg())
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::hex::decode_hex;
+    use crate::rc5::Rc5;
+    use crate::testing::{bytes, rc5_with_key, Aes};
+
+    fn ciphers() -> (Rc5<u64>, Rc5<u64>) {
+        let key = bytes(0, 48);
+        (rc5_with_key(24, &key[..24]), rc5_with_key(24, &key[24..]))
+    }
+
+    // Vectors 1 and 2 of IEEE 1619 appendix B, and a partial last block from OpenSSL's AES-XTS.
+    #[test]
+    fn aes_ieee_1619() {
+        let vectors = [
+            (
+                [0; 16],
+                [0; 16],
+                0,
+                vec![0; 32],
+                "917CF69EBD68B2EC9B9FE9A3EADDA692CD43D2F59598ED858C02C2652FBF922E",
+            ),
+            (
+                [0x11; 16],
+                [0x22; 16],
+                0x3333333333,
+                vec![0x44; 32],
+                "C454185E6A16936E39334038ACEF838BFB186FFF7480ADC4289382ECD6D394F0",
+            ),
+        ];
+        for (data_key, tweak_key, sector, pt, ct) in vectors.iter() {
+            let (data_cipher, tweak_cipher) = (Aes::new(data_key), Aes::new(tweak_key));
+            let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+            let mut data = pt.clone();
+            xts.encrypt_sector(*sector, 0, &mut data).unwrap();
+            assert_eq!(data, decode_hex(ct).unwrap());
+            xts.decrypt_sector(*sector, 0, &mut data).unwrap();
+            assert_eq!(&data, pt);
+        }
+
+        let key = bytes(0, 32);
+        let (data_cipher, tweak_cipher) = (Aes::new(&key[..16]), Aes::new(&key[16..]));
+        let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+        let mut data = bytes(100, 17);
+        xts.encrypt_sector(12345, 0, &mut data).unwrap();
+        assert_eq!(
+            data,
+            decode_hex("F565B5C9AE6D8DDC257D0F0EC3CA34B50E").unwrap()
+        );
+        xts.decrypt_sector(12345, 0, &mut data).unwrap();
+        assert_eq!(data, bytes(100, 17));
+    }
+
+    // Known answers generated by `python3 tests/vectors/rc5ref.py xts`.
+    #[test]
+    fn known_answers() {
+        let (data_cipher, tweak_cipher) = ciphers();
+        let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+
+        let vectors = [
+            (0, 16, "0564109D231AF894D74A0FA879B879E8"),
+            (
+                1,
+                32,
+                "417E2C0B5C07DA8FA3282DF212C52C1C5F2686183D8FD124F205678AE8E058DD",
+            ),
+            (
+                0x123456789A,
+                37,
+                "9D5BBF0A2ECE72BBCD0F360AFA6B032C1738D1AA6931253A4A6B538730570ED87D7A3A4CA5",
+            ),
+            (7, 17, "BDEAD31CC1EBD8DA1B65A24574E220EFA2"),
+        ];
+
+        for (sector, len, ct) in vectors {
+            let mut data = bytes(0, len);
+            xts.encrypt_sector(sector, 0, &mut data).unwrap();
+            assert_eq!(data, decode_hex(ct).unwrap());
+
+            xts.decrypt_sector(sector, 0, &mut data).unwrap();
+            assert_eq!(data, bytes(0, len));
+        }
+    }
+
+    // Any block aligned part of a sector encrypts the same as it does within the whole sector.
+    #[test]
+    fn random_access() {
+        let (data_cipher, tweak_cipher) = ciphers();
+        let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+
+        for len in [512, 520] {
+            let mut sector = bytes(0, len);
+            xts.encrypt_sector(42, 0, &mut sector).unwrap();
+
+            let mut middle = bytes(0, len)[128..256].to_vec();
+            xts.encrypt_sector(42, 128, &mut middle).unwrap();
+            assert_eq!(middle, sector[128..256]);
+
+            let mut end = bytes(0, len)[496..].to_vec();
+            xts.encrypt_sector(42, 496, &mut end).unwrap();
+            assert_eq!(end, sector[496..]);
+
+            xts.decrypt_sector(42, 496, &mut end).unwrap();
+            assert_eq!(end, bytes(0, len)[496..]);
+        }
+    }
+
+    #[test]
+    fn sectors_differ() {
+        let (data_cipher, tweak_cipher) = ciphers();
+        let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+
+        let mut first = [0; 32];
+        let mut second = [0; 32];
+        xts.encrypt_sector(1, 0, &mut first).unwrap();
+        xts.encrypt_sector(2, 0, &mut second).unwrap();
+        assert_ne!(first, second);
+        // Equal blocks within a sector encrypt differently too.
+        assert_ne!(first[..16], first[16..]);
+    }
+
+    #[test]
+    fn invalid_input() {
+        let (data_cipher, tweak_cipher) = ciphers();
+        let xts = Xts::new(&data_cipher, &tweak_cipher).unwrap();
+
+        assert!(matches!(
+            xts.encrypt_sector(0, 0, &mut [0; 15]),
+            Err(Error::SectorTooShort(15))
+        ));
+        assert!(matches!(
+            xts.decrypt_sector(0, 0, &mut []),
+            Err(Error::SectorTooShort(0))
+        ));
+        assert!(matches!(
+            xts.encrypt_sector(0, 8, &mut [0; 16]),
+            Err(Error::UnalignedOffset(8))
+        ));
+
+        let mut rc5_32: Rc5<u32> = Rc5::new(12, 16).unwrap();
+        rc5_32.setup(&[0; 16]);
+        assert!(matches!(
+            Xts::new(&rc5_32, &rc5_32),
+            Err(Error::UnsupportedBlockSize(8))
+        ));
+    }
+}